@@ -0,0 +1,41 @@
+import { type Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import { Escrow } from "../target/types/escrow";
+
+export const getMintRegistryAddress = (programId: PublicKey): PublicKey =>
+  PublicKey.findProgramAddressSync([Buffer.from("mint_registry")], programId)[0];
+
+export type MintAllowlist = {
+  // `false` means `make_offer` accepts any mint, whatever `mints` holds.
+  enabled: boolean;
+  mints: Array<PublicKey>;
+};
+
+// Reads the allowlist state of a deployment.  A deployment that never created
+// the registry reports a disabled, empty list.
+export const fetchMintAllowlist = async (
+  program: Program<Escrow>
+): Promise<MintAllowlist> => {
+  const [configAddress, _configBump] = PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  );
+  const config = await program.account.config.fetchNullable(configAddress);
+  const registry = await program.account.mintRegistry.fetchNullable(
+    getMintRegistryAddress(program.programId)
+  );
+
+  return {
+    enabled: config?.mintAllowlistEnabled ?? false,
+    mints: registry?.mints ?? [],
+  };
+};
+
+// Value to pass as the `mintRegistry` account of `makeOffer`: the registry
+// while the allowlist is enabled, and `null` otherwise.
+export const mintRegistryForMakeOffer = async (
+  program: Program<Escrow>
+): Promise<PublicKey | null> => {
+  const { enabled } = await fetchMintAllowlist(program);
+  return enabled ? getMintRegistryAddress(program.programId) : null;
+};
@@ -0,0 +1,349 @@
+//! Measures the compute units, rent and account sizes of `make_offer` and
+//! `take_offer` with classic SPL Token and Token-2022 mints, running the SBF
+//! build of the program in the LiteSVM runtime of `escrow-sim`.
+//!
+//! Build the programs with `anchor build` first.
+
+use std::fmt::Write as _;
+
+use anchor_lang::{
+    prelude::Pubkey, solana_program::instruction::Instruction, system_program, AccountSerialize,
+    InstructionData, Space, ToAccountMetas,
+};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use escrow::{Config, Offer, ANCHOR_DISCRIMINATOR, CONFIG_SEED};
+use escrow_sim::{Account, Runtime};
+use spl_token_2022::{
+    solana_program::{program_option::COption, program_pack::Pack},
+    state::Mint,
+};
+
+const DECIMALS: u8 = 6;
+const TOKEN_A_OFFERED_AMOUNT: u64 = 1_000_000;
+const TOKEN_B_WANTED_AMOUNT: u64 = 2_000_000;
+const STARTING_LAMPORTS: u64 = 10_000_000_000;
+
+pub struct Measurement {
+    pub instruction: &'static str,
+    pub token_program: &'static str,
+    pub scenario: &'static str,
+    pub compute_units: u64,
+    /// Lamports locked in accounts the transaction created, by its signer.
+    pub rent: u64,
+    pub created_accounts: Vec<(&'static str, usize)>,
+}
+
+struct Bench {
+    runtime: Runtime,
+    mint_authority: Pubkey,
+    token_program: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+}
+
+impl Bench {
+    fn new(token_program: Pubkey) -> Self {
+        let mut runtime = Runtime::new();
+        let admin = Pubkey::new_unique();
+        let mint_authority = Pubkey::new_unique();
+        runtime.airdrop(&mint_authority, STARTING_LAMPORTS);
+
+        let (config, bump) = Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID);
+        let mut data = Vec::new();
+        Config {
+            admin,
+            paused: false,
+            mint_allowlist_enabled: false,
+            max_referral_fee_bps: 0,
+            bump,
+        }
+        .try_serialize(&mut data)
+        .unwrap();
+        set_rent_exempt(&mut runtime, config, data, escrow::ID);
+
+        let mut bench = Self {
+            runtime,
+            mint_authority,
+            token_program,
+            mint_a: Pubkey::default(),
+            mint_b: Pubkey::default(),
+        };
+        bench.mint_a = bench.create_mint();
+        bench.mint_b = bench.create_mint();
+        bench
+    }
+
+    /// Returns the compute units the transaction consumed.
+    fn send(&mut self, instructions: &[Instruction], signers: &[Pubkey]) -> u64 {
+        self.runtime
+            .process_transaction_metered(instructions, signers)
+            .unwrap_or_else(|error| panic!("Transaction failed: {error}"))
+    }
+
+    fn create_mint(&mut self) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        let mut data = vec![0; Mint::LEN];
+        Mint {
+            mint_authority: COption::Some(self.mint_authority),
+            supply: 0,
+            decimals: DECIMALS,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }
+        .pack_into_slice(&mut data);
+        set_rent_exempt(&mut self.runtime, mint, data, self.token_program);
+        mint
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+
+    fn create_ata(&mut self, owner: &Pubkey, mint: &Pubkey) -> Instruction {
+        spl_associated_token_account::instruction::create_associated_token_account(
+            &self.mint_authority,
+            owner,
+            mint,
+            &self.token_program,
+        )
+    }
+
+    /// A wallet holding `amount` of `mint` in its associated token account.
+    fn funded_wallet(&mut self, mint: Pubkey, amount: u64) -> Pubkey {
+        let wallet = Pubkey::new_unique();
+        self.runtime.airdrop(&wallet, STARTING_LAMPORTS);
+        let instructions = [
+            self.create_ata(&wallet, &mint),
+            spl_token_2022::instruction::mint_to(
+                &self.token_program,
+                &mint,
+                &self.ata(&wallet, &mint),
+                &self.mint_authority,
+                &[],
+                amount,
+            )
+            .unwrap(),
+        ];
+        self.send(&instructions, &[self.mint_authority]);
+        wallet
+    }
+
+    fn data_len(&self, address: &Pubkey) -> usize {
+        self.runtime
+            .account(address)
+            .map_or(0, |account| account.data.len())
+    }
+
+    fn rent(&self, len: usize) -> u64 {
+        self.runtime.rent().minimum_balance(len)
+    }
+
+    fn make_offer(&mut self, maker: &Pubkey, id: u64) -> (Pubkey, u64) {
+        let (offer, _) = Offer::pda(maker, id);
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::MakeOffer {
+                maker: *maker,
+                token_mint_a: self.mint_a,
+                token_mint_b: self.mint_b,
+                maker_token_account_a: self.ata(maker, &self.mint_a),
+                offer,
+                vault: self.ata(&offer, &self.mint_a),
+                associated_token_program: associated_token::ID,
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                config: Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0,
+                mint_registry: None,
+                price_feed: None,
+                nft_metadata: None,
+                rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::MakeOffer {
+                id,
+                token_a_offered_amount: TOKEN_A_OFFERED_AMOUNT,
+                token_b_wanted_amount: TOKEN_B_WANTED_AMOUNT,
+                other_payments: Vec::new(),
+                peg: None,
+            }
+            .data(),
+        };
+        let compute_units = self.send(&[instruction], &[*maker]);
+        (offer, compute_units)
+    }
+
+    fn take_offer(&mut self, taker: &Pubkey, maker: &Pubkey, offer: &Pubkey) -> u64 {
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::TakeOffer {
+                taker: *taker,
+                maker: *maker,
+                token_mint_a: self.mint_a,
+                token_mint_b: self.mint_b,
+                taker_token_account_a: self.ata(taker, &self.mint_a),
+                taker_token_account_b: self.ata(taker, &self.mint_b),
+                maker_token_account_b: self.ata(maker, &self.mint_b),
+                offer: *offer,
+                vault: self.ata(offer, &self.mint_a),
+                associated_token_program: associated_token::ID,
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                config: Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0,
+                fill_receipt: None,
+                referrer: None,
+                referrer_token_account_b: None,
+                price_feed: None,
+                nft_metadata: None,
+                rent_payer: None,
+                offer_rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::TakeOffer {
+                referral_fee_bps: 0,
+            }
+            .data(),
+        };
+        self.send(&[instruction], &[*taker])
+    }
+}
+
+fn set_rent_exempt(runtime: &mut Runtime, address: Pubkey, data: Vec<u8>, owner: Pubkey) {
+    let lamports = runtime.rent().minimum_balance(data.len());
+    runtime.set_account(
+        address,
+        Account {
+            lamports,
+            data,
+            owner,
+            executable: false,
+        },
+    );
+}
+
+fn measure(token_program: Pubkey, name: &'static str) -> Vec<Measurement> {
+    let mut bench = Bench::new(token_program);
+    let (mint_a, mint_b) = (bench.mint_a, bench.mint_b);
+    let maker = bench.funded_wallet(mint_a, 2 * TOKEN_A_OFFERED_AMOUNT);
+    let taker = bench.funded_wallet(mint_b, 2 * TOKEN_B_WANTED_AMOUNT);
+    let mut measurements = Vec::new();
+
+    let (offer, compute_units) = bench.make_offer(&maker, 1);
+    let offer_len = bench.data_len(&offer);
+    let vault_len = bench.data_len(&bench.ata(&offer, &mint_a));
+    measurements.push(Measurement {
+        instruction: "make_offer",
+        token_program: name,
+        scenario: "-",
+        compute_units,
+        rent: bench.rent(offer_len) + bench.rent(vault_len),
+        created_accounts: vec![("offer", offer_len), ("vault", vault_len)],
+    });
+
+    // The taker has no account for token A and the maker none for token B,
+    // so `take_offer` creates both.
+    let compute_units = bench.take_offer(&taker, &maker, &offer);
+    let taker_a_len = bench.data_len(&bench.ata(&taker, &mint_a));
+    let maker_b_len = bench.data_len(&bench.ata(&maker, &mint_b));
+    measurements.push(Measurement {
+        instruction: "take_offer",
+        token_program: name,
+        scenario: "creates both token accounts",
+        compute_units,
+        rent: bench.rent(taker_a_len) + bench.rent(maker_b_len),
+        created_accounts: vec![
+            ("taker_token_account_a", taker_a_len),
+            ("maker_token_account_b", maker_b_len),
+        ],
+    });
+
+    // Both token accounts exist now.
+    let (offer, _) = bench.make_offer(&maker, 2);
+    let compute_units = bench.take_offer(&taker, &maker, &offer);
+    measurements.push(Measurement {
+        instruction: "take_offer",
+        token_program: name,
+        scenario: "token accounts exist",
+        compute_units,
+        rent: 0,
+        created_accounts: Vec::new(),
+    });
+
+    // A fresh maker and taker, where only the maker's token B account is
+    // missing, isolates the cost of one `init_if_needed`.
+    let maker = bench.funded_wallet(mint_a, TOKEN_A_OFFERED_AMOUNT);
+    let taker = bench.funded_wallet(mint_b, TOKEN_B_WANTED_AMOUNT);
+    let create_taker_a = bench.create_ata(&taker, &mint_a);
+    let mint_authority = bench.mint_authority;
+    bench.send(&[create_taker_a], &[mint_authority]);
+    let (offer, _) = bench.make_offer(&maker, 1);
+    let compute_units = bench.take_offer(&taker, &maker, &offer);
+    let maker_b_len = bench.data_len(&bench.ata(&maker, &mint_b));
+    measurements.push(Measurement {
+        instruction: "take_offer",
+        token_program: name,
+        scenario: "creates maker_token_account_b",
+        compute_units,
+        rent: bench.rent(maker_b_len),
+        created_accounts: vec![("maker_token_account_b", maker_b_len)],
+    });
+
+    measurements
+}
+
+/// Every scenario, with classic SPL Token mints first, then Token-2022.
+pub fn measure_all() -> Vec<Measurement> {
+    let mut measurements = measure(spl_token::ID, "SPL Token");
+    measurements.extend(measure(spl_token_2022::ID, "Token-2022"));
+    measurements
+}
+
+pub fn report(measurements: &[Measurement]) -> String {
+    let mut report = String::new();
+    writeln!(report, "# Escrow benchmark").unwrap();
+    writeln!(report).unwrap();
+    writeln!(
+        report,
+        "Generated by `cargo run -p escrow-bench`, which runs the SBF build \
+         of the program in escrow-sim. Rent is what the signer locks in the \
+         accounts the instruction creates."
+    )
+    .unwrap();
+    writeln!(report).unwrap();
+    writeln!(
+        report,
+        "| Instruction | Token program | Scenario | Compute units | Rent (lamports) | Accounts created (bytes) |"
+    )
+    .unwrap();
+    writeln!(report, "|---|---|---|---:|---:|---|").unwrap();
+    for measurement in measurements {
+        let created = measurement
+            .created_accounts
+            .iter()
+            .map(|(name, len)| format!("{name} ({len})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            report,
+            "| `{}` | {} | {} | {} | {} | {} |",
+            measurement.instruction,
+            measurement.token_program,
+            measurement.scenario,
+            measurement.compute_units,
+            measurement.rent,
+            if created.is_empty() {
+                "-".to_string()
+            } else {
+                created
+            },
+        )
+        .unwrap();
+    }
+    writeln!(report).unwrap();
+    writeln!(
+        report,
+        "`Offer` is {} bytes.",
+        ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE
+    )
+    .unwrap();
+    report
+}
@@ -0,0 +1,24 @@
+//! Writes the benchmark report, after building the programs:
+//!
+//! ```sh
+//! anchor build
+//! cargo run -p escrow-bench
+//! ```
+//!
+//! The report goes to `crates/escrow-bench/report.md`, or the path given as
+//! the first argument. Commit it with changes to the program so reviewers see
+//! the cost; the `report` test fails while it is out of date.
+
+use std::{fs, path::PathBuf};
+
+fn main() {
+    let report_path = std::env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("report.md"));
+
+    let report = escrow_bench::report(&escrow_bench::measure_all());
+    print!("{report}");
+    fs::write(&report_path, report).expect("Failed to write the report");
+    println!("\nWrote {}", report_path.display());
+}
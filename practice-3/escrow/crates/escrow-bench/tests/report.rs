@@ -0,0 +1,41 @@
+//! The committed report matches what the program costs today.
+
+const REPORT: &str = include_str!("../report.md");
+
+#[test]
+fn committed_report_has_compute_units_next_to_rent() {
+    let header: Vec<_> = REPORT
+        .lines()
+        .find(|line| line.starts_with("| Instruction |"))
+        .expect("report.md has no table")
+        .split('|')
+        .map(str::trim)
+        .collect();
+    let column = header
+        .iter()
+        .position(|cell| *cell == "Compute units")
+        .expect("report.md has no compute units column");
+    assert_eq!(header[column + 1], "Rent (lamports)");
+
+    let rows: Vec<_> = REPORT
+        .lines()
+        .filter(|line| line.starts_with("| `"))
+        .collect();
+    assert!(!rows.is_empty(), "report.md has no measurements");
+    for row in rows {
+        let cells: Vec<_> = row.split('|').map(str::trim).collect();
+        assert!(
+            cells[column].parse::<u64>().is_ok_and(|units| units > 0),
+            "no compute units in `{row}`, run `cargo run -p escrow-bench`"
+        );
+    }
+}
+
+#[test]
+fn committed_report_is_up_to_date() {
+    let report = escrow_bench::report(&escrow_bench::measure_all());
+    assert_eq!(
+        REPORT, report,
+        "report.md is out of date, run `cargo run -p escrow-bench`"
+    );
+}
@@ -0,0 +1,57 @@
+//! Conversion between human-readable token amounts and base units.
+
+/// Parses `text`, such as `"1.5"`, into base units of a mint with `decimals`.
+pub fn parse_amount(text: &str, decimals: u8) -> Result<u64, String> {
+    let invalid = || format!("invalid amount: {text}");
+
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(invalid());
+    }
+    if !whole
+        .bytes()
+        .chain(fraction.bytes())
+        .all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    if fraction.len() > decimals as usize {
+        return Err(format!(
+            "{text} has more than {decimals} decimal places, the precision of the mint"
+        ));
+    }
+
+    let scale = 10u64
+        .checked_pow(decimals as u32)
+        .ok_or_else(|| format!("unsupported number of decimals: {decimals}"))?;
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| invalid())?
+    };
+    let fraction: u64 = if fraction.is_empty() {
+        0
+    } else {
+        let padded = format!("{fraction:0<width$}", width = decimals as usize);
+        padded.parse().map_err(|_| invalid())?
+    };
+
+    whole
+        .checked_mul(scale)
+        .and_then(|amount| amount.checked_add(fraction))
+        .ok_or_else(|| format!("{text} is too large"))
+}
+
+/// Formats base units of a mint with `decimals`, without trailing zeros.
+pub fn format_amount(amount: u64, decimals: u8) -> String {
+    let decimals = decimals as usize;
+    let digits = format!("{amount:0>width$}", width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{whole}.{fraction}")
+    }
+}
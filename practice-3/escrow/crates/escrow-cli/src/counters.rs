@@ -0,0 +1,168 @@
+//! Reading counter offers and building the counter offer instructions.
+
+use anchor_lang::{
+    prelude::Pubkey, solana_program::instruction::Instruction, system_program, AccountDeserialize,
+    InstructionData, ToAccountMetas,
+};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use escrow::{CounterOffer, Offer};
+use escrow_client::rpc::RpcClient;
+
+use crate::{
+    offers::{config_address, offer_rent_payer, vault_address},
+    Result,
+};
+
+pub fn fetch_counter_offer(rpc: &RpcClient, address: &Pubkey) -> Result<CounterOffer> {
+    let account = rpc
+        .get_account(address)?
+        .ok_or_else(|| format!("Counter offer {address} does not exist"))?;
+    if account.owner != escrow::ID {
+        return Err(format!("{address} is not owned by the escrow program").into());
+    }
+    Ok(CounterOffer::try_deserialize(&mut account.data.as_slice())
+        .map_err(|error| format!("{address} is not a counter offer: {error}"))?)
+}
+
+/// Escrows `token_b_amount` of `token_mint_b`, which must be one of the
+/// offer's payment mints, as a proposal to take the whole offer for it.
+pub fn make_counter_offer_instruction(
+    counter_party: &Pubkey,
+    offer_address: &Pubkey,
+    offer: &Offer,
+    token_mint_b: &Pubkey,
+    token_program: &Pubkey,
+    token_b_amount: u64,
+) -> Instruction {
+    let (counter_offer, _) = CounterOffer::pda(offer_address, counter_party);
+    Instruction {
+        program_id: escrow::ID,
+        accounts: escrow::accounts::MakeCounterOffer {
+            counter_party: *counter_party,
+            offer: *offer_address,
+            vault: vault_address(offer_address, offer, token_program),
+            token_mint_a: offer.token_mint_a,
+            token_mint_b: *token_mint_b,
+            counter_party_token_account_b: get_associated_token_address_with_program_id(
+                counter_party,
+                token_mint_b,
+                token_program,
+            ),
+            counter_offer,
+            counter_vault: counter_vault_address(&counter_offer, token_mint_b, token_program),
+            config: config_address(),
+            associated_token_program: associated_token::ID,
+            token_program: *token_program,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::MakeCounterOffer { token_b_amount }.data(),
+    }
+}
+
+pub fn accept_counter_offer_instruction(
+    counter_offer_address: &Pubkey,
+    counter_offer: &CounterOffer,
+    offer: &Offer,
+    token_program: &Pubkey,
+) -> Instruction {
+    let ata = |owner: &Pubkey, mint: &Pubkey| {
+        get_associated_token_address_with_program_id(owner, mint, token_program)
+    };
+    Instruction {
+        program_id: escrow::ID,
+        accounts: escrow::accounts::AcceptCounterOffer {
+            maker: offer.maker,
+            counter_party: counter_offer.counter_party,
+            token_mint_a: counter_offer.token_mint_a,
+            token_mint_b: counter_offer.token_mint_b,
+            counter_party_token_account_a: ata(
+                &counter_offer.counter_party,
+                &counter_offer.token_mint_a,
+            ),
+            maker_token_account_b: ata(&offer.maker, &counter_offer.token_mint_b),
+            offer: counter_offer.offer,
+            vault: vault_address(&counter_offer.offer, offer, token_program),
+            counter_offer: *counter_offer_address,
+            counter_vault: counter_vault_address(
+                counter_offer_address,
+                &counter_offer.token_mint_b,
+                token_program,
+            ),
+            config: config_address(),
+            associated_token_program: associated_token::ID,
+            token_program: *token_program,
+            system_program: system_program::ID,
+            offer_rent_payer: offer_rent_payer(offer),
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::AcceptCounterOffer {}.data(),
+    }
+}
+
+pub fn reject_counter_offer_instruction(
+    maker: &Pubkey,
+    counter_offer_address: &Pubkey,
+    counter_offer: &CounterOffer,
+    token_program: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: escrow::ID,
+        accounts: escrow::accounts::RejectCounterOffer {
+            maker: *maker,
+            counter_party: counter_offer.counter_party,
+            token_mint_b: counter_offer.token_mint_b,
+            counter_party_token_account_b: get_associated_token_address_with_program_id(
+                &counter_offer.counter_party,
+                &counter_offer.token_mint_b,
+                token_program,
+            ),
+            offer: counter_offer.offer,
+            counter_offer: *counter_offer_address,
+            counter_vault: counter_vault_address(
+                counter_offer_address,
+                &counter_offer.token_mint_b,
+                token_program,
+            ),
+            token_program: *token_program,
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::RejectCounterOffer {}.data(),
+    }
+}
+
+pub fn withdraw_counter_offer_instruction(
+    counter_offer_address: &Pubkey,
+    counter_offer: &CounterOffer,
+    token_program: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: escrow::ID,
+        accounts: escrow::accounts::WithdrawCounterOffer {
+            counter_party: counter_offer.counter_party,
+            token_mint_b: counter_offer.token_mint_b,
+            counter_party_token_account_b: get_associated_token_address_with_program_id(
+                &counter_offer.counter_party,
+                &counter_offer.token_mint_b,
+                token_program,
+            ),
+            counter_offer: *counter_offer_address,
+            counter_vault: counter_vault_address(
+                counter_offer_address,
+                &counter_offer.token_mint_b,
+                token_program,
+            ),
+            token_program: *token_program,
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::WithdrawCounterOffer {}.data(),
+    }
+}
+
+fn counter_vault_address(
+    counter_offer_address: &Pubkey,
+    token_mint_b: &Pubkey,
+    token_program: &Pubkey,
+) -> Pubkey {
+    get_associated_token_address_with_program_id(counter_offer_address, token_mint_b, token_program)
+}
@@ -0,0 +1,41 @@
+//! Loads the signing keypair the same way as the practice binaries: a JSON
+//! array of secret key bytes, from a file or the `SECRET_KEY` variable.
+
+use std::{fs, path::Path};
+
+use solana_sdk::signature::{Keypair, SeedDerivable};
+
+use crate::Result;
+
+/// Reads the keypair from `path` if given, and from `SECRET_KEY` otherwise.
+/// `SECRET_KEY` may also be set in a `.env` file.
+pub fn load_keypair(path: Option<&Path>) -> Result<Keypair> {
+    let secret_key = match path {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|error| format!("Failed to read {}: {error}", path.display()))?,
+        None => {
+            dotenvy::dotenv().ok();
+            std::env::var("SECRET_KEY")
+                .map_err(|_| "SECRET_KEY must be set when no --keypair is given")?
+        }
+    };
+
+    let secret_key = parse_secret_key(&secret_key)?;
+    Keypair::from_seed(&secret_key)
+        .map_err(|error| format!("Failed to create keypair from seed: {error}").into())
+}
+
+pub fn parse_secret_key(secret_key: &str) -> Result<Vec<u8>> {
+    let value = serde_json::from_str::<serde_json::Value>(secret_key)
+        .map_err(|error| format!("Failed to parse secret key: {error}"))?;
+    value
+        .as_array()
+        .ok_or("Secret key is not an array")?
+        .iter()
+        .map(|byte| {
+            byte.as_u64()
+                .and_then(|byte| u8::try_from(byte).ok())
+                .ok_or_else(|| "Secret key contains a value that is not a byte".into())
+        })
+        .collect()
+}
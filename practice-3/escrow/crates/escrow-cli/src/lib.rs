@@ -0,0 +1,22 @@
+//! Library half of `escrow-cli`, so the parsing helpers can be tested.
+
+pub mod amount;
+pub mod counters;
+pub mod keypair;
+pub mod offers;
+pub mod receipts;
+pub mod transaction;
+
+pub use escrow_client::Result;
+
+/// Expands the cluster monikers the Solana CLI accepts into an RPC URL, and
+/// passes anything else through unchanged.
+pub fn cluster_url(url_or_moniker: &str) -> &str {
+    match url_or_moniker {
+        "localnet" | "l" => "http://127.0.0.1:8899",
+        "devnet" | "d" => "https://api.devnet.solana.com",
+        "testnet" | "t" => "https://api.testnet.solana.com",
+        "mainnet-beta" | "m" => "https://api.mainnet-beta.solana.com",
+        url => url,
+    }
+}
@@ -0,0 +1,558 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs::File,
+    io,
+    path::PathBuf,
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anchor_lang::prelude::Pubkey;
+use clap::{Parser, Subcommand};
+use escrow::{CounterOffer, FillReceipt, Offer, PaymentOption, PegTerms};
+use escrow_cli::{
+    amount::{format_amount, parse_amount},
+    cluster_url,
+    counters::{
+        accept_counter_offer_instruction, fetch_counter_offer, make_counter_offer_instruction,
+        reject_counter_offer_instruction, withdraw_counter_offer_instruction,
+    },
+    keypair::load_keypair,
+    offers::{
+        cancel_offer_instruction, fetch_config, fetch_mint, fetch_nft_metadata, fetch_offer,
+        fetch_offers, fetch_token_balance, make_offer_instruction, migrate_offer_instruction,
+        mint_registry_address, offer_rent_payer, royalty_accounts, take_offer_instruction,
+        vault_address, FetchedOffer, MintInfo, OfferFilter, OfferTerms, Peg, Referral,
+    },
+    receipts::{close_fill_receipt_instruction, fetch_fill_receipts, write_csv},
+    transaction::send_and_confirm,
+    Result,
+};
+use escrow_client::rpc::RpcClient;
+use solana_sdk::signature::Signer;
+
+/// Make, take, cancel and inspect escrow offers.
+#[derive(Parser)]
+struct Cli {
+    /// RPC URL, or one of localnet, devnet, testnet and mainnet-beta.
+    #[arg(
+        long,
+        short = 'u',
+        env = "RPC_URL",
+        default_value = "localnet",
+        global = true
+    )]
+    url: String,
+
+    /// Keypair file holding a JSON array of secret key bytes. Without it, the
+    /// keypair is read from the SECRET_KEY environment variable.
+    #[arg(long, short = 'k', global = true)]
+    keypair: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Deposits token A into a new offer that asks for token B in return.
+    Make {
+        #[arg(long)]
+        mint_a: Pubkey,
+        /// Amount of token A to offer, in whole tokens, such as 1.5.
+        #[arg(long)]
+        amount_a: String,
+        #[arg(long)]
+        mint_b: Pubkey,
+        /// Amount of token B wanted, in whole tokens. The least accepted when
+        /// pegged to a price feed.
+        #[arg(long)]
+        amount_b: String,
+        /// Another mint accepted instead of token B, with the amount wanted
+        /// in whole tokens, as MINT:AMOUNT. May be repeated.
+        #[arg(long, value_name = "MINT:AMOUNT", value_parser = parse_payment)]
+        also_accept: Vec<(Pubkey, String)>,
+        /// Price feed of token A in token B that prices the offer when taken.
+        #[arg(long)]
+        peg_feed: Option<Pubkey>,
+        /// With --peg-feed, basis points added to the feed price, such as
+        /// -100 to sell 1% below it.
+        #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+        peg_spread_bps: i16,
+        /// With --peg-feed, oldest feed price in seconds the offer can be
+        /// taken at.
+        #[arg(long, default_value_t = 60)]
+        peg_max_staleness: u32,
+        /// With --peg-feed, widest feed confidence interval the offer can be
+        /// taken at, in basis points of the price.
+        #[arg(long, default_value_t = 100)]
+        peg_max_confidence_bps: u16,
+        /// Lists token A as an NFT: it must be in a verified Metaplex
+        /// collection, and its creators get their royalties when sold.
+        #[arg(long)]
+        nft: bool,
+        /// Offer id, unique per maker. Defaults to the current time.
+        #[arg(long)]
+        id: Option<u64>,
+    },
+    /// Pays the wanted token B and receives the offered token A.
+    Take {
+        offer: Pubkey,
+        /// Mint to pay with, if the offer accepts several. Defaults to the
+        /// first one.
+        #[arg(long)]
+        pay_with: Option<Pubkey>,
+        /// Also pays for a fill receipt that records the trade on chain.
+        #[arg(long)]
+        receipt: bool,
+        /// Front-end to pay a referral fee to, on top of the wanted amount.
+        #[arg(long, requires = "referral_fee_bps")]
+        referrer: Option<Pubkey>,
+        /// Referral fee in basis points of the wanted amount.
+        #[arg(long, requires = "referrer")]
+        referral_fee_bps: Option<u16>,
+    },
+    /// Closes one of your offers and returns token A.
+    Cancel { offer: Pubkey },
+    /// Rewrites one of your offers stored in an old layout into the current
+    /// one, paying for its larger account, so that it can be taken.
+    Migrate { offer: Pubkey },
+    /// Escrows token B as a proposal to take the whole offer for another
+    /// amount, which the maker can accept or reject.
+    Counter {
+        offer: Pubkey,
+        /// Amount of token B proposed, in whole tokens.
+        #[arg(long)]
+        amount: String,
+        /// Mint to pay with, if the offer accepts several. Defaults to the
+        /// first one.
+        #[arg(long)]
+        pay_with: Option<Pubkey>,
+    },
+    /// Settles a counter offer on one of your offers.
+    AcceptCounter { counter_offer: Pubkey },
+    /// Refunds a counter offer on one of your offers.
+    RejectCounter { counter_offer: Pubkey },
+    /// Takes back one of your counter offers.
+    WithdrawCounter { counter_offer: Pubkey },
+    /// Prints one offer.
+    Show { offer: Pubkey },
+    /// Prints the open offers, optionally filtered.
+    List {
+        #[arg(long)]
+        maker: Option<Pubkey>,
+        #[arg(long)]
+        mint_a: Option<Pubkey>,
+        #[arg(long)]
+        mint_b: Option<Pubkey>,
+        /// Only NFT listings in this verified collection.
+        #[arg(long)]
+        collection: Option<Pubkey>,
+    },
+    /// Exports fill receipts as CSV.
+    Receipts {
+        #[arg(long)]
+        taker: Option<Pubkey>,
+        /// File to write to instead of standard output.
+        #[arg(long, short = 'o')]
+        output: Option<PathBuf>,
+    },
+    /// Closes one of your fill receipts and reclaims its rent.
+    CloseReceipt { receipt: Pubkey },
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(error) = run(cli) {
+        eprintln!("Error: {error}");
+        process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let rpc = RpcClient::new(cluster_url(&cli.url));
+
+    match cli.command {
+        Command::Make {
+            mint_a,
+            amount_a,
+            mint_b,
+            amount_b,
+            also_accept,
+            peg_feed,
+            peg_spread_bps,
+            peg_max_staleness,
+            peg_max_confidence_bps,
+            nft,
+            id,
+        } => {
+            let maker = load_keypair(cli.keypair.as_deref())?;
+            let mint_a = fetch_mint(&rpc, &mint_a)?;
+            let mint_b = fetch_mint(&rpc, &mint_b)?;
+            if mint_a.token_program != mint_b.token_program {
+                return Err("Both mints must belong to the same token program".into());
+            }
+            let token_a_offered_amount = parse_amount(&amount_a, mint_a.decimals)?;
+            let token_b_wanted_amount = parse_amount(&amount_b, mint_b.decimals)?;
+            let mut other_payments = Vec::new();
+            for (mint, amount) in also_accept {
+                let mint = fetch_mint(&rpc, &mint)?;
+                if mint.token_program != mint_a.token_program {
+                    return Err(format!(
+                        "{} belongs to another token program than token A",
+                        mint.address
+                    )
+                    .into());
+                }
+                other_payments.push(PaymentOption {
+                    mint: mint.address,
+                    amount: parse_amount(&amount, mint.decimals)?,
+                });
+            }
+            let id = match id {
+                Some(id) => id,
+                None => SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64,
+            };
+            let mint_registry = fetch_config(&rpc)?
+                .mint_allowlist_enabled
+                .then(mint_registry_address);
+
+            let instruction = make_offer_instruction(
+                &maker.pubkey(),
+                id,
+                &mint_a,
+                &mint_b,
+                OfferTerms {
+                    token_a_offered_amount,
+                    token_b_wanted_amount,
+                    other_payments,
+                    peg: peg_feed.map(|price_feed| Peg {
+                        price_feed,
+                        terms: PegTerms {
+                            spread_bps: peg_spread_bps,
+                            max_staleness_secs: peg_max_staleness,
+                            max_confidence_bps: peg_max_confidence_bps,
+                        },
+                    }),
+                    nft_listing: nft,
+                },
+                mint_registry,
+            );
+            let signature = send_and_confirm(&rpc, &[instruction], &maker)?;
+
+            println!("Offer: {}", Offer::pda(&maker.pubkey(), id).0);
+            println!("Id: {id}");
+            println!("Transaction signature: {signature}");
+        }
+        Command::Take {
+            offer: address,
+            pay_with,
+            receipt,
+            referrer,
+            referral_fee_bps,
+        } => {
+            let taker = load_keypair(cli.keypair.as_deref())?;
+            let fetched = fetch_offer(&rpc, &address)?;
+            require_migrated(&fetched)?;
+            let token_program = fetch_mint(&rpc, &fetched.offer.token_mint_a)?.token_program;
+            let token_mint_b = pay_with.unwrap_or(fetched.offer.token_mint_b);
+            if fetched.offer.wanted_amount(&token_mint_b).is_none() {
+                let accepted: Vec<String> = fetched
+                    .offer
+                    .payment_options()
+                    .map(|payment| payment.mint.to_string())
+                    .collect();
+                return Err(format!(
+                    "The offer does not accept {token_mint_b}, only {}",
+                    accepted.join(", ")
+                )
+                .into());
+            }
+
+            let mut instructions = Vec::new();
+            let mut take = take_offer_instruction(
+                &taker.pubkey(),
+                &address,
+                &fetched.offer,
+                &token_mint_b,
+                &token_program,
+                receipt,
+                referrer
+                    .zip(referral_fee_bps)
+                    .map(|(referrer, fee_bps)| Referral { referrer, fee_bps }),
+            );
+            if fetched.offer.nft_collection.is_some() {
+                let metadata = fetch_nft_metadata(&rpc, &fetched.offer.token_mint_a)?;
+                let (create_accounts, royalty_accounts) =
+                    royalty_accounts(&taker.pubkey(), &metadata, &token_mint_b, &token_program);
+                instructions.extend(create_accounts);
+                take.accounts.extend(royalty_accounts);
+            }
+            instructions.push(take);
+            let signature = send_and_confirm(&rpc, &instructions, &taker)?;
+            if receipt {
+                println!("Receipt: {}", FillReceipt::pda(&address, &taker.pubkey()).0);
+            }
+            println!("Transaction signature: {signature}");
+        }
+        Command::Cancel { offer: address } => {
+            let maker = load_keypair(cli.keypair.as_deref())?;
+            let fetched = fetch_offer(&rpc, &address)?;
+            if fetched.offer.maker != maker.pubkey() {
+                return Err(format!("Only the maker {} can cancel", fetched.offer.maker).into());
+            }
+            let token_program = fetch_mint(&rpc, &fetched.offer.token_mint_a)?.token_program;
+
+            let mut instructions = Vec::new();
+            if fetched.needs_migration {
+                instructions.push(migrate_offer_instruction(&address, &fetched.offer));
+            }
+            instructions.push(cancel_offer_instruction(
+                &address,
+                &fetched.offer,
+                &token_program,
+            ));
+            let signature = send_and_confirm(&rpc, &instructions, &maker)?;
+            println!("Transaction signature: {signature}");
+        }
+        Command::Migrate { offer: address } => {
+            let maker = load_keypair(cli.keypair.as_deref())?;
+            let fetched = fetch_offer(&rpc, &address)?;
+            if fetched.offer.maker != maker.pubkey() {
+                return Err(format!("Only the maker {} can migrate", fetched.offer.maker).into());
+            }
+            if !fetched.needs_migration {
+                return Err("The offer is already stored in the current layout".into());
+            }
+            let instruction = migrate_offer_instruction(&address, &fetched.offer);
+            let signature = send_and_confirm(&rpc, &[instruction], &maker)?;
+            println!("Transaction signature: {signature}");
+        }
+        Command::Counter {
+            offer: address,
+            amount,
+            pay_with,
+        } => {
+            let counter_party = load_keypair(cli.keypair.as_deref())?;
+            let fetched = fetch_offer(&rpc, &address)?;
+            require_migrated(&fetched)?;
+            let token_mint_b = pay_with.unwrap_or(fetched.offer.token_mint_b);
+            if fetched.offer.wanted_amount(&token_mint_b).is_none() {
+                return Err(format!("The offer does not accept {token_mint_b}").into());
+            }
+            let mint_b = fetch_mint(&rpc, &token_mint_b)?;
+
+            let instruction = make_counter_offer_instruction(
+                &counter_party.pubkey(),
+                &address,
+                &fetched.offer,
+                &token_mint_b,
+                &mint_b.token_program,
+                parse_amount(&amount, mint_b.decimals)?,
+            );
+            let signature = send_and_confirm(&rpc, &[instruction], &counter_party)?;
+            println!(
+                "Counter offer: {}",
+                CounterOffer::pda(&address, &counter_party.pubkey()).0
+            );
+            println!("Transaction signature: {signature}");
+        }
+        Command::AcceptCounter {
+            counter_offer: address,
+        } => {
+            let maker = load_keypair(cli.keypair.as_deref())?;
+            let counter_offer = fetch_counter_offer(&rpc, &address)?;
+            let fetched = fetch_offer(&rpc, &counter_offer.offer)?;
+            if fetched.offer.maker != maker.pubkey() {
+                return Err(format!("Only the maker {} can accept", fetched.offer.maker).into());
+            }
+            let token_program = fetch_mint(&rpc, &counter_offer.token_mint_a)?.token_program;
+
+            let mut instructions = Vec::new();
+            if fetched.needs_migration {
+                instructions.push(migrate_offer_instruction(
+                    &counter_offer.offer,
+                    &fetched.offer,
+                ));
+            }
+            instructions.push(accept_counter_offer_instruction(
+                &address,
+                &counter_offer,
+                &fetched.offer,
+                &token_program,
+            ));
+            let signature = send_and_confirm(&rpc, &instructions, &maker)?;
+            println!("Transaction signature: {signature}");
+        }
+        Command::RejectCounter {
+            counter_offer: address,
+        } => {
+            let maker = load_keypair(cli.keypair.as_deref())?;
+            let counter_offer = fetch_counter_offer(&rpc, &address)?;
+            let fetched = fetch_offer(&rpc, &counter_offer.offer)?;
+            if fetched.offer.maker != maker.pubkey() {
+                return Err(format!("Only the maker {} can reject", fetched.offer.maker).into());
+            }
+            let token_program = fetch_mint(&rpc, &counter_offer.token_mint_b)?.token_program;
+
+            let mut instructions = Vec::new();
+            if fetched.needs_migration {
+                instructions.push(migrate_offer_instruction(
+                    &counter_offer.offer,
+                    &fetched.offer,
+                ));
+            }
+            instructions.push(reject_counter_offer_instruction(
+                &maker.pubkey(),
+                &address,
+                &counter_offer,
+                &token_program,
+            ));
+            let signature = send_and_confirm(&rpc, &instructions, &maker)?;
+            println!("Transaction signature: {signature}");
+        }
+        Command::WithdrawCounter {
+            counter_offer: address,
+        } => {
+            let counter_party = load_keypair(cli.keypair.as_deref())?;
+            let counter_offer = fetch_counter_offer(&rpc, &address)?;
+            if counter_offer.counter_party != counter_party.pubkey() {
+                return Err(format!(
+                    "Only the counter party {} can withdraw",
+                    counter_offer.counter_party
+                )
+                .into());
+            }
+            let token_program = fetch_mint(&rpc, &counter_offer.token_mint_b)?.token_program;
+            let instruction =
+                withdraw_counter_offer_instruction(&address, &counter_offer, &token_program);
+            let signature = send_and_confirm(&rpc, &[instruction], &counter_party)?;
+            println!("Transaction signature: {signature}");
+        }
+        Command::Show { offer: address } => {
+            let fetched = fetch_offer(&rpc, &address)?;
+            let mut mints = HashMap::new();
+            print_offer(&rpc, &mut mints, &address, &fetched.offer)?;
+            if fetched.needs_migration {
+                println!(
+                    "  Stored in an old layout; its maker must migrate it before it can be taken."
+                );
+            }
+        }
+        Command::List {
+            maker,
+            mint_a,
+            mint_b,
+            collection,
+        } => {
+            let filter = OfferFilter {
+                maker,
+                token_mint_a: mint_a,
+                token_mint_b: mint_b,
+                collection,
+            };
+            let mut offers = fetch_offers(&rpc, &filter)?;
+            offers.sort_by_key(|(_, offer)| (offer.maker, offer.id));
+
+            let mut mints = HashMap::new();
+            for (address, offer) in &offers {
+                print_offer(&rpc, &mut mints, address, offer)?;
+            }
+            println!("{} offer(s)", offers.len());
+        }
+        Command::Receipts { taker, output } => {
+            let receipts = fetch_fill_receipts(&rpc, taker.as_ref())?;
+            match output {
+                Some(path) => {
+                    write_csv(File::create(&path)?, &receipts)?;
+                    println!("Wrote {} receipt(s) to {}", receipts.len(), path.display());
+                }
+                None => write_csv(io::stdout().lock(), &receipts)?,
+            }
+        }
+        Command::CloseReceipt { receipt } => {
+            let taker = load_keypair(cli.keypair.as_deref())?;
+            let instruction = close_fill_receipt_instruction(&taker.pubkey(), &receipt);
+            let signature = send_and_confirm(&rpc, &[instruction], &taker)?;
+            println!("Transaction signature: {signature}");
+        }
+    }
+    Ok(())
+}
+
+/// Only the maker can pay to migrate an offer, so others wait for it.
+fn require_migrated(fetched: &FetchedOffer) -> Result<()> {
+    if fetched.needs_migration {
+        return Err(format!(
+            "The offer is stored in an old layout; its maker {} must migrate it first",
+            fetched.offer.maker
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn print_offer(
+    rpc: &RpcClient,
+    mints: &mut HashMap<Pubkey, MintInfo>,
+    address: &Pubkey,
+    offer: &Offer,
+) -> Result<()> {
+    let payments: Vec<PaymentOption> = offer.payment_options().collect();
+    for mint in std::iter::once(offer.token_mint_a).chain(payments.iter().map(|p| p.mint)) {
+        if let Entry::Vacant(entry) = mints.entry(mint) {
+            entry.insert(fetch_mint(rpc, &mint)?);
+        }
+    }
+    let mint_a = &mints[&offer.token_mint_a];
+
+    let vault = vault_address(address, offer, &mint_a.token_program);
+    let offered = match fetch_token_balance(rpc, &vault)? {
+        Some(amount) => format_amount(amount, mint_a.decimals),
+        None => "unknown, the vault is missing".to_string(),
+    };
+
+    println!("Offer {address}");
+    println!("  Maker: {}", offer.maker);
+    println!("  Id: {}", offer.id);
+    println!("  Offered: {offered} of {}", offer.token_mint_a);
+    for (index, payment) in payments.iter().enumerate() {
+        println!(
+            "  {}: {} of {}",
+            match index {
+                0 if offer.peg.is_some() => "Wanted at least",
+                0 => "Wanted",
+                _ => "Or",
+            },
+            format_amount(payment.amount, mints[&payment.mint].decimals),
+            payment.mint
+        );
+    }
+    if let Some(collection) = &offer.nft_collection {
+        println!("  NFT collection: {collection}");
+    }
+    if let Some(peg) = &offer.peg {
+        println!(
+            "  Pegged to: {}, {:+} bps, at most {}s old and {} bps wide",
+            peg.price_feed,
+            peg.terms.spread_bps,
+            peg.terms.max_staleness_secs,
+            peg.terms.max_confidence_bps
+        );
+    }
+    if let Some(rent_payer) = offer_rent_payer(offer) {
+        println!("  Rent sponsored by: {rent_payer}");
+    }
+    Ok(())
+}
+
+/// Splits `MINT:AMOUNT`. The amount is parsed once the mint decimals are known.
+fn parse_payment(text: &str) -> std::result::Result<(Pubkey, String), String> {
+    let (mint, amount) = text
+        .split_once(':')
+        .ok_or_else(|| format!("expected MINT:AMOUNT, got {text}"))?;
+    let mint = mint
+        .parse()
+        .map_err(|error| format!("invalid mint {mint}: {error}"))?;
+    Ok((mint, amount.to_string()))
+}
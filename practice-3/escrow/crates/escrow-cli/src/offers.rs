@@ -0,0 +1,347 @@
+//! Reading escrow accounts and building escrow instructions.
+
+use anchor_lang::{
+    prelude::{AccountMeta, Pubkey},
+    solana_program::instruction::Instruction,
+    system_program, AccountDeserialize, Discriminator, InstructionData, Space, ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::{
+        self, get_associated_token_address_with_program_id,
+        spl_associated_token_account::instruction::create_associated_token_account_idempotent,
+    },
+    token_interface::{Mint, TokenAccount},
+};
+use escrow::{
+    Config, FillReceipt, NftMetadata, Offer, PaymentOption, PegTerms, ANCHOR_DISCRIMINATOR,
+    CONFIG_SEED, MINT_REGISTRY_SEED,
+};
+use escrow_client::rpc::RpcClient;
+
+use crate::Result;
+
+pub struct MintInfo {
+    pub address: Pubkey,
+    pub token_program: Pubkey,
+    pub decimals: u8,
+}
+
+pub fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0
+}
+
+pub fn mint_registry_address() -> Pubkey {
+    Pubkey::find_program_address(&[MINT_REGISTRY_SEED], &escrow::ID).0
+}
+
+pub fn vault_address(offer_address: &Pubkey, offer: &Offer, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(offer_address, &offer.token_mint_a, token_program)
+}
+
+/// Where the rent of a sponsored offer goes back to when it closes. `None`
+/// when the maker paid it.
+pub fn offer_rent_payer(offer: &Offer) -> Option<Pubkey> {
+    (offer.rent_payer != offer.maker).then_some(offer.rent_payer)
+}
+
+pub fn fetch_mint(rpc: &RpcClient, address: &Pubkey) -> Result<MintInfo> {
+    let account = rpc
+        .get_account(address)?
+        .ok_or_else(|| format!("Mint {address} does not exist"))?;
+    let mint = Mint::try_deserialize(&mut account.data.as_slice())
+        .map_err(|error| format!("{address} is not a mint: {error}"))?;
+    Ok(MintInfo {
+        address: *address,
+        token_program: account.owner,
+        decimals: mint.decimals,
+    })
+}
+
+/// Balance of a token account, or `None` if it does not exist.
+pub fn fetch_token_balance(rpc: &RpcClient, address: &Pubkey) -> Result<Option<u64>> {
+    let Some(account) = rpc.get_account(address)? else {
+        return Ok(None);
+    };
+    let token_account = TokenAccount::try_deserialize(&mut account.data.as_slice())
+        .map_err(|error| format!("{address} is not a token account: {error}"))?;
+    Ok(Some(token_account.amount))
+}
+
+pub fn fetch_config(rpc: &RpcClient) -> Result<Config> {
+    let address = config_address();
+    let account = rpc
+        .get_account(&address)?
+        .ok_or("The escrow config is not initialized")?;
+    Ok(Config::try_deserialize(&mut account.data.as_slice())?)
+}
+
+pub struct Referral {
+    pub referrer: Pubkey,
+    pub fee_bps: u16,
+}
+
+/// Prices token B from `price_feed` instead of a fixed amount.
+pub struct Peg {
+    pub price_feed: Pubkey,
+    pub terms: PegTerms,
+}
+
+/// What a new offer gives and what it accepts in return. With a `peg`,
+/// `token_b_wanted_amount` is the floor. With `nft_listing`, token A must be
+/// an NFT in a verified collection, whose creators get royalties.
+pub struct OfferTerms {
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    pub other_payments: Vec<PaymentOption>,
+    pub peg: Option<Peg>,
+    pub nft_listing: bool,
+}
+
+pub struct FetchedOffer {
+    pub offer: Offer,
+    /// Stored in an older layout, so it needs `migrate_offer`, paid by the
+    /// maker, before the program accepts it in `take_offer` or `cancel_offer`.
+    pub needs_migration: bool,
+}
+
+/// Fetches an offer in any layout the program has used.
+pub fn fetch_offer(rpc: &RpcClient, address: &Pubkey) -> Result<FetchedOffer> {
+    let account = rpc
+        .get_account(address)?
+        .ok_or_else(|| format!("Offer {address} does not exist"))?;
+    if account.owner != escrow::ID {
+        return Err(format!("{address} is not owned by the escrow program").into());
+    }
+    let offer = Offer::try_from_versioned(&account.data)
+        .map_err(|error| format!("{address} is not an offer: {error}"))?;
+    Ok(FetchedOffer {
+        offer,
+        needs_migration: account.data.len() != ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE,
+    })
+}
+
+#[derive(Default)]
+pub struct OfferFilter {
+    pub maker: Option<Pubkey>,
+    pub token_mint_a: Option<Pubkey>,
+    pub token_mint_b: Option<Pubkey>,
+    /// Only NFT listings in this collection.
+    pub collection: Option<Pubkey>,
+}
+
+impl OfferFilter {
+    fn matches(&self, offer: &Offer) -> bool {
+        self.maker.is_none_or(|maker| offer.maker == maker)
+            && self
+                .token_mint_a
+                .is_none_or(|mint| offer.token_mint_a == mint)
+            && self
+                .token_mint_b
+                .is_none_or(|mint| offer.wanted_amount(&mint).is_some())
+            && self
+                .collection
+                .is_none_or(|collection| offer.nft_collection == Some(collection))
+    }
+}
+
+/// All live offers matching `filter`. Filtering happens after decoding, as
+/// the field offsets differ between offer layouts.
+pub fn fetch_offers(rpc: &RpcClient, filter: &OfferFilter) -> Result<Vec<(Pubkey, Offer)>> {
+    let (_, accounts) = rpc.get_program_accounts(&escrow::ID, Offer::DISCRIMINATOR)?;
+    Ok(accounts
+        .into_iter()
+        .filter_map(|account| {
+            let offer = Offer::try_from_versioned(&account.data).ok()?;
+            filter.matches(&offer).then_some((account.pubkey, offer))
+        })
+        .collect())
+}
+
+pub fn make_offer_instruction(
+    maker: &Pubkey,
+    id: u64,
+    mint_a: &MintInfo,
+    mint_b: &MintInfo,
+    terms: OfferTerms,
+    mint_registry: Option<Pubkey>,
+) -> Instruction {
+    let (offer, _) = Offer::pda(maker, id);
+    let token_program = mint_a.token_program;
+    Instruction {
+        program_id: escrow::ID,
+        accounts: escrow::accounts::MakeOffer {
+            maker: *maker,
+            token_mint_a: mint_a.address,
+            token_mint_b: mint_b.address,
+            maker_token_account_a: get_associated_token_address_with_program_id(
+                maker,
+                &mint_a.address,
+                &token_program,
+            ),
+            offer,
+            vault: get_associated_token_address_with_program_id(
+                &offer,
+                &mint_a.address,
+                &token_program,
+            ),
+            config: config_address(),
+            mint_registry,
+            associated_token_program: associated_token::ID,
+            token_program,
+            system_program: system_program::ID,
+            price_feed: terms.peg.as_ref().map(|peg| peg.price_feed),
+            nft_metadata: terms
+                .nft_listing
+                .then(|| NftMetadata::address(&mint_a.address)),
+            rent_payer: None,
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::MakeOffer {
+            id,
+            token_a_offered_amount: terms.token_a_offered_amount,
+            token_b_wanted_amount: terms.token_b_wanted_amount,
+            other_payments: terms.other_payments,
+            peg: terms.peg.map(|peg| peg.terms),
+        }
+        .data(),
+    }
+}
+
+/// Pays with `token_mint_b`, which must be one of the offer's payment mints.
+/// With `save_fill_receipt`, the taker also pays for a `FillReceipt` that
+/// outlives the offer. With a `referral`, the taker pays the referrer its fee
+/// in basis points of the wanted amount on top.
+pub fn take_offer_instruction(
+    taker: &Pubkey,
+    offer_address: &Pubkey,
+    offer: &Offer,
+    token_mint_b: &Pubkey,
+    token_program: &Pubkey,
+    save_fill_receipt: bool,
+    referral: Option<Referral>,
+) -> Instruction {
+    let ata = |owner: &Pubkey, mint: &Pubkey| {
+        get_associated_token_address_with_program_id(owner, mint, token_program)
+    };
+    Instruction {
+        program_id: escrow::ID,
+        accounts: escrow::accounts::TakeOffer {
+            taker: *taker,
+            maker: offer.maker,
+            token_mint_a: offer.token_mint_a,
+            token_mint_b: *token_mint_b,
+            taker_token_account_a: ata(taker, &offer.token_mint_a),
+            taker_token_account_b: ata(taker, token_mint_b),
+            maker_token_account_b: ata(&offer.maker, token_mint_b),
+            offer: *offer_address,
+            vault: vault_address(offer_address, offer, token_program),
+            config: config_address(),
+            associated_token_program: associated_token::ID,
+            token_program: *token_program,
+            system_program: system_program::ID,
+            fill_receipt: save_fill_receipt.then(|| FillReceipt::pda(offer_address, taker).0),
+            referrer: referral.as_ref().map(|referral| referral.referrer),
+            referrer_token_account_b: referral
+                .as_ref()
+                .map(|referral| ata(&referral.referrer, token_mint_b)),
+            price_feed: offer.peg.as_ref().map(|peg| peg.price_feed),
+            nft_metadata: offer
+                .nft_collection
+                .map(|_| NftMetadata::address(&offer.token_mint_a)),
+            rent_payer: None,
+            offer_rent_payer: offer_rent_payer(offer),
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::TakeOffer {
+            referral_fee_bps: referral.map_or(0, |referral| referral.fee_bps),
+        }
+        .data(),
+    }
+}
+
+/// Metadata of the NFT an offer lists, for its royalties.
+pub fn fetch_nft_metadata(rpc: &RpcClient, mint: &Pubkey) -> Result<NftMetadata> {
+    let address = NftMetadata::address(mint);
+    let account = rpc
+        .get_account(&address)?
+        .ok_or_else(|| format!("NFT {mint} has no metadata"))?;
+    Ok(
+        NftMetadata::try_from_account_data(&account.owner, &account.data, mint)
+            .map_err(|error| format!("{address} is not the metadata of {mint}: {error}"))?,
+    )
+}
+
+/// Instructions creating the `token_mint_b` ATAs of an NFT's creators where
+/// missing, and the accounts `take_offer` expects after its own to pay them
+/// royalties.
+pub fn royalty_accounts(
+    payer: &Pubkey,
+    metadata: &NftMetadata,
+    token_mint_b: &Pubkey,
+    token_program: &Pubkey,
+) -> (Vec<Instruction>, Vec<AccountMeta>) {
+    metadata
+        .creators
+        .iter()
+        .map(|(creator, _)| {
+            (
+                create_associated_token_account_idempotent(
+                    payer,
+                    creator,
+                    token_mint_b,
+                    token_program,
+                ),
+                AccountMeta::new(
+                    get_associated_token_address_with_program_id(
+                        creator,
+                        token_mint_b,
+                        token_program,
+                    ),
+                    false,
+                ),
+            )
+        })
+        .unzip()
+}
+
+pub fn cancel_offer_instruction(
+    offer_address: &Pubkey,
+    offer: &Offer,
+    token_program: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: escrow::ID,
+        accounts: escrow::accounts::CancelOffer {
+            maker: offer.maker,
+            token_mint_a: offer.token_mint_a,
+            maker_token_account_a: get_associated_token_address_with_program_id(
+                &offer.maker,
+                &offer.token_mint_a,
+                token_program,
+            ),
+            offer: *offer_address,
+            vault: vault_address(offer_address, offer, token_program),
+            associated_token_program: associated_token::ID,
+            token_program: *token_program,
+            system_program: system_program::ID,
+            offer_rent_payer: offer_rent_payer(offer),
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::CancelOffer {}.data(),
+    }
+}
+
+/// Migrates with the maker paying, as the program requires.
+pub fn migrate_offer_instruction(offer_address: &Pubkey, offer: &Offer) -> Instruction {
+    Instruction {
+        program_id: escrow::ID,
+        accounts: escrow::accounts::MigrateOffer {
+            payer: offer.maker,
+            offer: *offer_address,
+            maker: offer.maker,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::MigrateOffer {}.data(),
+    }
+}
@@ -0,0 +1,69 @@
+//! Reading fill receipts and exporting them for bookkeeping.
+
+use std::io::{self, Write};
+
+use anchor_lang::{
+    prelude::Pubkey, solana_program::instruction::Instruction, AccountDeserialize, Discriminator,
+    InstructionData, ToAccountMetas,
+};
+use escrow::FillReceipt;
+use escrow_client::rpc::RpcClient;
+
+use crate::{amount::format_amount, Result};
+
+/// Columns written by [`write_csv`], in order.
+pub const CSV_HEADER: &str = "receipt,offer,maker,taker,slot,unix_timestamp,\
+token_mint_a,token_a_amount,token_mint_b,token_b_amount,price";
+
+/// All fill receipts, or only those of `taker`, oldest fill first.
+pub fn fetch_fill_receipts(
+    rpc: &RpcClient,
+    taker: Option<&Pubkey>,
+) -> Result<Vec<(Pubkey, FillReceipt)>> {
+    let (_, accounts) = rpc.get_program_accounts(&escrow::ID, FillReceipt::DISCRIMINATOR)?;
+    let mut receipts = Vec::new();
+    for account in accounts {
+        let receipt = FillReceipt::try_deserialize(&mut account.data.as_slice())
+            .map_err(|error| format!("{} is not a fill receipt: {error}", account.pubkey))?;
+        if taker.is_none_or(|taker| receipt.taker == *taker) {
+            receipts.push((account.pubkey, receipt));
+        }
+    }
+    receipts.sort_by_key(|(address, receipt)| (receipt.slot, *address));
+    Ok(receipts)
+}
+
+/// Writes `receipts` as CSV, starting with [`CSV_HEADER`]. Amounts are in
+/// whole tokens, and the price is in whole token B per whole token A.
+pub fn write_csv(mut writer: impl Write, receipts: &[(Pubkey, FillReceipt)]) -> io::Result<()> {
+    writeln!(writer, "{CSV_HEADER}")?;
+    for (address, receipt) in receipts {
+        writeln!(
+            writer,
+            "{address},{},{},{},{},{},{},{},{},{},{}",
+            receipt.offer,
+            receipt.maker,
+            receipt.taker,
+            receipt.slot,
+            receipt.unix_timestamp,
+            receipt.token_mint_a,
+            format_amount(receipt.token_a_amount, receipt.token_a_decimals),
+            receipt.token_mint_b,
+            format_amount(receipt.token_b_amount, receipt.token_b_decimals),
+            format_amount(receipt.price, receipt.token_b_decimals),
+        )?;
+    }
+    Ok(())
+}
+
+pub fn close_fill_receipt_instruction(taker: &Pubkey, fill_receipt: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: escrow::ID,
+        accounts: escrow::accounts::CloseFillReceipt {
+            taker: *taker,
+            fill_receipt: *fill_receipt,
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::CloseFillReceipt {}.data(),
+    }
+}
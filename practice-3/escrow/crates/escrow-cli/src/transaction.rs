@@ -0,0 +1,40 @@
+use std::{thread, time::Duration};
+
+use escrow_client::rpc::RpcClient;
+use solana_sdk::{
+    instruction::Instruction,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+
+use crate::Result;
+
+const CONFIRMATION_POLLS: u32 = 60;
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Signs `instructions` with `payer`, sends them in one transaction and
+/// waits until it is confirmed.
+pub fn send_and_confirm(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Keypair,
+) -> Result<String> {
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        rpc.get_latest_blockhash()?,
+    );
+    let signature = rpc.send_transaction(&bincode::serialize(&transaction)?)?;
+
+    for _ in 0..CONFIRMATION_POLLS {
+        match rpc.get_signature_status(&signature)? {
+            Some(Ok(())) => return Ok(signature),
+            Some(Err(error)) => {
+                return Err(format!("Transaction {signature} failed: {error}").into())
+            }
+            None => thread::sleep(CONFIRMATION_POLL_INTERVAL),
+        }
+    }
+    Err(format!("Transaction {signature} was not confirmed in time").into())
+}
@@ -0,0 +1,40 @@
+use escrow_cli::amount::{format_amount, parse_amount};
+
+#[test]
+fn parses_amounts_scaled_by_decimals() {
+    assert_eq!(parse_amount("1", 6), Ok(1_000_000));
+    assert_eq!(parse_amount("1.5", 6), Ok(1_500_000));
+    assert_eq!(parse_amount("0.000001", 6), Ok(1));
+    assert_eq!(parse_amount(".25", 2), Ok(25));
+    assert_eq!(parse_amount("42.", 0), Ok(42));
+    assert_eq!(parse_amount("18446744073709551615", 0), Ok(u64::MAX));
+}
+
+#[test]
+fn rejects_invalid_amounts() {
+    for text in ["", ".", "-1", "1,5", "1.2.3", "one", " 1"] {
+        assert!(parse_amount(text, 6).is_err(), "{text:?} was accepted");
+    }
+    assert!(parse_amount("0.0000001", 6)
+        .unwrap_err()
+        .contains("decimal places"));
+    assert!(parse_amount("18446744073709551616", 0).is_err());
+    assert!(parse_amount("18446744073710", 6).is_err());
+}
+
+#[test]
+fn formats_amounts_without_trailing_zeros() {
+    assert_eq!(format_amount(1_500_000, 6), "1.5");
+    assert_eq!(format_amount(1_000_000, 6), "1");
+    assert_eq!(format_amount(1, 6), "0.000001");
+    assert_eq!(format_amount(0, 9), "0");
+    assert_eq!(format_amount(42, 0), "42");
+}
+
+#[test]
+fn round_trips() {
+    for (amount, decimals) in [(123_456_789, 9), (5, 1), (u64::MAX, 9)] {
+        let text = format_amount(amount, decimals);
+        assert_eq!(parse_amount(&text, decimals), Ok(amount));
+    }
+}
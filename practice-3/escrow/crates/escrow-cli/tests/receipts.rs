@@ -0,0 +1,62 @@
+use anchor_lang::prelude::Pubkey;
+use escrow::FillReceipt;
+use escrow_cli::receipts::{write_csv, CSV_HEADER};
+
+fn receipt() -> FillReceipt {
+    FillReceipt {
+        offer: Pubkey::new_unique(),
+        maker: Pubkey::new_unique(),
+        taker: Pubkey::new_unique(),
+        token_mint_a: Pubkey::new_unique(),
+        token_mint_b: Pubkey::new_unique(),
+        token_a_amount: 2_500_000_000,
+        token_b_amount: 5_000_000,
+        token_a_decimals: 9,
+        token_b_decimals: 6,
+        price: FillReceipt::price(2_500_000_000, 5_000_000, 9),
+        slot: 1234,
+        unix_timestamp: 1_700_000_000,
+        bump: 255,
+    }
+}
+
+#[test]
+fn price_is_token_b_base_units_per_whole_token_a() {
+    assert_eq!(FillReceipt::price(2_500_000_000, 5_000_000, 9), 2_000_000);
+    assert_eq!(FillReceipt::price(3, 10, 0), 3);
+    assert_eq!(FillReceipt::price(0, 10, 6), 0);
+    assert_eq!(FillReceipt::price(1, u64::MAX, 9), u64::MAX);
+    assert_eq!(FillReceipt::price(1, 1, 255), u64::MAX);
+}
+
+#[test]
+fn writes_one_row_per_receipt_in_whole_tokens() {
+    let address = Pubkey::new_unique();
+    let receipt = receipt();
+    let mut csv = Vec::new();
+    write_csv(&mut csv, &[(address, receipt.clone())]).unwrap();
+
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], CSV_HEADER);
+    assert_eq!(
+        lines[1],
+        format!(
+            "{address},{},{},{},1234,1700000000,{},2.5,{},5,2",
+            receipt.offer, receipt.maker, receipt.taker, receipt.token_mint_a, receipt.token_mint_b,
+        )
+    );
+    assert_eq!(
+        lines[0].split(',').count(),
+        lines[1].split(',').count(),
+        "header and row disagree on the column count"
+    );
+}
+
+#[test]
+fn writes_only_the_header_without_receipts() {
+    let mut csv = Vec::new();
+    write_csv(&mut csv, &[]).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap(), format!("{CSV_HEADER}\n"));
+}
@@ -0,0 +1,5 @@
+//! Code shared by the off-chain escrow tools.
+
+pub mod rpc;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
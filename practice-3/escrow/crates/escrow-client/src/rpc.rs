@@ -0,0 +1,227 @@
+//! The handful of JSON-RPC methods the escrow tools need, over blocking HTTP.
+
+use anchor_lang::{prelude::Pubkey, solana_program::hash::Hash};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use crate::Result;
+
+/// Maximum page size of `getSignaturesForAddress`.
+pub const SIGNATURES_PAGE_LIMIT: usize = 1000;
+
+pub struct RpcClient {
+    url: String,
+    agent: ureq::Agent,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureInfo {
+    pub signature: String,
+    pub slot: u64,
+    pub err: Option<Value>,
+}
+
+#[derive(Debug)]
+pub struct TransactionLogs {
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub failed: bool,
+    pub log_messages: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Account {
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct ProgramAccount {
+    pub pubkey: Pubkey,
+    pub data: Vec<u8>,
+}
+
+impl RpcClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            agent: ureq::Agent::new(),
+        }
+    }
+
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            }))?
+            .into_json()?;
+
+        if let Some(error) = response.get("error") {
+            return Err(format!("{method} failed: {error}").into());
+        }
+        Ok(serde_json::from_value(response["result"].clone())?)
+    }
+
+    /// Newest first, as returned by the node. `until` is exclusive.
+    pub fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+        before: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Vec<SignatureInfo>> {
+        self.call(
+            "getSignaturesForAddress",
+            json!([
+                address.to_string(),
+                {
+                    "commitment": "confirmed",
+                    "limit": SIGNATURES_PAGE_LIMIT,
+                    "before": before,
+                    "until": until,
+                }
+            ]),
+        )
+    }
+
+    pub fn get_transaction_logs(&self, signature: &str) -> Result<Option<TransactionLogs>> {
+        let result: Value = self.call(
+            "getTransaction",
+            json!([
+                signature,
+                {
+                    "commitment": "confirmed",
+                    "encoding": "json",
+                    "maxSupportedTransactionVersion": 0,
+                }
+            ]),
+        )?;
+        if result.is_null() {
+            return Ok(None);
+        }
+
+        let meta = &result["meta"];
+        let log_messages = serde_json::from_value(meta["logMessages"].clone()).unwrap_or_default();
+        Ok(Some(TransactionLogs {
+            slot: result["slot"].as_u64().unwrap_or_default(),
+            block_time: result["blockTime"].as_i64(),
+            failed: !meta["err"].is_null(),
+            log_messages,
+        }))
+    }
+
+    /// Accounts of `program_id` whose data starts with `prefix`, and the slot
+    /// the snapshot was taken at.
+    pub fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        prefix: &[u8],
+    ) -> Result<(u64, Vec<ProgramAccount>)> {
+        let result: Value = self.call(
+            "getProgramAccounts",
+            json!([
+                program_id.to_string(),
+                {
+                    "commitment": "confirmed",
+                    "encoding": "base64",
+                    "withContext": true,
+                    "filters": [
+                        { "memcmp": { "offset": 0, "bytes": bs58::encode(prefix).into_string() } }
+                    ],
+                }
+            ]),
+        )?;
+
+        let slot = result["context"]["slot"].as_u64().unwrap_or_default();
+        let accounts = result["value"]
+            .as_array()
+            .ok_or("getProgramAccounts returned no value")?
+            .iter()
+            .map(|entry| {
+                let pubkey = entry["pubkey"]
+                    .as_str()
+                    .ok_or("account without pubkey")?
+                    .parse()?;
+                let data = BASE64.decode(
+                    entry["account"]["data"][0]
+                        .as_str()
+                        .ok_or("account without data")?,
+                )?;
+                Ok(ProgramAccount { pubkey, data })
+            })
+            .collect::<Result<_>>()?;
+        Ok((slot, accounts))
+    }
+
+    pub fn get_account(&self, address: &Pubkey) -> Result<Option<Account>> {
+        let result: Value = self.call(
+            "getAccountInfo",
+            json!([
+                address.to_string(),
+                { "commitment": "confirmed", "encoding": "base64" }
+            ]),
+        )?;
+
+        let value = &result["value"];
+        if value.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(Account {
+            owner: value["owner"]
+                .as_str()
+                .ok_or("account without owner")?
+                .parse()?,
+            lamports: value["lamports"].as_u64().unwrap_or_default(),
+            data: BASE64.decode(value["data"][0].as_str().ok_or("account without data")?)?,
+        }))
+    }
+
+    pub fn get_latest_blockhash(&self) -> Result<Hash> {
+        let result: Value =
+            self.call("getLatestBlockhash", json!([{ "commitment": "confirmed" }]))?;
+        Ok(result["value"]["blockhash"]
+            .as_str()
+            .ok_or("getLatestBlockhash returned no blockhash")?
+            .parse()?)
+    }
+
+    /// Submits a signed, serialized transaction and returns its signature.
+    pub fn send_transaction(&self, wire_transaction: &[u8]) -> Result<String> {
+        self.call(
+            "sendTransaction",
+            json!([
+                BASE64.encode(wire_transaction),
+                { "encoding": "base64", "preflightCommitment": "confirmed" }
+            ]),
+        )
+    }
+
+    /// `None` while the transaction is not confirmed yet, otherwise whether
+    /// it succeeded.
+    pub fn get_signature_status(
+        &self,
+        signature: &str,
+    ) -> Result<Option<std::result::Result<(), String>>> {
+        let result: Value = self.call("getSignatureStatuses", json!([[signature]]))?;
+
+        let status = &result["value"][0];
+        let confirmed = matches!(
+            status["confirmationStatus"].as_str(),
+            Some("confirmed" | "finalized")
+        );
+        if !confirmed {
+            return Ok(None);
+        }
+        Ok(Some(match &status["err"] {
+            Value::Null => Ok(()),
+            error => Err(error.to_string()),
+        }))
+    }
+}
@@ -0,0 +1,145 @@
+//! Read-only HTTP JSON API over the store.
+//!
+//! - `GET /health`: the newest indexed transaction.
+//! - `GET /offers?maker=&mint_a=&mint_b=&collection=&status=&limit=`: offer
+//!   lifetimes, newest first. All parameters are optional; `mint_b` matches
+//!   any mint an offer accepts as payment, `collection` only matches NFT
+//!   listings, and `status` is one of `open`, `taken`, `cancelled` or
+//!   `closed`.
+//! - `GET /offers/<address>`: the current state of an offer address, its
+//!   earlier lifetimes and the events that touched it.
+
+use anchor_lang::prelude::Pubkey;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Server};
+
+use crate::{
+    store::{OfferFilter, Store},
+    Result,
+};
+
+pub const DEFAULT_LIMIT: u32 = 100;
+pub const MAX_LIMIT: u32 = 1000;
+
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message.into() }),
+        }
+    }
+}
+
+/// Serves requests until the process exits.
+pub fn serve(listen: &str, store: Store) -> Result<()> {
+    let server = Server::http(listen)?;
+    println!("Serving the offer API on http://{listen}");
+
+    let content_type =
+        Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+    for request in server.incoming_requests() {
+        let response = if *request.method() == Method::Get {
+            handle(&store, request.url())
+        } else {
+            Response::error(405, "only GET is supported")
+        };
+
+        let http_response = tiny_http::Response::from_string(response.body.to_string())
+            .with_status_code(response.status)
+            .with_header(content_type.clone());
+        if let Err(error) = request.respond(http_response) {
+            eprintln!("Failed to send a response: {error}");
+        }
+    }
+    Ok(())
+}
+
+/// Answers a `GET` for `url`, which is the path with the query string.
+pub fn handle(store: &Store, url: &str) -> Response {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    let result = match segments.as_slice() {
+        ["health"] => health(store),
+        ["offers"] => list_offers(store, query),
+        ["offers", address] => offer_details(store, address),
+        _ => Ok(Response::error(404, "not found")),
+    };
+    result.unwrap_or_else(|error| Response::error(500, error.to_string()))
+}
+
+fn health(store: &Store) -> Result<Response> {
+    Ok(Response::ok(json!({
+        "last_signature": store.last_signature()?,
+    })))
+}
+
+fn list_offers(store: &Store, query: &str) -> Result<Response> {
+    let filter = match parse_filter(query) {
+        Ok(filter) => filter,
+        Err(message) => return Ok(Response::error(400, message)),
+    };
+    Ok(Response::ok(json!(store.offers(&filter)?)))
+}
+
+fn offer_details(store: &Store, address: &str) -> Result<Response> {
+    let Ok(address) = address.parse::<Pubkey>() else {
+        return Ok(Response::error(
+            400,
+            format!("invalid offer address: {address}"),
+        ));
+    };
+
+    let mut history = store.offer_history(&address)?;
+    if history.is_empty() {
+        return Ok(Response::error(404, format!("unknown offer: {address}")));
+    }
+    let current = history.remove(0);
+
+    Ok(Response::ok(json!({
+        "offer": current,
+        "history": history,
+        "events": store.offer_events(&address)?,
+    })))
+}
+
+fn parse_filter(query: &str) -> std::result::Result<OfferFilter, String> {
+    let mut filter = OfferFilter {
+        limit: DEFAULT_LIMIT,
+        ..OfferFilter::default()
+    };
+
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let pubkey = || {
+            value
+                .parse::<Pubkey>()
+                .map_err(|_| format!("{name} is not a valid public key: {value}"))
+        };
+        match name {
+            "maker" => filter.maker = Some(pubkey()?),
+            "mint_a" => filter.token_mint_a = Some(pubkey()?),
+            "mint_b" => filter.token_mint_b = Some(pubkey()?),
+            "collection" => filter.collection = Some(pubkey()?),
+            "status" => filter.status = Some(value.parse()?),
+            "limit" => {
+                filter.limit = value
+                    .parse::<u32>()
+                    .map_err(|_| format!("limit is not a number: {value}"))?
+                    .min(MAX_LIMIT)
+            }
+            _ => return Err(format!("unknown query parameter: {name}")),
+        }
+    }
+
+    Ok(filter)
+}
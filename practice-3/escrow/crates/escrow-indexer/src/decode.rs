@@ -0,0 +1,106 @@
+//! Turns raw account data and transaction logs into escrow types.
+
+use anchor_lang::{prelude::Pubkey, AnchorDeserialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use escrow::{NftListed, Offer, OfferCancelled, OfferMade, OfferTaken};
+
+pub enum EscrowEvent {
+    Made(OfferMade),
+    NftListed(NftListed),
+    Taken(OfferTaken),
+    Cancelled(OfferCancelled),
+}
+
+impl EscrowEvent {
+    pub fn offer(&self) -> Pubkey {
+        match self {
+            EscrowEvent::Made(event) => event.offer,
+            EscrowEvent::NftListed(event) => event.offer,
+            EscrowEvent::Taken(event) => event.offer,
+            EscrowEvent::Cancelled(event) => event.offer,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EscrowEvent::Made(_) => "made",
+            EscrowEvent::NftListed(_) => "listed",
+            EscrowEvent::Taken(_) => "taken",
+            EscrowEvent::Cancelled(_) => "cancelled",
+        }
+    }
+
+    /// Decodes the payload of one `emit!`, as logged in `Program data:`.
+    pub fn try_from_bytes(data: &[u8]) -> Option<Self> {
+        let (discriminator, mut body) = data.split_at_checked(8)?;
+
+        if discriminator == OfferMade::DISCRIMINATOR {
+            OfferMade::deserialize(&mut body)
+                .ok()
+                .map(EscrowEvent::Made)
+        } else if discriminator == NftListed::DISCRIMINATOR {
+            NftListed::deserialize(&mut body)
+                .ok()
+                .map(EscrowEvent::NftListed)
+        } else if discriminator == OfferTaken::DISCRIMINATOR {
+            OfferTaken::deserialize(&mut body)
+                .ok()
+                .map(EscrowEvent::Taken)
+        } else if discriminator == OfferCancelled::DISCRIMINATOR {
+            OfferCancelled::deserialize(&mut body)
+                .ok()
+                .map(EscrowEvent::Cancelled)
+        } else {
+            None
+        }
+    }
+}
+
+/// Extracts the escrow events from the logs of one transaction.
+///
+/// Only `Program data:` lines written while `program_id` is the innermost
+/// running program are considered, so events of other programs are skipped
+/// while events of an escrow invoked through CPI, say by a router, are kept.
+pub fn events_from_logs(program_id: &Pubkey, log_messages: &[String]) -> Vec<EscrowEvent> {
+    let program_id = program_id.to_string();
+    let mut invocation_stack: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in log_messages {
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+
+        if let Some(payload) = rest.strip_prefix("data: ") {
+            if invocation_stack.last() == Some(&program_id.as_str()) {
+                if let Some(event) = BASE64
+                    .decode(payload)
+                    .ok()
+                    .and_then(|data| EscrowEvent::try_from_bytes(&data))
+                {
+                    events.push(event);
+                }
+            }
+            continue;
+        }
+
+        let mut words = rest.split(' ');
+        let (Some(program), Some(action)) = (words.next(), words.next()) else {
+            continue;
+        };
+        match action {
+            "invoke" => invocation_stack.push(program),
+            "success" | "failed:" => {
+                invocation_stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    events
+}
+
+/// Decodes an offer account in any layout the program has used.
+pub fn offer_from_account_data(data: &[u8]) -> Option<Offer> {
+    Offer::try_from_versioned(data).ok()
+}
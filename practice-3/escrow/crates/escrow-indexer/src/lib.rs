@@ -0,0 +1,9 @@
+//! Indexer for the escrow program: follows its transactions and accounts over
+//! RPC, keeps every offer in SQLite and serves them over a small HTTP API.
+
+pub mod api;
+pub mod decode;
+pub mod store;
+pub mod sync;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
@@ -0,0 +1,48 @@
+use std::{path::PathBuf, thread, time::Duration};
+
+use anchor_lang::prelude::Pubkey;
+use clap::Parser;
+use escrow_client::rpc::RpcClient;
+use escrow_indexer::{api, store::Store, sync::Indexer};
+
+/// Indexes escrow offers into SQLite and serves them over HTTP.
+#[derive(Parser)]
+struct Args {
+    /// JSON-RPC endpoint to follow, for example a local test validator.
+    #[arg(long, env = "RPC_URL", default_value = "http://127.0.0.1:8899")]
+    url: String,
+
+    #[arg(long, default_value_t = escrow::ID)]
+    program_id: Pubkey,
+
+    /// SQLite database file, created if missing.
+    #[arg(long, default_value = "escrow-indexer.sqlite")]
+    db: PathBuf,
+
+    /// Address for the HTTP API.
+    #[arg(long, default_value = "127.0.0.1:3000")]
+    listen: String,
+
+    #[arg(long, default_value_t = 2)]
+    poll_interval_secs: u64,
+
+    /// Reconcile with the live offer accounts every this many polls.
+    #[arg(long, default_value_t = 30)]
+    snapshot_every: u32,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let indexer = Indexer::new(
+        RpcClient::new(&args.url),
+        Store::open(&args.db).expect("Failed to open the database"),
+        args.program_id,
+    );
+    let poll_interval = Duration::from_secs(args.poll_interval_secs);
+    let snapshot_every = args.snapshot_every;
+    thread::spawn(move || indexer.run(poll_interval, snapshot_every));
+
+    let store = Store::open(&args.db).expect("Failed to open the database");
+    api::serve(&args.listen, store).expect("HTTP server failed");
+}
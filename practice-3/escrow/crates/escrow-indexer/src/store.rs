@@ -0,0 +1,538 @@
+//! SQLite storage of offers and their event history.
+//!
+//! An offer address is `[OFFER_SEED, maker, id]`, so a maker can reuse it
+//! after the previous offer with the same id was closed. The `offers` table
+//! therefore keeps one row per offer *lifetime*, and the current state of an
+//! address is its newest row.
+//!
+//! Amounts and ids are `u64` and stored as decimal `TEXT`, as SQLite integers
+//! are signed.
+
+use std::{fmt, path::Path, str::FromStr};
+
+use anchor_lang::prelude::Pubkey;
+use escrow::{Offer, PaymentOption};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+use crate::{decode::EscrowEvent, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OfferStatus {
+    Open,
+    Taken,
+    Cancelled,
+    /// Gone from the chain without the indexer seeing why, for example
+    /// because its transactions were older than the node's history.
+    Closed,
+}
+
+impl OfferStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OfferStatus::Open => "open",
+            OfferStatus::Taken => "taken",
+            OfferStatus::Cancelled => "cancelled",
+            OfferStatus::Closed => "closed",
+        }
+    }
+}
+
+impl fmt::Display for OfferStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OfferStatus {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "open" => Ok(OfferStatus::Open),
+            "taken" => Ok(OfferStatus::Taken),
+            "cancelled" => Ok(OfferStatus::Cancelled),
+            "closed" => Ok(OfferStatus::Closed),
+            _ => Err(format!("unknown offer status: {value}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OfferRow {
+    pub address: String,
+    pub maker: String,
+    pub token_mint_a: String,
+    pub token_mint_b: String,
+    pub id: Option<String>,
+    pub token_a_offered_amount: Option<String>,
+    pub token_b_wanted_amount: Option<String>,
+    pub status: OfferStatus,
+    pub taker: Option<String>,
+    pub created_slot: Option<u64>,
+    pub created_signature: Option<String>,
+    pub closed_slot: Option<u64>,
+    pub closed_signature: Option<String>,
+    /// Verified collection of an NFT listing.
+    pub collection: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventRow {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub kind: String,
+    pub offer: String,
+}
+
+/// Where an event was seen.
+pub struct EventSource<'a> {
+    pub signature: &'a str,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+}
+
+/// How an offer lifetime ended, as told by a take or cancel event.
+struct Closure<'a> {
+    status: OfferStatus,
+    maker: &'a Pubkey,
+    token_mint_a: &'a Pubkey,
+    token_mint_b: &'a Pubkey,
+    taker: Option<&'a Pubkey>,
+    token_b_amount: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct OfferFilter {
+    pub maker: Option<Pubkey>,
+    pub token_mint_a: Option<Pubkey>,
+    pub token_mint_b: Option<Pubkey>,
+    pub collection: Option<Pubkey>,
+    pub status: Option<OfferStatus>,
+    pub limit: u32,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS offers (
+        row_id INTEGER PRIMARY KEY,
+        address TEXT NOT NULL,
+        maker TEXT NOT NULL,
+        token_mint_a TEXT NOT NULL,
+        token_mint_b TEXT NOT NULL,
+        id TEXT,
+        token_a_offered_amount TEXT,
+        token_b_wanted_amount TEXT,
+        status TEXT NOT NULL,
+        taker TEXT,
+        created_slot INTEGER,
+        created_signature TEXT,
+        closed_slot INTEGER,
+        closed_signature TEXT,
+        collection TEXT
+    );
+    CREATE INDEX IF NOT EXISTS offers_address ON offers (address);
+    CREATE INDEX IF NOT EXISTS offers_maker ON offers (maker);
+    CREATE INDEX IF NOT EXISTS offers_mints ON offers (token_mint_a, token_mint_b);
+
+    -- Mints an offer lifetime accepts besides its token_mint_b, keyed by
+    -- offers.row_id.
+    CREATE TABLE IF NOT EXISTS offer_payments (
+        offer INTEGER NOT NULL,
+        mint TEXT NOT NULL,
+        amount TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS offer_payments_offer ON offer_payments (offer);
+    CREATE INDEX IF NOT EXISTS offer_payments_mint ON offer_payments (mint);
+
+    CREATE TABLE IF NOT EXISTS events (
+        signature TEXT NOT NULL,
+        slot INTEGER NOT NULL,
+        block_time INTEGER,
+        kind TEXT NOT NULL,
+        offer TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS events_offer ON events (offer);
+
+    CREATE TABLE IF NOT EXISTS cursor (
+        name TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+/// Indexes on columns added after the first schema, created once
+/// `add_missing_columns` made sure the columns exist.
+const LATER_INDEXES: &str = "
+    CREATE INDEX IF NOT EXISTS offers_collection ON offers (collection);
+";
+
+const OFFER_COLUMNS: &str = "address, maker, token_mint_a, token_mint_b, id, \
+    token_a_offered_amount, token_b_wanted_amount, status, taker, created_slot, \
+    created_signature, closed_slot, closed_signature, collection";
+
+const LAST_SIGNATURE: &str = "last_signature";
+
+pub struct Store {
+    connection: Connection,
+}
+
+impl Store {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        // The sync loop and the HTTP server use separate connections.
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.busy_timeout(std::time::Duration::from_secs(5))?;
+        Self::with_connection(connection)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        add_missing_columns(&connection)?;
+        connection.execute_batch(LATER_INDEXES)?;
+        Ok(Self { connection })
+    }
+
+    /// Newest transaction signature that has been fully processed.
+    pub fn last_signature(&self) -> Result<Option<String>> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT value FROM cursor WHERE name = ?1",
+                [LAST_SIGNATURE],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Applies the events of one transaction and advances the cursor past it,
+    /// atomically, so a restart never applies a transaction twice.
+    pub fn apply_transaction(&self, source: &EventSource, events: &[EscrowEvent]) -> Result<()> {
+        let transaction = self.connection.unchecked_transaction()?;
+        for event in events {
+            self.apply_event(source, event)?;
+        }
+        self.set_last_signature(source.signature)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn set_last_signature(&self, signature: &str) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO cursor (name, value) VALUES (?1, ?2)
+             ON CONFLICT (name) DO UPDATE SET value = excluded.value",
+            params![LAST_SIGNATURE, signature],
+        )?;
+        Ok(())
+    }
+
+    /// Row of the offer lifetime at `address` that an event about to close it
+    /// applies to: an open one, or one the account snapshot already marked as
+    /// closed without knowing why.
+    fn closable_row(&self, address: &str) -> Result<Option<i64>> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT row_id FROM offers
+                 WHERE address = ?1 AND closed_signature IS NULL AND status IN ('open', 'closed')
+                 ORDER BY row_id DESC LIMIT 1",
+                [address],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn open_row(&self, address: &str) -> Result<Option<(i64, Option<String>)>> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT row_id, created_signature FROM offers
+                 WHERE address = ?1 AND status = 'open'
+                 ORDER BY row_id DESC LIMIT 1",
+                [address],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?)
+    }
+
+    fn apply_event(&self, source: &EventSource, event: &EscrowEvent) -> Result<()> {
+        let address = event.offer().to_string();
+
+        self.connection.execute(
+            "INSERT INTO events (signature, slot, block_time, kind, offer)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                source.signature,
+                source.slot,
+                source.block_time,
+                event.kind(),
+                address
+            ],
+        )?;
+
+        match event {
+            EscrowEvent::Made(made) => {
+                // An account snapshot may have seen the offer before its
+                // creation was indexed. The event has the complete picture.
+                if let Some((row_id, None)) = self.open_row(&address)? {
+                    self.connection
+                        .execute("DELETE FROM offers WHERE row_id = ?1", [row_id])?;
+                    self.connection
+                        .execute("DELETE FROM offer_payments WHERE offer = ?1", [row_id])?;
+                }
+                self.connection.execute(
+                    "INSERT INTO offers (address, maker, token_mint_a, token_mint_b, id,
+                        token_a_offered_amount, token_b_wanted_amount, created_slot,
+                        created_signature, status)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 'open')",
+                    params![
+                        address,
+                        made.maker.to_string(),
+                        made.token_mint_a.to_string(),
+                        made.token_mint_b.to_string(),
+                        made.id.to_string(),
+                        made.token_a_offered_amount.to_string(),
+                        made.token_b_wanted_amount.to_string(),
+                        source.slot,
+                        source.signature
+                    ],
+                )?;
+                self.insert_payments(self.connection.last_insert_rowid(), &made.other_payments)?;
+            }
+            EscrowEvent::NftListed(listed) => {
+                if let Some((row_id, _)) = self.open_row(&address)? {
+                    self.connection.execute(
+                        "UPDATE offers SET collection = ?1 WHERE row_id = ?2",
+                        params![listed.collection.to_string(), row_id],
+                    )?;
+                }
+            }
+            EscrowEvent::Taken(taken) => self.close_offer(
+                &address,
+                source,
+                Closure {
+                    status: OfferStatus::Taken,
+                    maker: &taken.maker,
+                    token_mint_a: &taken.token_mint_a,
+                    token_mint_b: &taken.token_mint_b,
+                    taker: Some(&taken.taker),
+                    token_b_amount: Some(taken.token_b_amount),
+                },
+            )?,
+            EscrowEvent::Cancelled(cancelled) => self.close_offer(
+                &address,
+                source,
+                Closure {
+                    status: OfferStatus::Cancelled,
+                    maker: &cancelled.maker,
+                    token_mint_a: &cancelled.token_mint_a,
+                    token_mint_b: &cancelled.token_mint_b,
+                    taker: None,
+                    token_b_amount: None,
+                },
+            )?,
+        }
+        Ok(())
+    }
+
+    fn insert_payments(&self, row_id: i64, payments: &[PaymentOption]) -> Result<()> {
+        for payment in payments {
+            self.connection.execute(
+                "INSERT INTO offer_payments (offer, mint, amount) VALUES (?1, ?2, ?3)",
+                params![row_id, payment.mint.to_string(), payment.amount.to_string()],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn close_offer(&self, address: &str, source: &EventSource, closure: Closure) -> Result<()> {
+        let taker = closure.taker.map(Pubkey::to_string);
+        let token_b_amount = closure.token_b_amount.map(|amount| amount.to_string());
+
+        match self.closable_row(address)? {
+            Some(row_id) => {
+                self.connection.execute(
+                    "UPDATE offers SET status = ?1, taker = ?2, closed_slot = ?3,
+                        closed_signature = ?4,
+                        token_b_wanted_amount = COALESCE(?5, token_b_wanted_amount)
+                     WHERE row_id = ?6",
+                    params![
+                        closure.status.as_str(),
+                        taker,
+                        source.slot,
+                        source.signature,
+                        token_b_amount,
+                        row_id
+                    ],
+                )?;
+            }
+            // Created before the oldest transaction the indexer could see.
+            None => {
+                self.connection.execute(
+                    "INSERT INTO offers (address, maker, token_mint_a, token_mint_b,
+                        token_b_wanted_amount, status, taker, closed_slot, closed_signature)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        address,
+                        closure.maker.to_string(),
+                        closure.token_mint_a.to_string(),
+                        closure.token_mint_b.to_string(),
+                        token_b_amount,
+                        closure.status.as_str(),
+                        taker,
+                        source.slot,
+                        source.signature
+                    ],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reconciles the store with the set of offer accounts that currently
+    /// exist: unknown live offers are added as open, and open offers that no
+    /// longer exist are marked as closed.
+    pub fn apply_snapshot(&self, slot: u64, live_offers: &[(Pubkey, Offer)]) -> Result<()> {
+        let transaction = self.connection.unchecked_transaction()?;
+
+        transaction.execute(
+            "CREATE TEMP TABLE IF NOT EXISTS live (address TEXT PRIMARY KEY)",
+            [],
+        )?;
+        transaction.execute("DELETE FROM live", [])?;
+
+        for (address, offer) in live_offers {
+            let address = address.to_string();
+            transaction.execute(
+                "INSERT OR IGNORE INTO live (address) VALUES (?1)",
+                [&address],
+            )?;
+
+            if self.open_row(&address)?.is_none() {
+                transaction.execute(
+                    "INSERT INTO offers (address, maker, token_mint_a, token_mint_b, id,
+                        token_b_wanted_amount, collection, status)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'open')",
+                    params![
+                        address,
+                        offer.maker.to_string(),
+                        offer.token_mint_a.to_string(),
+                        offer.token_mint_b.to_string(),
+                        offer.id.to_string(),
+                        offer.token_b_wanted_amount.to_string(),
+                        offer.nft_collection.map(|key| key.to_string())
+                    ],
+                )?;
+                self.insert_payments(self.connection.last_insert_rowid(), &offer.other_payments)?;
+            }
+        }
+
+        transaction.execute(
+            "UPDATE offers SET status = 'closed', closed_slot = ?1
+             WHERE status = 'open' AND address NOT IN (SELECT address FROM live)",
+            [slot],
+        )?;
+
+        transaction.commit()?;
+        Ok(())
+    }
+
+    pub fn offers(&self, filter: &OfferFilter) -> Result<Vec<OfferRow>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {OFFER_COLUMNS} FROM offers
+             WHERE (?1 IS NULL OR maker = ?1)
+               AND (?2 IS NULL OR token_mint_a = ?2)
+               AND (?3 IS NULL OR token_mint_b = ?3 OR EXISTS (
+                   SELECT 1 FROM offer_payments
+                   WHERE offer_payments.offer = offers.row_id AND offer_payments.mint = ?3
+               ))
+               AND (?4 IS NULL OR collection = ?4)
+               AND (?5 IS NULL OR status = ?5)
+             ORDER BY row_id DESC
+             LIMIT ?6"
+        ))?;
+        let rows = statement
+            .query_map(
+                params![
+                    filter.maker.map(|key| key.to_string()),
+                    filter.token_mint_a.map(|key| key.to_string()),
+                    filter.token_mint_b.map(|key| key.to_string()),
+                    filter.collection.map(|key| key.to_string()),
+                    filter.status.map(OfferStatus::as_str),
+                    filter.limit
+                ],
+                offer_from_row,
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
+
+    /// Every lifetime of the offer at `address`, newest first.
+    pub fn offer_history(&self, address: &Pubkey) -> Result<Vec<OfferRow>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {OFFER_COLUMNS} FROM offers WHERE address = ?1 ORDER BY row_id DESC"
+        ))?;
+        let rows = statement
+            .query_map([address.to_string()], offer_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
+
+    /// Events that touched the offer at `address`, oldest first.
+    pub fn offer_events(&self, address: &Pubkey) -> Result<Vec<EventRow>> {
+        let mut statement = self.connection.prepare(
+            "SELECT signature, slot, block_time, kind, offer FROM events
+             WHERE offer = ?1 ORDER BY rowid",
+        )?;
+        let rows = statement
+            .query_map([address.to_string()], |row| {
+                Ok(EventRow {
+                    signature: row.get(0)?,
+                    slot: row.get(1)?,
+                    block_time: row.get(2)?,
+                    kind: row.get(3)?,
+                    offer: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(rows)
+    }
+}
+
+fn offer_from_row(row: &Row) -> rusqlite::Result<OfferRow> {
+    let status: String = row.get(7)?;
+    Ok(OfferRow {
+        address: row.get(0)?,
+        maker: row.get(1)?,
+        token_mint_a: row.get(2)?,
+        token_mint_b: row.get(3)?,
+        id: row.get(4)?,
+        token_a_offered_amount: row.get(5)?,
+        token_b_wanted_amount: row.get(6)?,
+        status: status.parse().map_err(|error: String| {
+            rusqlite::Error::FromSqlConversionFailure(7, rusqlite::types::Type::Text, error.into())
+        })?,
+        taker: row.get(8)?,
+        created_slot: row.get(9)?,
+        created_signature: row.get(10)?,
+        closed_slot: row.get(11)?,
+        closed_signature: row.get(12)?,
+        collection: row.get(13)?,
+    })
+}
+
+/// Adds the columns introduced after a database was created, which
+/// `CREATE TABLE IF NOT EXISTS` leaves out.
+fn add_missing_columns(connection: &Connection) -> Result<()> {
+    if connection
+        .prepare("SELECT collection FROM offers LIMIT 0")
+        .is_err()
+    {
+        connection.execute("ALTER TABLE offers ADD COLUMN collection TEXT", [])?;
+    }
+    Ok(())
+}
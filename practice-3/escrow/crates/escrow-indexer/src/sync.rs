@@ -0,0 +1,124 @@
+//! Keeps the store in step with the chain.
+
+use std::{thread, time::Duration};
+
+use anchor_lang::{prelude::Pubkey, Discriminator};
+use escrow::Offer;
+use escrow_client::rpc::{RpcClient, SIGNATURES_PAGE_LIMIT};
+
+use crate::{
+    decode::{events_from_logs, offer_from_account_data},
+    store::{EventSource, Store},
+    Result,
+};
+
+pub struct Indexer {
+    rpc: RpcClient,
+    store: Store,
+    program_id: Pubkey,
+}
+
+impl Indexer {
+    pub fn new(rpc: RpcClient, store: Store, program_id: Pubkey) -> Self {
+        Self {
+            rpc,
+            store,
+            program_id,
+        }
+    }
+
+    /// Applies every program transaction since the last processed one, oldest
+    /// first. Returns the number of transactions applied.
+    pub fn sync_transactions(&self) -> Result<usize> {
+        let until = self.store.last_signature()?;
+
+        let mut pending = Vec::new();
+        let mut before: Option<String> = None;
+        loop {
+            let page = self.rpc.get_signatures_for_address(
+                &self.program_id,
+                before.as_deref(),
+                until.as_deref(),
+            )?;
+            let last_page = page.len() < SIGNATURES_PAGE_LIMIT;
+            before = page.last().map(|info| info.signature.clone());
+            pending.extend(page);
+            if last_page || before.is_none() {
+                break;
+            }
+        }
+
+        let mut applied = 0;
+        for info in pending.iter().rev() {
+            let logs = match info.err {
+                Some(_) => None,
+                // The node may not have the transaction yet, or may have
+                // pruned it. Applying it without its events would move the
+                // cursor past them for good, so stop and retry next poll.
+                None => match self.rpc.get_transaction_logs(&info.signature)? {
+                    Some(logs) => Some(logs),
+                    None => break,
+                },
+            };
+            let (block_time, events) = match logs {
+                Some(logs) if !logs.failed => (
+                    logs.block_time,
+                    events_from_logs(&self.program_id, &logs.log_messages),
+                ),
+                _ => (None, Vec::new()),
+            };
+
+            let source = EventSource {
+                signature: &info.signature,
+                slot: info.slot,
+                block_time,
+            };
+            self.store.apply_transaction(&source, &events)?;
+            applied += 1;
+        }
+
+        Ok(applied)
+    }
+
+    /// Reconciles the store with the offer accounts that exist right now.
+    /// Returns the number of live offers.
+    pub fn sync_accounts(&self) -> Result<usize> {
+        let (slot, accounts) = self
+            .rpc
+            .get_program_accounts(&self.program_id, Offer::DISCRIMINATOR)?;
+
+        let offers: Vec<_> = accounts
+            .into_iter()
+            .filter_map(|account| {
+                offer_from_account_data(&account.data).map(|offer| (account.pubkey, offer))
+            })
+            .collect();
+        self.store.apply_snapshot(slot, &offers)?;
+
+        Ok(offers.len())
+    }
+
+    /// Polls forever. Transactions are followed every `poll_interval`, and the
+    /// account snapshot is taken every `snapshot_every` polls, starting with
+    /// the first one, after the initial catch up.
+    pub fn run(&self, poll_interval: Duration, snapshot_every: u32) -> ! {
+        let mut poll: u32 = 0;
+        loop {
+            match self.sync_transactions() {
+                Ok(0) => {}
+                Ok(count) => println!("Indexed {count} transaction(s)"),
+                Err(error) => eprintln!("Failed to follow transactions: {error}"),
+            }
+
+            if poll.is_multiple_of(snapshot_every.max(1)) {
+                match self.sync_accounts() {
+                    Ok(count) => println!("Account snapshot: {count} open offer(s)"),
+                    Err(error) => eprintln!("Failed to snapshot offer accounts: {error}"),
+                }
+            }
+
+            poll = poll.wrapping_add(1);
+            thread::sleep(poll_interval);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Log parsing: events are only taken from the escrow's own `Program data:`
+//! lines, including when it is invoked through CPI.
+
+use anchor_lang::{prelude::Pubkey, Event};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use escrow::{NftListed, OfferCancelled, OfferMade, OfferTaken, PaymentOption};
+use escrow_indexer::decode::{events_from_logs, EscrowEvent};
+
+fn data_line(event: &impl Event) -> String {
+    format!("Program data: {}", BASE64.encode(event.data()))
+}
+
+fn offer_made(offer: Pubkey) -> OfferMade {
+    OfferMade {
+        offer,
+        maker: Pubkey::new_unique(),
+        id: 7,
+        token_mint_a: Pubkey::new_unique(),
+        token_mint_b: Pubkey::new_unique(),
+        token_a_offered_amount: 1_000,
+        token_b_wanted_amount: 2_000,
+        other_payments: Vec::new(),
+    }
+}
+
+fn offer_cancelled(offer: Pubkey) -> OfferCancelled {
+    OfferCancelled {
+        offer,
+        maker: Pubkey::new_unique(),
+        token_mint_a: Pubkey::new_unique(),
+        token_mint_b: Pubkey::new_unique(),
+        token_a_amount: 1_000,
+    }
+}
+
+#[test]
+fn decodes_top_level_events() {
+    let offer = Pubkey::new_unique();
+    let made = offer_made(offer);
+    let logs = vec![
+        format!("Program {} invoke [1]", escrow::ID),
+        "Program log: Instruction: MakeOffer".to_string(),
+        data_line(&made),
+        format!(
+            "Program {} consumed 30000 of 200000 compute units",
+            escrow::ID
+        ),
+        format!("Program {} success", escrow::ID),
+    ];
+
+    let events = events_from_logs(&escrow::ID, &logs);
+    assert_eq!(events.len(), 1);
+    let EscrowEvent::Made(decoded) = &events[0] else {
+        panic!("expected OfferMade");
+    };
+    assert_eq!(decoded.offer, offer);
+    assert_eq!(decoded.maker, made.maker);
+    assert_eq!(decoded.id, 7);
+    assert_eq!(decoded.token_a_offered_amount, 1_000);
+    assert_eq!(decoded.token_b_wanted_amount, 2_000);
+}
+
+#[test]
+fn keeps_events_emitted_through_cpi() {
+    let router = Pubkey::new_unique();
+    let offer = Pubkey::new_unique();
+    let logs = vec![
+        format!("Program {router} invoke [1]"),
+        format!("Program {} invoke [2]", escrow::ID),
+        format!("Program {} invoke [3]", token_program()),
+        format!("Program {} success", token_program()),
+        data_line(&offer_cancelled(offer)),
+        format!("Program {} success", escrow::ID),
+        format!("Program {router} success"),
+    ];
+
+    let events = events_from_logs(&escrow::ID, &logs);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind(), "cancelled");
+    assert_eq!(events[0].offer(), offer);
+}
+
+#[test]
+fn ignores_events_of_other_programs() {
+    let impostor = Pubkey::new_unique();
+    let logs = vec![
+        format!("Program {impostor} invoke [1]"),
+        // Same bytes as a real event, but not written by the escrow.
+        data_line(&offer_made(Pubkey::new_unique())),
+        format!("Program {} invoke [2]", escrow::ID),
+        format!("Program {} success", escrow::ID),
+        data_line(&offer_made(Pubkey::new_unique())),
+        format!("Program {impostor} success"),
+    ];
+
+    assert!(events_from_logs(&escrow::ID, &logs).is_empty());
+}
+
+#[test]
+fn decodes_take_events_with_referral_fields() {
+    let offer = Pubkey::new_unique();
+    let referrer = Pubkey::new_unique();
+    let taken = OfferTaken {
+        offer,
+        maker: Pubkey::new_unique(),
+        taker: Pubkey::new_unique(),
+        token_mint_a: Pubkey::new_unique(),
+        token_mint_b: Pubkey::new_unique(),
+        token_a_amount: 1_000,
+        token_b_amount: 2_000,
+        referrer: Some(referrer),
+        referral_fee_amount: 10,
+    };
+    let logs = vec![
+        format!("Program {} invoke [1]", escrow::ID),
+        data_line(&taken),
+        format!("Program {} success", escrow::ID),
+    ];
+
+    let events = events_from_logs(&escrow::ID, &logs);
+    assert_eq!(events.len(), 1);
+    let EscrowEvent::Taken(decoded) = &events[0] else {
+        panic!("expected OfferTaken");
+    };
+    assert_eq!(decoded.offer, offer);
+    assert_eq!(decoded.taker, taken.taker);
+    assert_eq!(decoded.token_b_amount, 2_000);
+    assert_eq!(decoded.referrer, Some(referrer));
+    assert_eq!(decoded.referral_fee_amount, 10);
+}
+
+#[test]
+fn decodes_make_events_with_other_payments() {
+    let mut made = offer_made(Pubkey::new_unique());
+    made.other_payments.push(PaymentOption {
+        mint: Pubkey::new_unique(),
+        amount: 3_000,
+    });
+    let logs = vec![
+        format!("Program {} invoke [1]", escrow::ID),
+        data_line(&made),
+        format!("Program {} success", escrow::ID),
+    ];
+
+    let events = events_from_logs(&escrow::ID, &logs);
+    assert_eq!(events.len(), 1);
+    let EscrowEvent::Made(decoded) = &events[0] else {
+        panic!("expected OfferMade");
+    };
+    assert_eq!(decoded.offer, made.offer);
+    assert_eq!(decoded.token_b_wanted_amount, 2_000);
+    assert_eq!(decoded.other_payments, made.other_payments);
+}
+
+#[test]
+fn decodes_nft_listings_after_their_offer() {
+    let made = offer_made(Pubkey::new_unique());
+    let listed = NftListed {
+        offer: made.offer,
+        collection: Pubkey::new_unique(),
+    };
+    let logs = vec![
+        format!("Program {} invoke [1]", escrow::ID),
+        data_line(&made),
+        data_line(&listed),
+        format!("Program {} success", escrow::ID),
+    ];
+
+    let events = events_from_logs(&escrow::ID, &logs);
+    assert_eq!(events.len(), 2);
+    let EscrowEvent::NftListed(decoded) = &events[1] else {
+        panic!("expected NftListed");
+    };
+    assert_eq!(decoded.offer, made.offer);
+    assert_eq!(decoded.collection, listed.collection);
+    assert_eq!(events[1].kind(), "listed");
+}
+
+#[test]
+fn skips_unknown_and_malformed_payloads() {
+    let logs = vec![
+        format!("Program {} invoke [1]", escrow::ID),
+        "Program data: not base64!".to_string(),
+        format!("Program data: {}", BASE64.encode([0u8; 4])),
+        format!("Program data: {}", BASE64.encode([1u8; 64])),
+        format!("Program {} success", escrow::ID),
+    ];
+
+    assert!(events_from_logs(&escrow::ID, &logs).is_empty());
+}
+
+fn token_program() -> Pubkey {
+    "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"
+        .parse()
+        .unwrap()
+}
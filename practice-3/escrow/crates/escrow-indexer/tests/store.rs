@@ -0,0 +1,453 @@
+//! Offer lifecycles in the store and the HTTP handlers on top of it.
+
+use anchor_lang::prelude::Pubkey;
+use escrow::{
+    NftListed, Offer, OfferCancelled, OfferMade, OfferTaken, PaymentOption, OFFER_VERSION,
+};
+use escrow_indexer::{
+    api::handle,
+    decode::EscrowEvent,
+    store::{EventSource, OfferFilter, OfferStatus, Store},
+};
+
+struct Fixture {
+    maker: Pubkey,
+    token_mint_a: Pubkey,
+    token_mint_b: Pubkey,
+    address: Pubkey,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let maker = Pubkey::new_unique();
+        Self {
+            maker,
+            token_mint_a: Pubkey::new_unique(),
+            token_mint_b: Pubkey::new_unique(),
+            address: Offer::pda(&maker, 1).0,
+        }
+    }
+
+    fn made(&self) -> EscrowEvent {
+        EscrowEvent::Made(OfferMade {
+            offer: self.address,
+            maker: self.maker,
+            id: 1,
+            token_mint_a: self.token_mint_a,
+            token_mint_b: self.token_mint_b,
+            token_a_offered_amount: 100,
+            token_b_wanted_amount: 200,
+            other_payments: Vec::new(),
+        })
+    }
+
+    fn listed(&self, collection: Pubkey) -> EscrowEvent {
+        EscrowEvent::NftListed(NftListed {
+            offer: self.address,
+            collection,
+        })
+    }
+
+    fn taken(&self, taker: Pubkey) -> EscrowEvent {
+        EscrowEvent::Taken(OfferTaken {
+            offer: self.address,
+            maker: self.maker,
+            taker,
+            token_mint_a: self.token_mint_a,
+            token_mint_b: self.token_mint_b,
+            token_a_amount: 100,
+            token_b_amount: 200,
+            referrer: None,
+            referral_fee_amount: 0,
+        })
+    }
+
+    fn cancelled(&self) -> EscrowEvent {
+        EscrowEvent::Cancelled(OfferCancelled {
+            offer: self.address,
+            maker: self.maker,
+            token_mint_a: self.token_mint_a,
+            token_mint_b: self.token_mint_b,
+            token_a_amount: 100,
+        })
+    }
+
+    fn account(&self) -> (Pubkey, Offer) {
+        let (_, bump) = Offer::pda(&self.maker, 1);
+        let offer = Offer {
+            version: OFFER_VERSION,
+            id: 1,
+            maker: self.maker,
+            token_mint_a: self.token_mint_a,
+            token_mint_b: self.token_mint_b,
+            token_b_wanted_amount: 200,
+            bump,
+            other_payments: Vec::new(),
+            peg: None,
+            nft_collection: None,
+            rent_payer: self.maker,
+        };
+        (self.address, offer)
+    }
+}
+
+fn source(signature: &str, slot: u64) -> EventSource<'_> {
+    EventSource {
+        signature,
+        slot,
+        block_time: Some(1_700_000_000 + slot as i64),
+    }
+}
+
+fn all_offers() -> OfferFilter {
+    OfferFilter {
+        limit: 100,
+        ..OfferFilter::default()
+    }
+}
+
+#[test]
+fn made_then_taken() {
+    let store = Store::open_in_memory().unwrap();
+    let fixture = Fixture::new();
+    let taker = Pubkey::new_unique();
+
+    store
+        .apply_transaction(&source("make", 1), &[fixture.made()])
+        .unwrap();
+    let offers = store.offers(&all_offers()).unwrap();
+    assert_eq!(offers.len(), 1);
+    assert_eq!(offers[0].status, OfferStatus::Open);
+    assert_eq!(offers[0].token_a_offered_amount.as_deref(), Some("100"));
+
+    store
+        .apply_transaction(&source("take", 2), &[fixture.taken(taker)])
+        .unwrap();
+    let history = store.offer_history(&fixture.address).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].status, OfferStatus::Taken);
+    assert_eq!(history[0].taker, Some(taker.to_string()));
+    assert_eq!(history[0].created_signature.as_deref(), Some("make"));
+    assert_eq!(history[0].closed_signature.as_deref(), Some("take"));
+
+    let kinds: Vec<_> = store
+        .offer_events(&fixture.address)
+        .unwrap()
+        .into_iter()
+        .map(|event| event.kind)
+        .collect();
+    assert_eq!(kinds, ["made", "taken"]);
+    assert_eq!(store.last_signature().unwrap().as_deref(), Some("take"));
+}
+
+#[test]
+fn reused_address_keeps_one_row_per_lifetime() {
+    let store = Store::open_in_memory().unwrap();
+    let fixture = Fixture::new();
+
+    store
+        .apply_transaction(&source("make-1", 1), &[fixture.made()])
+        .unwrap();
+    store
+        .apply_transaction(&source("cancel-1", 2), &[fixture.cancelled()])
+        .unwrap();
+    store
+        .apply_transaction(&source("make-2", 3), &[fixture.made()])
+        .unwrap();
+
+    let history = store.offer_history(&fixture.address).unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].status, OfferStatus::Open);
+    assert_eq!(history[0].created_signature.as_deref(), Some("make-2"));
+    assert_eq!(history[1].status, OfferStatus::Cancelled);
+}
+
+#[test]
+fn snapshot_adds_unknown_offers_and_closes_vanished_ones() {
+    let store = Store::open_in_memory().unwrap();
+    let seen = Fixture::new();
+    let unseen = Fixture::new();
+
+    store
+        .apply_transaction(&source("make", 1), &[seen.made()])
+        .unwrap();
+    // `seen` was closed in a transaction the indexer missed, and `unseen`
+    // was created before the indexer's history starts.
+    store.apply_snapshot(10, &[unseen.account()]).unwrap();
+
+    assert_eq!(
+        store.offer_history(&seen.address).unwrap()[0].status,
+        OfferStatus::Closed
+    );
+    let unseen_row = &store.offer_history(&unseen.address).unwrap()[0];
+    assert_eq!(unseen_row.status, OfferStatus::Open);
+    assert_eq!(unseen_row.created_signature, None);
+
+    // Running the snapshot again changes nothing.
+    store.apply_snapshot(11, &[unseen.account()]).unwrap();
+    assert_eq!(store.offers(&all_offers()).unwrap().len(), 2);
+
+    // Catching up on the history replaces the placeholder and fills in why
+    // the other offer was closed.
+    store
+        .apply_transaction(&source("make-unseen", 2), &[unseen.made()])
+        .unwrap();
+    store
+        .apply_transaction(&source("cancel-seen", 3), &[seen.cancelled()])
+        .unwrap();
+
+    let unseen_history = store.offer_history(&unseen.address).unwrap();
+    assert_eq!(unseen_history.len(), 1);
+    assert_eq!(
+        unseen_history[0].created_signature.as_deref(),
+        Some("make-unseen")
+    );
+    let seen_history = store.offer_history(&seen.address).unwrap();
+    assert_eq!(seen_history.len(), 1);
+    assert_eq!(seen_history[0].status, OfferStatus::Cancelled);
+}
+
+#[test]
+fn close_without_creation_inserts_a_closed_row() {
+    let store = Store::open_in_memory().unwrap();
+    let fixture = Fixture::new();
+
+    store
+        .apply_transaction(&source("cancel", 5), &[fixture.cancelled()])
+        .unwrap();
+
+    let history = store.offer_history(&fixture.address).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].status, OfferStatus::Cancelled);
+    assert_eq!(history[0].id, None);
+}
+
+#[test]
+fn filters_offers() {
+    let store = Store::open_in_memory().unwrap();
+    let first = Fixture::new();
+    let second = Fixture::new();
+
+    store
+        .apply_transaction(&source("make-first", 1), &[first.made()])
+        .unwrap();
+    store
+        .apply_transaction(
+            &source("make-and-take-second", 2),
+            &[second.made(), second.taken(Pubkey::new_unique())],
+        )
+        .unwrap();
+
+    let by_maker = store
+        .offers(&OfferFilter {
+            maker: Some(first.maker),
+            ..all_offers()
+        })
+        .unwrap();
+    assert_eq!(by_maker.len(), 1);
+    assert_eq!(by_maker[0].address, first.address.to_string());
+
+    let by_mint = store
+        .offers(&OfferFilter {
+            token_mint_b: Some(second.token_mint_b),
+            ..all_offers()
+        })
+        .unwrap();
+    assert_eq!(by_mint.len(), 1);
+    assert_eq!(by_mint[0].address, second.address.to_string());
+
+    let taken = store
+        .offers(&OfferFilter {
+            status: Some(OfferStatus::Taken),
+            ..all_offers()
+        })
+        .unwrap();
+    assert_eq!(taken.len(), 1);
+    assert_eq!(taken[0].address, second.address.to_string());
+
+    let limited = store
+        .offers(&OfferFilter {
+            limit: 1,
+            ..OfferFilter::default()
+        })
+        .unwrap();
+    assert_eq!(limited.len(), 1);
+}
+
+#[test]
+fn filters_by_any_payment_mint() {
+    let store = Store::open_in_memory().unwrap();
+    let second_mint = Pubkey::new_unique();
+    let payment = PaymentOption {
+        mint: second_mint,
+        amount: 300,
+    };
+    let made = Fixture::new();
+    let snapshotted = Fixture::new();
+    let other = Fixture::new();
+
+    let EscrowEvent::Made(mut event) = made.made() else {
+        unreachable!()
+    };
+    event.other_payments.push(payment.clone());
+    store
+        .apply_transaction(
+            &source("make", 1),
+            &[EscrowEvent::Made(event), other.made()],
+        )
+        .unwrap();
+    let (address, mut account) = snapshotted.account();
+    account.other_payments.push(payment);
+    store
+        .apply_snapshot(2, &[made.account(), (address, account), other.account()])
+        .unwrap();
+
+    let by_mint = store
+        .offers(&OfferFilter {
+            token_mint_b: Some(second_mint),
+            ..all_offers()
+        })
+        .unwrap();
+    let addresses: Vec<_> = by_mint.iter().map(|row| &row.address).collect();
+    assert_eq!(
+        addresses,
+        [&snapshotted.address.to_string(), &made.address.to_string()]
+    );
+
+    let by_api = handle(&store, &format!("/offers?mint_b={second_mint}"));
+    assert_eq!(by_api.status, 200);
+    assert_eq!(by_api.body.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn filters_nft_listings_by_collection() {
+    let store = Store::open_in_memory().unwrap();
+    let collection = Pubkey::new_unique();
+    let listing = Fixture::new();
+    let offer = Fixture::new();
+    let snapshotted = Fixture::new();
+
+    store
+        .apply_transaction(
+            &source("list", 1),
+            &[listing.made(), listing.listed(collection)],
+        )
+        .unwrap();
+    store
+        .apply_transaction(&source("make", 2), &[offer.made()])
+        .unwrap();
+    let (address, mut account) = snapshotted.account();
+    account.nft_collection = Some(collection);
+    store
+        .apply_snapshot(3, &[listing.account(), offer.account(), (address, account)])
+        .unwrap();
+
+    let in_collection = store
+        .offers(&OfferFilter {
+            collection: Some(collection),
+            ..all_offers()
+        })
+        .unwrap();
+    let addresses: Vec<_> = in_collection.iter().map(|row| &row.address).collect();
+    assert_eq!(
+        addresses,
+        [
+            &snapshotted.address.to_string(),
+            &listing.address.to_string()
+        ]
+    );
+    assert_eq!(in_collection[1].collection, Some(collection.to_string()));
+
+    let events = store.offer_events(&listing.address).unwrap();
+    let kinds: Vec<_> = events.iter().map(|event| event.kind.as_str()).collect();
+    assert_eq!(kinds, ["made", "listed"]);
+
+    let by_api = handle(&store, &format!("/offers?collection={collection}"));
+    assert_eq!(by_api.status, 200);
+    assert_eq!(by_api.body.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn opens_databases_from_before_nft_listings() {
+    let path = std::env::temp_dir().join(format!("escrow-indexer-{}.db", Pubkey::new_unique()));
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE offers (
+                row_id INTEGER PRIMARY KEY,
+                address TEXT NOT NULL,
+                maker TEXT NOT NULL,
+                token_mint_a TEXT NOT NULL,
+                token_mint_b TEXT NOT NULL,
+                id TEXT,
+                token_a_offered_amount TEXT,
+                token_b_wanted_amount TEXT,
+                status TEXT NOT NULL,
+                taker TEXT,
+                created_slot INTEGER,
+                created_signature TEXT,
+                closed_slot INTEGER,
+                closed_signature TEXT
+            );",
+        )
+        .unwrap();
+
+    let store = Store::open(&path).unwrap();
+    let fixture = Fixture::new();
+    store
+        .apply_transaction(&source("make", 1), &[fixture.made()])
+        .unwrap();
+    let offers = store.offers(&all_offers()).unwrap();
+    assert_eq!(offers.len(), 1);
+    assert_eq!(offers[0].collection, None);
+
+    drop(store);
+    // Opening again finds the column already there.
+    Store::open(&path).unwrap();
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+}
+
+#[test]
+fn api_routes() {
+    let store = Store::open_in_memory().unwrap();
+    let fixture = Fixture::new();
+    store
+        .apply_transaction(&source("make", 1), &[fixture.made()])
+        .unwrap();
+
+    let health = handle(&store, "/health");
+    assert_eq!(health.status, 200);
+    assert_eq!(health.body["last_signature"], "make");
+
+    let offers = handle(
+        &store,
+        &format!("/offers?maker={}&status=open", fixture.maker),
+    );
+    assert_eq!(offers.status, 200);
+    assert_eq!(offers.body.as_array().unwrap().len(), 1);
+    assert_eq!(offers.body[0]["status"], "open");
+    assert_eq!(offers.body[0]["token_b_wanted_amount"], "200");
+
+    let details = handle(&store, &format!("/offers/{}", fixture.address));
+    assert_eq!(details.status, 200);
+    assert_eq!(details.body["offer"]["id"], "1");
+    assert_eq!(details.body["events"][0]["kind"], "made");
+    assert_eq!(details.body["history"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn api_errors() {
+    let store = Store::open_in_memory().unwrap();
+
+    assert_eq!(handle(&store, "/nope").status, 404);
+    assert_eq!(
+        handle(&store, &format!("/offers/{}", Pubkey::new_unique())).status,
+        404
+    );
+    assert_eq!(handle(&store, "/offers/not-a-key").status, 400);
+    assert_eq!(handle(&store, "/offers?status=lost").status, 400);
+    assert_eq!(handle(&store, "/offers?limit=many").status, 400);
+    assert_eq!(handle(&store, "/offers?colour=red").status, 400);
+}
@@ -0,0 +1,126 @@
+//! Following transactions against a mock JSON-RPC node.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use anchor_lang::{prelude::Pubkey, Event};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use escrow::OfferMade;
+use escrow_client::rpc::RpcClient;
+use escrow_indexer::{
+    store::{OfferFilter, Store},
+    sync::Indexer,
+};
+use serde_json::{json, Value};
+use tiny_http::{Response, Server};
+
+const OLDER: &str = "older-signature";
+const NEWER: &str = "newer-signature";
+
+fn offer_made(offer: Pubkey) -> OfferMade {
+    OfferMade {
+        offer,
+        maker: Pubkey::new_unique(),
+        id: 7,
+        token_mint_a: Pubkey::new_unique(),
+        token_mint_b: Pubkey::new_unique(),
+        token_a_offered_amount: 1_000,
+        token_b_wanted_amount: 2_000,
+        other_payments: Vec::new(),
+    }
+}
+
+fn transaction(slot: u64, event: &impl Event) -> Value {
+    json!({
+        "slot": slot,
+        "blockTime": 1_700_000_000 + slot as i64,
+        "meta": {
+            "err": null,
+            "logMessages": [
+                format!("Program {} invoke [1]", escrow::ID),
+                format!("Program data: {}", BASE64.encode(event.data())),
+                format!("Program {} success", escrow::ID),
+            ],
+        },
+    })
+}
+
+/// Serves two program transactions, newest first. `getTransaction` for the
+/// older one returns null the first `missing_for` times it is asked.
+fn mock_rpc(older: Value, newer: Value, missing_for: usize) -> String {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+    let older_requests = Arc::new(AtomicUsize::new(0));
+
+    thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let call: Value = serde_json::from_str(&body).unwrap();
+
+            let result = match call["method"].as_str().unwrap() {
+                "getSignaturesForAddress" if call["params"][1]["until"] == NEWER => json!([]),
+                "getSignaturesForAddress" => json!([
+                    { "signature": NEWER, "slot": 11, "err": null },
+                    { "signature": OLDER, "slot": 10, "err": null },
+                ]),
+                "getTransaction" if call["params"][0] == OLDER => {
+                    if older_requests.fetch_add(1, Ordering::SeqCst) < missing_for {
+                        Value::Null
+                    } else {
+                        older.clone()
+                    }
+                }
+                "getTransaction" => newer.clone(),
+                method => panic!("unexpected {method}"),
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": 1, "result": result });
+            request
+                .respond(Response::from_string(response.to_string()))
+                .unwrap();
+        }
+    });
+    url
+}
+
+#[test]
+fn retries_transactions_the_node_does_not_have_yet() {
+    let first = Pubkey::new_unique();
+    let second = Pubkey::new_unique();
+    let url = mock_rpc(
+        transaction(10, &offer_made(first)),
+        transaction(11, &offer_made(second)),
+        1,
+    );
+
+    let path = std::env::temp_dir().join(format!("escrow-indexer-sync-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let indexer = Indexer::new(RpcClient::new(url), Store::open(&path).unwrap(), escrow::ID);
+    let store = Store::open(&path).unwrap();
+    let all_offers = OfferFilter {
+        limit: 10,
+        ..Default::default()
+    };
+
+    // The older transaction is missing, so nothing is applied, not even the
+    // newer one, and the cursor stays where it was
+    assert_eq!(indexer.sync_transactions().unwrap(), 0);
+    assert_eq!(store.last_signature().unwrap(), None);
+    assert!(store.offers(&all_offers).unwrap().is_empty());
+
+    // Once the node has it, both are applied in order
+    assert_eq!(indexer.sync_transactions().unwrap(), 2);
+    assert_eq!(store.last_signature().unwrap().as_deref(), Some(NEWER));
+    let offers = store.offers(&all_offers).unwrap();
+    let addresses: Vec<_> = offers.iter().map(|offer| offer.address.as_str()).collect();
+    assert_eq!(addresses, [second.to_string(), first.to_string()]);
+    assert_eq!(offers[1].created_signature.as_deref(), Some(OLDER));
+
+    assert_eq!(indexer.sync_transactions().unwrap(), 0);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,12 @@
+//! Runs the escrow and mock oracle programs in LiteSVM, as built for SBF by
+//! `anchor build`, next to the SPL Token, Token-2022 and Associated Token
+//! Account programs LiteSVM ships with.
+//!
+//! The programs are loaded from `target/deploy`, or from `SBF_OUT_DIR` when
+//! it is set. Signatures are not verified, and a separate fee payer pays for
+//! every transaction, so the lamports of the accounts the tests look at only
+//! move as the programs move them.
+
+mod runtime;
+
+pub use runtime::{Account, ExecutionError, Runtime};
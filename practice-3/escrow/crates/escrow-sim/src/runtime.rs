@@ -0,0 +1,232 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anchor_lang::{
+    prelude::{Clock, ProgramError, Pubkey, Rent},
+    solana_program::{
+        instruction::{Instruction, InstructionError},
+        message::Message,
+    },
+    system_program,
+};
+use litesvm::LiteSVM;
+use solana_keypair::Keypair;
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+use solana_transaction_error::TransactionError;
+
+const FEE_PAYER_LAMPORTS: u64 = 1_000_000_000_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+impl From<solana_account::Account> for Account {
+    fn from(account: solana_account::Account) -> Self {
+        Self {
+            lamports: account.lamports,
+            data: account.data,
+            owner: account.owner,
+            executable: account.executable,
+        }
+    }
+}
+
+impl From<Account> for solana_account::Account {
+    fn from(account: Account) -> Self {
+        Self {
+            lamports: account.lamports,
+            data: account.data,
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: u64::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutionError {
+    /// Returned by a program, including from a CPI.
+    Program(ProgramError),
+    /// A rule the runtime enforces around the programs, such as which
+    /// accounts a program may change.
+    Runtime(InstructionError),
+    /// The transaction was rejected before its instructions ran.
+    Transaction(TransactionError),
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionError::Program(error) => write!(f, "program error: {error}"),
+            ExecutionError::Runtime(error) => write!(f, "runtime error: {error}"),
+            ExecutionError::Transaction(error) => write!(f, "transaction error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+impl From<TransactionError> for ExecutionError {
+    fn from(error: TransactionError) -> Self {
+        match error {
+            TransactionError::InstructionError(_, error) => ProgramError::try_from(error)
+                .map_or_else(ExecutionError::Runtime, ExecutionError::Program),
+            error => ExecutionError::Transaction(error),
+        }
+    }
+}
+
+pub struct Runtime {
+    svm: LiteSVM,
+    fee_payer: Keypair,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Runtime {
+    /// A runtime with the escrow and mock oracle programs deployed, next to
+    /// the System, SPL Token, Token-2022 and Associated Token Account
+    /// programs.
+    ///
+    /// Panics if the programs have not been built.
+    pub fn new() -> Self {
+        let mut svm = LiteSVM::new()
+            .with_sigverify(false)
+            // Tests send the same transaction twice to see it fail the
+            // second time, which the history would reject as a duplicate.
+            .with_transaction_history(0);
+        for (program_id, name) in [(escrow::ID, "escrow"), (mock_oracle::ID, "mock_oracle")] {
+            let path = program_dir().join(format!("{name}.so"));
+            svm.add_program_from_file(program_id, &path)
+                .unwrap_or_else(|error| {
+                    panic!(
+                        "Failed to load {}: {error}. Build the programs with `anchor build`.",
+                        path.display()
+                    )
+                });
+        }
+
+        let mut runtime = Self {
+            svm,
+            fee_payer: Keypair::new(),
+        };
+        runtime.airdrop(&runtime.fee_payer.pubkey(), FEE_PAYER_LAMPORTS);
+        runtime
+    }
+
+    pub fn rent(&self) -> Rent {
+        self.svm.get_sysvar()
+    }
+
+    pub fn clock(&self) -> Clock {
+        self.svm.get_sysvar()
+    }
+
+    /// Sets the clock sysvar programs read in later transactions.
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.svm.set_sysvar(&clock);
+    }
+
+    pub fn account(&self, address: &Pubkey) -> Option<Account> {
+        self.svm.get_account(address).map(Account::from)
+    }
+
+    /// Every account but the fee payer's, including programs and sysvars.
+    pub fn accounts(&self) -> impl Iterator<Item = (Pubkey, Account)> + '_ {
+        let fee_payer = self.fee_payer.pubkey();
+        self.svm
+            .accounts_db()
+            .inner
+            .keys()
+            .filter(move |address| **address != fee_payer)
+            .filter_map(|address| Some((*address, self.account(address)?)))
+    }
+
+    pub fn lamports(&self, address: &Pubkey) -> u64 {
+        self.svm.get_balance(address).unwrap_or(0)
+    }
+
+    pub fn set_account(&mut self, address: Pubkey, account: Account) {
+        self.svm
+            .set_account(address, account.into())
+            .unwrap_or_else(|error| panic!("Failed to set {address}: {error:?}"));
+    }
+
+    /// Credits `lamports` to a system account, creating it if needed.
+    pub fn airdrop(&mut self, address: &Pubkey, lamports: u64) {
+        let mut account = self.account(address).unwrap_or_else(|| Account {
+            owner: system_program::ID,
+            ..Account::default()
+        });
+        account.lamports += lamports;
+        self.set_account(*address, account);
+    }
+
+    /// Lamports in every account but the fee payer's, which transactions
+    /// only change by their fees.
+    pub fn total_lamports(&self) -> u128 {
+        self.accounts()
+            .map(|(_, account)| account.lamports as u128)
+            .sum()
+    }
+
+    /// Executes `instructions` in one transaction. Either all of them
+    /// succeed, or the accounts are left as they were. Signatures are not
+    /// verified: every key in `signers` counts as having signed.
+    pub fn process_transaction(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[Pubkey],
+    ) -> Result<(), ExecutionError> {
+        self.process_transaction_metered(instructions, signers)
+            .map(|_| ())
+    }
+
+    /// [`Runtime::process_transaction`], returning the compute units the
+    /// transaction consumed.
+    pub fn process_transaction_metered(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[Pubkey],
+    ) -> Result<u64, ExecutionError> {
+        // Without signature verification, LiteSVM takes every signer the
+        // instructions ask for as present.
+        for meta in instructions
+            .iter()
+            .flat_map(|instruction| &instruction.accounts)
+        {
+            if meta.is_signer && !signers.contains(&meta.pubkey) {
+                return Err(ExecutionError::Program(
+                    ProgramError::MissingRequiredSignature,
+                ));
+            }
+        }
+
+        let message = Message::new_with_blockhash(
+            instructions,
+            Some(&self.fee_payer.pubkey()),
+            &self.svm.latest_blockhash(),
+        );
+        self.svm
+            .send_transaction(Transaction::new_unsigned(message))
+            .map(|meta| meta.compute_units_consumed)
+            .map_err(|failed| failed.err.into())
+    }
+}
+
+fn program_dir() -> PathBuf {
+    std::env::var_os("SBF_OUT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy"))
+}
@@ -0,0 +1,99 @@
+//! The runtime rejects account changes the program itself does not check:
+//! SPL Token relies on it to keep foreign accounts that merely look like
+//! token accounts untouched.
+
+mod common;
+
+use anchor_lang::{prelude::Pubkey, solana_program::instruction::InstructionError};
+use anchor_spl::associated_token::get_associated_token_address;
+use common::Fixture;
+use escrow_sim::{Account, ExecutionError};
+
+const AMOUNT: u64 = 1_000;
+
+impl Fixture {
+    /// A copy of the taker's token B account at a new address, owned by
+    /// `owner`.
+    fn copy_of_taker_token_account(&mut self, owner: Pubkey) -> Pubkey {
+        let original = get_associated_token_address(&self.taker, &self.mint_b);
+        let account = self.runtime.account(&original).unwrap();
+        let address = Pubkey::new_unique();
+        self.runtime
+            .set_account(address, Account { owner, ..account });
+        address
+    }
+}
+
+#[test]
+fn token_program_moves_tokens_between_its_own_accounts() {
+    let mut fixture = Fixture::new();
+    let source = fixture.copy_of_taker_token_account(spl_token::ID);
+    let destination = fixture.copy_of_taker_token_account(spl_token::ID);
+
+    let transfer = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &source,
+        &destination,
+        &fixture.taker,
+        &[],
+        AMOUNT,
+    )
+    .unwrap();
+    fixture
+        .runtime
+        .process_transaction(&[transfer], &[fixture.taker])
+        .unwrap();
+}
+
+#[test]
+fn only_the_owner_changes_account_data() {
+    let mut fixture = Fixture::new();
+    let source = fixture.copy_of_taker_token_account(Pubkey::new_unique());
+    let destination = fixture.copy_of_taker_token_account(spl_token::ID);
+    let before = fixture.runtime.account(&source);
+
+    let transfer = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &source,
+        &destination,
+        &fixture.taker,
+        &[],
+        AMOUNT,
+    )
+    .unwrap();
+    assert_eq!(
+        fixture
+            .runtime
+            .process_transaction(&[transfer], &[fixture.taker]),
+        Err(ExecutionError::Runtime(
+            InstructionError::ExternalAccountDataModified
+        ))
+    );
+    assert_eq!(fixture.runtime.account(&source), before);
+}
+
+#[test]
+fn read_only_accounts_do_not_change() {
+    let mut fixture = Fixture::new();
+    let source = fixture.copy_of_taker_token_account(spl_token::ID);
+    let destination = fixture.copy_of_taker_token_account(spl_token::ID);
+
+    let mut transfer = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &source,
+        &destination,
+        &fixture.taker,
+        &[],
+        AMOUNT,
+    )
+    .unwrap();
+    transfer.accounts[1].is_writable = false;
+    assert_eq!(
+        fixture
+            .runtime
+            .process_transaction(&[transfer], &[fixture.taker]),
+        Err(ExecutionError::Runtime(
+            InstructionError::ReadonlyDataModified
+        ))
+    );
+}
@@ -0,0 +1,287 @@
+//! An open offer in the LiteSVM runtime, shared by the test files that
+//! exercise single instructions rather than random sequences.
+
+#![allow(dead_code)]
+
+use anchor_lang::{
+    prelude::Pubkey, solana_program::instruction::Instruction, system_program, AccountSerialize,
+    InstructionData, ToAccountMetas,
+};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use escrow::{Config, Offer, PaymentOption, PegTerms, CONFIG_SEED};
+use escrow_sim::{Account, Runtime};
+use spl_token::{
+    solana_program::{program_option::COption, program_pack::Pack},
+    state::{Account as TokenAccount, Mint},
+};
+
+pub const STARTING_LAMPORTS: u64 = 10_000_000_000;
+pub const OFFER_ID: u64 = 7;
+pub const TOKEN_A_OFFERED_AMOUNT: u64 = 2_500_000_000;
+pub const TOKEN_B_WANTED_AMOUNT: u64 = 5_000_000;
+/// Twice the wanted amount, so the taker can also afford any referral fee.
+pub const TAKER_TOKEN_B_BALANCE: u64 = 2 * TOKEN_B_WANTED_AMOUNT;
+
+pub struct Fixture {
+    pub runtime: Runtime,
+    pub admin: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    /// Mints the offer accepts besides `mint_b`, see [`Fixture::with_other_payments`].
+    pub other_mints: Vec<Pubkey>,
+    /// Mock oracle feed of a pegged offer, see [`Fixture::pegged`].
+    pub price_feed: Option<Pubkey>,
+    pub offer: Pubkey,
+}
+
+impl Fixture {
+    /// An open offer of 2.5 token A (9 decimals) for 5 token B (6 decimals),
+    /// and a taker who holds `TAKER_TOKEN_B_BALANCE` but no token A account.
+    pub fn new() -> Self {
+        Self::build(&[], None)
+    }
+
+    /// Like [`Fixture::new`], but the offer also accepts a new 6 decimal mint
+    /// for each of `amounts`, of which the taker holds twice the amount.
+    pub fn with_other_payments(amounts: &[u64]) -> Self {
+        Self::build(amounts, None)
+    }
+
+    /// Like [`Fixture::new`], but token B is pegged with `terms` to a mock
+    /// oracle feed the admin publishes, starting at `price * 10^exponent`
+    /// token B per token A with no confidence interval. The wanted amount
+    /// stays `TOKEN_B_WANTED_AMOUNT`, as the floor.
+    pub fn pegged(price: i64, exponent: i32, terms: PegTerms) -> Self {
+        Self::build(&[], Some((price, exponent, terms)))
+    }
+
+    fn build(amounts: &[u64], peg: Option<(i64, i32, PegTerms)>) -> Self {
+        let mut runtime = Runtime::new();
+        let maker = Pubkey::new_unique();
+        let taker = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let mint_authority = Pubkey::new_unique();
+        for user in [admin, maker, taker, mint_authority] {
+            runtime.airdrop(&user, STARTING_LAMPORTS);
+        }
+
+        let (config, bump) = Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID);
+        let mut data = Vec::new();
+        Config {
+            admin,
+            paused: false,
+            mint_allowlist_enabled: false,
+            max_referral_fee_bps: 0,
+            bump,
+        }
+        .try_serialize(&mut data)
+        .unwrap();
+        set_rent_exempt(&mut runtime, config, data, escrow::ID);
+
+        let mut create_mint = |decimals| {
+            let mint = Pubkey::new_unique();
+            let mut data = vec![0; Mint::LEN];
+            Mint {
+                mint_authority: COption::Some(mint_authority),
+                supply: 0,
+                decimals,
+                is_initialized: true,
+                freeze_authority: COption::None,
+            }
+            .pack_into_slice(&mut data);
+            set_rent_exempt(&mut runtime, mint, data, spl_token::ID);
+            mint
+        };
+        let mint_a = create_mint(9);
+        let mint_b = create_mint(6);
+        let other_payments: Vec<_> = amounts
+            .iter()
+            .map(|&amount| PaymentOption {
+                mint: create_mint(6),
+                amount,
+            })
+            .collect();
+
+        let mut setup = Vec::new();
+        let balances = [
+            (maker, mint_a, TOKEN_A_OFFERED_AMOUNT),
+            (taker, mint_b, TAKER_TOKEN_B_BALANCE),
+        ]
+        .into_iter()
+        .chain(
+            other_payments
+                .iter()
+                .map(|option| (taker, option.mint, 2 * option.amount)),
+        );
+        for (owner, mint, amount) in balances {
+            setup.push(
+                spl_associated_token_account::instruction::create_associated_token_account(
+                    &mint_authority,
+                    &owner,
+                    &mint,
+                    &spl_token::ID,
+                ),
+            );
+            setup.push(
+                spl_token::instruction::mint_to(
+                    &spl_token::ID,
+                    &mint,
+                    &get_associated_token_address(&owner, &mint),
+                    &mint_authority,
+                    &[],
+                    amount,
+                )
+                .unwrap(),
+            );
+        }
+        runtime
+            .process_transaction(&setup, &[mint_authority])
+            .expect("setup failed");
+
+        let price_feed = peg.map(|(price, exponent, _)| {
+            let price_feed = Pubkey::new_unique();
+            let initialize = Instruction {
+                program_id: mock_oracle::ID,
+                accounts: mock_oracle::accounts::InitializePriceFeed {
+                    authority: admin,
+                    price_feed,
+                    system_program: system_program::ID,
+                }
+                .to_account_metas(None),
+                data: mock_oracle::instruction::InitializePriceFeed {
+                    price,
+                    confidence: 0,
+                    exponent,
+                }
+                .data(),
+            };
+            runtime
+                .process_transaction(&[initialize], &[admin, price_feed])
+                .expect("initialize_price_feed failed");
+            price_feed
+        });
+
+        let (offer, _) = Offer::pda(&maker, OFFER_ID);
+        let make_offer = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::MakeOffer {
+                maker,
+                token_mint_a: mint_a,
+                token_mint_b: mint_b,
+                maker_token_account_a: get_associated_token_address(&maker, &mint_a),
+                offer,
+                vault: get_associated_token_address(&offer, &mint_a),
+                config,
+                mint_registry: None,
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                price_feed,
+                nft_metadata: None,
+                rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::MakeOffer {
+                id: OFFER_ID,
+                token_a_offered_amount: TOKEN_A_OFFERED_AMOUNT,
+                token_b_wanted_amount: TOKEN_B_WANTED_AMOUNT,
+                other_payments: other_payments.clone(),
+                peg: peg.map(|(_, _, terms)| terms),
+            }
+            .data(),
+        };
+        runtime
+            .process_transaction(&[make_offer], &[maker])
+            .expect("make_offer failed");
+
+        Self {
+            runtime,
+            admin,
+            maker,
+            taker,
+            mint_a,
+            mint_b,
+            other_mints: other_payments.iter().map(|option| option.mint).collect(),
+            price_feed,
+            offer,
+        }
+    }
+
+    pub fn take_offer(&self, fill_receipt: Option<Pubkey>) -> Instruction {
+        self.take_offer_with(fill_receipt, None, 0)
+    }
+
+    /// `take_offer` with every optional account and argument spelled out.
+    pub fn take_offer_with(
+        &self,
+        fill_receipt: Option<Pubkey>,
+        referrer: Option<Pubkey>,
+        referral_fee_bps: u16,
+    ) -> Instruction {
+        self.take_offer_paying(&self.mint_b, fill_receipt, referrer, referral_fee_bps)
+    }
+
+    /// `take_offer_with`, paying in `mint` instead of `mint_b`.
+    pub fn take_offer_paying(
+        &self,
+        mint: &Pubkey,
+        fill_receipt: Option<Pubkey>,
+        referrer: Option<Pubkey>,
+        referral_fee_bps: u16,
+    ) -> Instruction {
+        Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::TakeOffer {
+                taker: self.taker,
+                maker: self.maker,
+                token_mint_a: self.mint_a,
+                token_mint_b: *mint,
+                taker_token_account_a: get_associated_token_address(&self.taker, &self.mint_a),
+                taker_token_account_b: get_associated_token_address(&self.taker, mint),
+                maker_token_account_b: get_associated_token_address(&self.maker, mint),
+                offer: self.offer,
+                vault: get_associated_token_address(&self.offer, &self.mint_a),
+                config: Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0,
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                fill_receipt,
+                referrer,
+                referrer_token_account_b: referrer
+                    .map(|referrer| get_associated_token_address(&referrer, mint)),
+                price_feed: self.price_feed,
+                nft_metadata: None,
+                rent_payer: None,
+                offer_rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::TakeOffer { referral_fee_bps }.data(),
+        }
+    }
+
+    pub fn token_balance(&self, owner: &Pubkey, mint: &Pubkey) -> Option<u64> {
+        let account = self
+            .runtime
+            .account(&get_associated_token_address(owner, mint))?;
+        Some(TokenAccount::unpack(&account.data).unwrap().amount)
+    }
+
+    pub fn token_account_rent(&self) -> u64 {
+        self.runtime.rent().minimum_balance(TokenAccount::LEN)
+    }
+}
+
+pub fn set_rent_exempt(runtime: &mut Runtime, address: Pubkey, data: Vec<u8>, owner: Pubkey) {
+    let lamports = runtime.rent().minimum_balance(data.len());
+    runtime.set_account(
+        address,
+        Account {
+            lamports,
+            data,
+            owner,
+            executable: false,
+        },
+    );
+}
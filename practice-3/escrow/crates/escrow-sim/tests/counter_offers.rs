@@ -0,0 +1,319 @@
+//! Counter offers: made by a prospective taker against an open offer, then
+//! accepted or rejected by the maker, or withdrawn by the counter party.
+
+mod common;
+
+use anchor_lang::{
+    prelude::{ProgramError, Pubkey},
+    solana_program::instruction::Instruction,
+    system_program, AccountDeserialize, InstructionData, ToAccountMetas,
+};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use common::{Fixture, OFFER_ID, STARTING_LAMPORTS, TAKER_TOKEN_B_BALANCE, TOKEN_A_OFFERED_AMOUNT};
+use escrow::{error::ErrorCode, CounterOffer, CONFIG_SEED};
+use escrow_sim::ExecutionError;
+
+/// Less than the 5 token B the maker wants.
+const COUNTER_AMOUNT: u64 = 4_000_000;
+
+impl Fixture {
+    fn counter_offer(&self) -> Pubkey {
+        CounterOffer::pda(&self.offer, &self.taker).0
+    }
+
+    fn counter_vault(&self) -> Pubkey {
+        get_associated_token_address(&self.counter_offer(), &self.mint_b)
+    }
+
+    fn config(&self) -> Pubkey {
+        Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0
+    }
+
+    fn make_counter_offer(&mut self, token_b_amount: u64) -> Result<(), ExecutionError> {
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::MakeCounterOffer {
+                counter_party: self.taker,
+                offer: self.offer,
+                vault: get_associated_token_address(&self.offer, &self.mint_a),
+                token_mint_a: self.mint_a,
+                token_mint_b: self.mint_b,
+                counter_party_token_account_b: get_associated_token_address(
+                    &self.taker,
+                    &self.mint_b,
+                ),
+                counter_offer: self.counter_offer(),
+                counter_vault: self.counter_vault(),
+                config: self.config(),
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::MakeCounterOffer { token_b_amount }.data(),
+        };
+        self.runtime
+            .process_transaction(&[instruction], &[self.taker])
+    }
+
+    fn accept_counter_offer(&mut self, signer: Pubkey) -> Result<(), ExecutionError> {
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::AcceptCounterOffer {
+                maker: signer,
+                counter_party: self.taker,
+                token_mint_a: self.mint_a,
+                token_mint_b: self.mint_b,
+                counter_party_token_account_a: get_associated_token_address(
+                    &self.taker,
+                    &self.mint_a,
+                ),
+                maker_token_account_b: get_associated_token_address(&signer, &self.mint_b),
+                offer: self.offer,
+                vault: get_associated_token_address(&self.offer, &self.mint_a),
+                counter_offer: self.counter_offer(),
+                counter_vault: self.counter_vault(),
+                config: self.config(),
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                offer_rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::AcceptCounterOffer {}.data(),
+        };
+        self.runtime.process_transaction(&[instruction], &[signer])
+    }
+
+    fn reject_counter_offer(&mut self, signer: Pubkey) -> Result<(), ExecutionError> {
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::RejectCounterOffer {
+                maker: signer,
+                counter_party: self.taker,
+                token_mint_b: self.mint_b,
+                counter_party_token_account_b: get_associated_token_address(
+                    &self.taker,
+                    &self.mint_b,
+                ),
+                offer: self.offer,
+                counter_offer: self.counter_offer(),
+                counter_vault: self.counter_vault(),
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::RejectCounterOffer {}.data(),
+        };
+        self.runtime.process_transaction(&[instruction], &[signer])
+    }
+
+    fn withdraw_counter_offer(&mut self, signer: Pubkey) -> Result<(), ExecutionError> {
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::WithdrawCounterOffer {
+                counter_party: signer,
+                token_mint_b: self.mint_b,
+                counter_party_token_account_b: get_associated_token_address(&signer, &self.mint_b),
+                counter_offer: self.counter_offer(),
+                counter_vault: self.counter_vault(),
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::WithdrawCounterOffer {}.data(),
+        };
+        self.runtime.process_transaction(&[instruction], &[signer])
+    }
+
+    /// Cancels the offer and makes a new one at the same address, for
+    /// `token_a_offered_amount` token A.
+    fn replace_offer(&mut self, token_a_offered_amount: u64) {
+        let vault = get_associated_token_address(&self.offer, &self.mint_a);
+        let maker_token_account_a = get_associated_token_address(&self.maker, &self.mint_a);
+        let cancel = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::CancelOffer {
+                maker: self.maker,
+                token_mint_a: self.mint_a,
+                maker_token_account_a,
+                offer: self.offer,
+                vault,
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                offer_rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::CancelOffer {}.data(),
+        };
+        let make = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::MakeOffer {
+                maker: self.maker,
+                token_mint_a: self.mint_a,
+                token_mint_b: self.mint_b,
+                maker_token_account_a,
+                offer: self.offer,
+                vault,
+                config: self.config(),
+                mint_registry: None,
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                price_feed: None,
+                nft_metadata: None,
+                rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::MakeOffer {
+                id: OFFER_ID,
+                token_a_offered_amount,
+                token_b_wanted_amount: COUNTER_AMOUNT,
+                other_payments: Vec::new(),
+                peg: None,
+            }
+            .data(),
+        };
+        self.runtime
+            .process_transaction(&[cancel, make], &[self.maker])
+            .expect("replacing the offer failed");
+    }
+}
+
+fn program_error(error: ErrorCode) -> ExecutionError {
+    ExecutionError::Program(ProgramError::from(anchor_lang::error::Error::from(error)))
+}
+
+#[test]
+fn counter_offer_escrows_the_proposed_amount() {
+    let mut fixture = Fixture::new();
+    fixture.make_counter_offer(COUNTER_AMOUNT).unwrap();
+
+    let account = fixture.runtime.account(&fixture.counter_offer()).unwrap();
+    let counter_offer = CounterOffer::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(counter_offer.offer, fixture.offer);
+    assert_eq!(counter_offer.counter_party, fixture.taker);
+    assert_eq!(counter_offer.token_a_amount, TOKEN_A_OFFERED_AMOUNT);
+    assert_eq!(counter_offer.token_mint_b, fixture.mint_b);
+    assert_eq!(counter_offer.token_b_amount, COUNTER_AMOUNT);
+
+    assert_eq!(
+        fixture.token_balance(&fixture.counter_offer(), &fixture.mint_b),
+        Some(COUNTER_AMOUNT)
+    );
+    assert_eq!(
+        fixture.token_balance(&fixture.taker, &fixture.mint_b),
+        Some(TAKER_TOKEN_B_BALANCE - COUNTER_AMOUNT)
+    );
+}
+
+#[test]
+fn accepting_settles_both_vaults() {
+    let mut fixture = Fixture::new();
+    fixture.make_counter_offer(COUNTER_AMOUNT).unwrap();
+    let maker_lamports = fixture.runtime.lamports(&fixture.maker);
+    let offer_rent = fixture.runtime.lamports(&fixture.offer);
+    let token_account_rent = fixture.token_account_rent();
+
+    fixture.accept_counter_offer(fixture.maker).unwrap();
+
+    assert_eq!(
+        fixture.token_balance(&fixture.maker, &fixture.mint_b),
+        Some(COUNTER_AMOUNT)
+    );
+    assert_eq!(
+        fixture.token_balance(&fixture.taker, &fixture.mint_a),
+        Some(TOKEN_A_OFFERED_AMOUNT)
+    );
+    for closed in [
+        fixture.offer,
+        get_associated_token_address(&fixture.offer, &fixture.mint_a),
+        fixture.counter_offer(),
+        fixture.counter_vault(),
+    ] {
+        assert!(fixture.runtime.account(&closed).is_none());
+    }
+
+    // The maker gets back the offer and its vault, and pays for the maker's
+    // token B account and the counter party's token A account.
+    assert_eq!(
+        fixture.runtime.lamports(&fixture.maker),
+        maker_lamports + offer_rent - token_account_rent
+    );
+    // The counter party gets back the counter offer and its vault.
+    assert_eq!(fixture.runtime.lamports(&fixture.taker), STARTING_LAMPORTS);
+}
+
+#[test]
+fn rejecting_refunds_the_counter_party() {
+    let mut fixture = Fixture::new();
+    fixture.make_counter_offer(COUNTER_AMOUNT).unwrap();
+
+    fixture.reject_counter_offer(fixture.maker).unwrap();
+
+    assert_eq!(
+        fixture.token_balance(&fixture.taker, &fixture.mint_b),
+        Some(TAKER_TOKEN_B_BALANCE)
+    );
+    assert_eq!(fixture.runtime.lamports(&fixture.taker), STARTING_LAMPORTS);
+    assert!(fixture.runtime.account(&fixture.counter_offer()).is_none());
+    assert!(fixture.runtime.account(&fixture.counter_vault()).is_none());
+    // The offer itself stays open.
+    assert!(fixture.runtime.account(&fixture.offer).is_some());
+}
+
+#[test]
+fn counter_party_withdraws_even_after_the_offer_is_gone() {
+    let mut fixture = Fixture::new();
+    fixture.make_counter_offer(COUNTER_AMOUNT).unwrap();
+    let instruction = fixture.take_offer(None);
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .expect("take_offer failed");
+
+    fixture.withdraw_counter_offer(fixture.taker).unwrap();
+
+    assert!(fixture.runtime.account(&fixture.counter_offer()).is_none());
+    assert_eq!(
+        fixture.token_balance(&fixture.taker, &fixture.mint_a),
+        Some(TOKEN_A_OFFERED_AMOUNT)
+    );
+}
+
+#[test]
+fn only_the_maker_accepts_or_rejects() {
+    let mut fixture = Fixture::new();
+    fixture.make_counter_offer(COUNTER_AMOUNT).unwrap();
+    let stranger = Pubkey::new_unique();
+    fixture.runtime.airdrop(&stranger, STARTING_LAMPORTS);
+
+    assert!(fixture.accept_counter_offer(stranger).is_err());
+    assert!(fixture.reject_counter_offer(stranger).is_err());
+    assert!(fixture.withdraw_counter_offer(stranger).is_err());
+    assert!(fixture.runtime.account(&fixture.counter_offer()).is_some());
+}
+
+#[test]
+fn counter_offer_must_escrow_an_accepted_mint() {
+    let mut fixture = Fixture::new();
+    assert_eq!(
+        fixture.make_counter_offer(0),
+        Err(program_error(ErrorCode::InvalidCounterOfferAmount))
+    );
+    assert!(fixture
+        .make_counter_offer(TAKER_TOKEN_B_BALANCE + 1)
+        .is_err());
+}
+
+#[test]
+fn counter_offer_for_a_replaced_offer_can_not_be_accepted() {
+    let mut fixture = Fixture::new();
+    fixture.make_counter_offer(COUNTER_AMOUNT).unwrap();
+
+    fixture.replace_offer(TOKEN_A_OFFERED_AMOUNT / 2);
+    assert_eq!(
+        fixture.accept_counter_offer(fixture.maker),
+        Err(program_error(ErrorCode::CounterOfferOutdated))
+    );
+    fixture.withdraw_counter_offer(fixture.taker).unwrap();
+}
@@ -0,0 +1,185 @@
+//! Fill receipts written by `take_offer` and closed by `close_fill_receipt`.
+
+mod common;
+
+use anchor_lang::{
+    prelude::{Clock, Pubkey},
+    solana_program::instruction::Instruction,
+    AccountDeserialize, InstructionData, Space, ToAccountMetas,
+};
+use common::{Fixture, STARTING_LAMPORTS, TOKEN_A_OFFERED_AMOUNT, TOKEN_B_WANTED_AMOUNT};
+use escrow::{FillReceipt, ANCHOR_DISCRIMINATOR};
+
+impl Fixture {
+    fn close_fill_receipt(&self, signer: Pubkey) -> Instruction {
+        Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::CloseFillReceipt {
+                taker: signer,
+                fill_receipt: self.receipt_address(),
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::CloseFillReceipt {}.data(),
+        }
+    }
+
+    fn receipt_address(&self) -> Pubkey {
+        FillReceipt::pda(&self.offer, &self.taker).0
+    }
+
+    fn receipt(&self) -> Option<FillReceipt> {
+        let account = self.runtime.account(&self.receipt_address())?;
+        Some(FillReceipt::try_deserialize(&mut account.data.as_slice()).unwrap())
+    }
+
+    fn receipt_rent(&self) -> u64 {
+        self.runtime
+            .rent()
+            .minimum_balance(ANCHOR_DISCRIMINATOR + FillReceipt::INIT_SPACE)
+    }
+}
+
+#[test]
+fn take_offer_records_the_fill_at_the_takers_expense() {
+    let mut fixture = Fixture::new();
+    fixture.runtime.set_clock(Clock {
+        slot: 4_242,
+        unix_timestamp: 1_700_000_000,
+        ..Clock::default()
+    });
+
+    let instruction = fixture.take_offer(Some(fixture.receipt_address()));
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .expect("take_offer failed");
+
+    let receipt = fixture.receipt().expect("no receipt was written");
+    assert_eq!(receipt.offer, fixture.offer);
+    assert_eq!(receipt.maker, fixture.maker);
+    assert_eq!(receipt.taker, fixture.taker);
+    assert_eq!(receipt.token_mint_a, fixture.mint_a);
+    assert_eq!(receipt.token_mint_b, fixture.mint_b);
+    assert_eq!(receipt.token_a_amount, TOKEN_A_OFFERED_AMOUNT);
+    assert_eq!(receipt.token_b_amount, TOKEN_B_WANTED_AMOUNT);
+    assert_eq!(receipt.token_a_decimals, 9);
+    assert_eq!(receipt.token_b_decimals, 6);
+    // 5 token B for 2.5 token A is 2 token B per token A.
+    assert_eq!(receipt.price, 2_000_000);
+    assert_eq!(receipt.slot, 4_242);
+    assert_eq!(receipt.unix_timestamp, 1_700_000_000);
+    assert_eq!(
+        fixture.runtime.account(&fixture.offer),
+        None,
+        "the offer outlived the take"
+    );
+
+    // The taker pays for the receipt and both token accounts `take_offer`
+    // creates, the maker's token B account and the taker's token A account.
+    assert_eq!(
+        fixture.runtime.lamports(&fixture.taker),
+        STARTING_LAMPORTS - fixture.receipt_rent() - 2 * fixture.token_account_rent()
+    );
+    assert_eq!(
+        fixture.runtime.lamports(&fixture.receipt_address()),
+        fixture.receipt_rent()
+    );
+}
+
+#[test]
+fn closing_a_receipt_returns_its_rent_to_the_taker() {
+    let mut fixture = Fixture::new();
+    let instruction = fixture.take_offer(Some(fixture.receipt_address()));
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .expect("take_offer failed");
+    let taker_lamports = fixture.runtime.lamports(&fixture.taker);
+
+    let instruction = fixture.close_fill_receipt(fixture.taker);
+    fixture
+        .runtime
+        .process_transaction(std::slice::from_ref(&instruction), &[fixture.taker])
+        .expect("close_fill_receipt failed");
+
+    assert!(fixture.receipt().is_none(), "the receipt was not closed");
+    assert_eq!(
+        fixture.runtime.lamports(&fixture.taker),
+        taker_lamports + fixture.receipt_rent()
+    );
+    assert!(
+        fixture
+            .runtime
+            .process_transaction(&[instruction], &[fixture.taker])
+            .is_err(),
+        "a closed receipt was closed again"
+    );
+}
+
+#[test]
+fn only_the_taker_can_close_a_receipt() {
+    let mut fixture = Fixture::new();
+    let instruction = fixture.take_offer(Some(fixture.receipt_address()));
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .expect("take_offer failed");
+
+    for signer in [fixture.maker, Pubkey::new_unique()] {
+        fixture.runtime.airdrop(&signer, STARTING_LAMPORTS);
+        let instruction = fixture.close_fill_receipt(signer);
+        assert!(
+            fixture
+                .runtime
+                .process_transaction(&[instruction], &[signer])
+                .is_err(),
+            "{signer} closed the taker's receipt"
+        );
+    }
+    assert!(fixture.receipt().is_some());
+}
+
+#[test]
+fn take_offer_without_a_receipt_leaves_none_behind() {
+    let mut fixture = Fixture::new();
+    let instruction = fixture.take_offer(None);
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .expect("take_offer failed");
+
+    assert!(fixture.receipt().is_none());
+    assert_eq!(
+        fixture.runtime.lamports(&fixture.taker),
+        STARTING_LAMPORTS - 2 * fixture.token_account_rent()
+    );
+}
+
+#[test]
+fn take_offer_accepts_callers_that_omit_the_receipt_account() {
+    let mut fixture = Fixture::new();
+    let mut instruction = fixture.take_offer(None);
+    instruction.accounts.pop();
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .expect("take_offer without its trailing optional account failed");
+
+    assert!(fixture.receipt().is_none());
+    assert!(fixture.runtime.account(&fixture.offer).is_none());
+}
+
+#[test]
+fn receipt_address_must_match_the_offer_and_taker() {
+    let mut fixture = Fixture::new();
+    let wrong_address = FillReceipt::pda(&fixture.offer, &fixture.maker).0;
+    let instruction = fixture.take_offer(Some(wrong_address));
+    assert!(fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .is_err());
+    assert!(
+        fixture.runtime.account(&fixture.offer).is_some(),
+        "the failed take was not rolled back"
+    );
+}
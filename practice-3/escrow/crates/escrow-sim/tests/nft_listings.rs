@@ -0,0 +1,425 @@
+//! NFT listings: offers of a Metaplex NFT that check its collection when
+//! made and pay its creators royalties when taken.
+
+mod common;
+
+use anchor_lang::{
+    prelude::{AccountMeta, ProgramError, Pubkey},
+    solana_program::instruction::Instruction,
+    system_program, AccountDeserialize, InstructionData, ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::{self, get_associated_token_address},
+    metadata::mpl_token_metadata::{
+        accounts::Metadata,
+        types::{Collection, Creator, Key},
+    },
+};
+use borsh::BorshSerialize;
+use common::{set_rent_exempt, Fixture, TAKER_TOKEN_B_BALANCE, TOKEN_B_WANTED_AMOUNT};
+use escrow::{error::ErrorCode, NftMetadata, Offer, CONFIG_SEED};
+use escrow_sim::ExecutionError;
+use spl_token::{
+    solana_program::{program_option::COption, program_pack::Pack},
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+
+const LISTING_ID: u64 = 8;
+const SELLER_FEE_BASIS_POINTS: u16 = 500;
+/// 5% of `TOKEN_B_WANTED_AMOUNT`, split 70/30 between the two creators.
+const ROYALTIES: [u64; 2] = [175_000, 75_000];
+
+struct Nft {
+    mint: Pubkey,
+    collection: Pubkey,
+    creators: [Pubkey; 2],
+}
+
+impl Fixture {
+    /// Mints an NFT to the maker, with metadata naming a collection, verified
+    /// or not, and two creators.
+    fn mint_nft(&mut self, supply: u64, collection_verified: bool) -> Nft {
+        let nft = Nft {
+            mint: Pubkey::new_unique(),
+            collection: Pubkey::new_unique(),
+            creators: [Pubkey::new_unique(), Pubkey::new_unique()],
+        };
+
+        let mut mint_data = vec![0; Mint::LEN];
+        Mint {
+            mint_authority: COption::None,
+            supply,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }
+        .pack_into_slice(&mut mint_data);
+        set_rent_exempt(&mut self.runtime, nft.mint, mint_data, spl_token::ID);
+
+        let mut account_data = vec![0; TokenAccount::LEN];
+        TokenAccount {
+            mint: nft.mint,
+            owner: self.maker,
+            amount: supply,
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut account_data);
+        set_rent_exempt(
+            &mut self.runtime,
+            get_associated_token_address(&self.maker, &nft.mint),
+            account_data,
+            spl_token::ID,
+        );
+
+        self.set_metadata(&nft.mint, &nft, collection_verified);
+        nft
+    }
+
+    /// Writes the metadata `nft` describes at the metadata address of `mint`.
+    fn set_metadata(&mut self, mint: &Pubkey, nft: &Nft, collection_verified: bool) {
+        let metadata = Metadata {
+            key: Key::MetadataV1,
+            update_authority: Pubkey::new_unique(),
+            mint: nft.mint,
+            name: "Escrow Test".to_string(),
+            symbol: "ESC".to_string(),
+            uri: "https://example.com/nft.json".to_string(),
+            seller_fee_basis_points: SELLER_FEE_BASIS_POINTS,
+            creators: Some(vec![
+                Creator {
+                    address: nft.creators[0],
+                    verified: true,
+                    share: 70,
+                },
+                Creator {
+                    address: nft.creators[1],
+                    verified: false,
+                    share: 30,
+                },
+            ]),
+            primary_sale_happened: true,
+            is_mutable: true,
+            edition_nonce: None,
+            token_standard: None,
+            collection: Some(Collection {
+                verified: collection_verified,
+                key: nft.collection,
+            }),
+            uses: None,
+            collection_details: None,
+            programmable_config: None,
+        };
+        set_rent_exempt(
+            &mut self.runtime,
+            NftMetadata::address(mint),
+            metadata.try_to_vec().unwrap(),
+            anchor_spl::metadata::ID,
+        );
+    }
+
+    fn listing(&self) -> Pubkey {
+        Offer::pda(&self.maker, LISTING_ID).0
+    }
+
+    fn list(
+        &mut self,
+        mint: &Pubkey,
+        amount: u64,
+        nft_metadata: Option<Pubkey>,
+    ) -> Result<(), ExecutionError> {
+        let listing = self.listing();
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::MakeOffer {
+                maker: self.maker,
+                token_mint_a: *mint,
+                token_mint_b: self.mint_b,
+                maker_token_account_a: get_associated_token_address(&self.maker, mint),
+                offer: listing,
+                vault: get_associated_token_address(&listing, mint),
+                config: Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0,
+                mint_registry: None,
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                price_feed: None,
+                nft_metadata,
+                rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::MakeOffer {
+                id: LISTING_ID,
+                token_a_offered_amount: amount,
+                token_b_wanted_amount: TOKEN_B_WANTED_AMOUNT,
+                other_payments: Vec::new(),
+                peg: None,
+            }
+            .data(),
+        };
+        self.runtime
+            .process_transaction(&[instruction], &[self.maker])
+    }
+
+    /// Lists `nft` for `TOKEN_B_WANTED_AMOUNT`, and creates the creators'
+    /// token B accounts.
+    fn list_nft(&mut self, nft: &Nft) {
+        self.list(&nft.mint, 1, Some(NftMetadata::address(&nft.mint)))
+            .expect("listing failed");
+        let create_accounts: Vec<_> = nft
+            .creators
+            .iter()
+            .map(|creator| {
+                associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                    &self.taker,
+                    creator,
+                    &self.mint_b,
+                    &spl_token::ID,
+                )
+            })
+            .collect();
+        self.runtime
+            .process_transaction(&create_accounts, &[self.taker])
+            .expect("creating the creator accounts failed");
+    }
+
+    fn buy(
+        &mut self,
+        nft: &Nft,
+        nft_metadata: Option<Pubkey>,
+        royalty_accounts: &[Pubkey],
+    ) -> Result<(), ExecutionError> {
+        let listing = self.listing();
+        let mut accounts = escrow::accounts::TakeOffer {
+            taker: self.taker,
+            maker: self.maker,
+            token_mint_a: nft.mint,
+            token_mint_b: self.mint_b,
+            taker_token_account_a: get_associated_token_address(&self.taker, &nft.mint),
+            taker_token_account_b: get_associated_token_address(&self.taker, &self.mint_b),
+            maker_token_account_b: get_associated_token_address(&self.maker, &self.mint_b),
+            offer: listing,
+            vault: get_associated_token_address(&listing, &nft.mint),
+            config: Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0,
+            associated_token_program: associated_token::ID,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            fill_receipt: None,
+            referrer: None,
+            referrer_token_account_b: None,
+            price_feed: None,
+            nft_metadata,
+            rent_payer: None,
+            offer_rent_payer: None,
+        }
+        .to_account_metas(None);
+        accounts.extend(
+            royalty_accounts
+                .iter()
+                .map(|account| AccountMeta::new(*account, false)),
+        );
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts,
+            data: escrow::instruction::TakeOffer {
+                referral_fee_bps: 0,
+            }
+            .data(),
+        };
+        self.runtime
+            .process_transaction(&[instruction], &[self.taker])
+    }
+
+    fn creator_accounts(&self, nft: &Nft) -> Vec<Pubkey> {
+        nft.creators
+            .iter()
+            .map(|creator| get_associated_token_address(creator, &self.mint_b))
+            .collect()
+    }
+
+    fn listing_state(&self) -> Offer {
+        let account = self.runtime.account(&self.listing()).unwrap();
+        Offer::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+}
+
+fn program_error(error: ErrorCode) -> ExecutionError {
+    ExecutionError::Program(ProgramError::from(anchor_lang::error::Error::from(error)))
+}
+
+#[test]
+fn listing_records_the_verified_collection() {
+    let mut fixture = Fixture::new();
+    let nft = fixture.mint_nft(1, true);
+
+    fixture.list_nft(&nft);
+
+    assert_eq!(fixture.listing_state().nft_collection, Some(nft.collection));
+    assert_eq!(
+        fixture.token_balance(&fixture.listing(), &nft.mint),
+        Some(1)
+    );
+    // Offers made without the metadata are not listings.
+    assert_eq!(
+        Offer::try_deserialize(
+            &mut fixture
+                .runtime
+                .account(&fixture.offer)
+                .unwrap()
+                .data
+                .as_slice()
+        )
+        .unwrap()
+        .nft_collection,
+        None
+    );
+}
+
+#[test]
+fn sale_pays_creators_out_of_the_price() {
+    let mut fixture = Fixture::new();
+    let nft = fixture.mint_nft(1, true);
+    fixture.list_nft(&nft);
+
+    let creator_accounts = fixture.creator_accounts(&nft);
+    fixture
+        .buy(
+            &nft,
+            Some(NftMetadata::address(&nft.mint)),
+            &creator_accounts,
+        )
+        .unwrap();
+
+    assert_eq!(fixture.token_balance(&fixture.taker, &nft.mint), Some(1));
+    assert_eq!(
+        fixture.token_balance(&fixture.taker, &fixture.mint_b),
+        Some(TAKER_TOKEN_B_BALANCE - TOKEN_B_WANTED_AMOUNT)
+    );
+    assert_eq!(
+        fixture.token_balance(&fixture.maker, &fixture.mint_b),
+        Some(TOKEN_B_WANTED_AMOUNT - ROYALTIES.iter().sum::<u64>())
+    );
+    for (creator, royalty) in nft.creators.iter().zip(ROYALTIES) {
+        assert_eq!(
+            fixture.token_balance(creator, &fixture.mint_b),
+            Some(royalty)
+        );
+    }
+    assert!(fixture.runtime.account(&fixture.listing()).is_none());
+}
+
+#[test]
+fn sale_needs_the_metadata_and_every_creator_account_in_order() {
+    let mut fixture = Fixture::new();
+    let nft = fixture.mint_nft(1, true);
+    fixture.list_nft(&nft);
+    let metadata = Some(NftMetadata::address(&nft.mint));
+    let mut creator_accounts = fixture.creator_accounts(&nft);
+
+    assert_eq!(
+        fixture.buy(&nft, None, &creator_accounts),
+        Err(program_error(ErrorCode::NftMetadataRequired))
+    );
+    assert_eq!(
+        fixture.buy(&nft, metadata, &creator_accounts[..1]),
+        Err(program_error(ErrorCode::RoyaltyAccountMismatch))
+    );
+    creator_accounts.reverse();
+    assert_eq!(
+        fixture.buy(&nft, metadata, &creator_accounts),
+        Err(program_error(ErrorCode::RoyaltyAccountMismatch))
+    );
+    // Another NFT's metadata can not stand in for the listed one.
+    let other = fixture.mint_nft(1, true);
+    assert_eq!(
+        fixture.buy(
+            &nft,
+            Some(NftMetadata::address(&other.mint)),
+            &fixture.creator_accounts(&other)
+        ),
+        Err(program_error(ErrorCode::NftMetadataMismatch))
+    );
+}
+
+#[test]
+fn listing_needs_a_verified_collection() {
+    let mut fixture = Fixture::new();
+    let nft = fixture.mint_nft(1, false);
+
+    assert_eq!(
+        fixture.list(&nft.mint, 1, Some(NftMetadata::address(&nft.mint))),
+        Err(program_error(ErrorCode::NftCollectionNotVerified))
+    );
+}
+
+#[test]
+fn listing_needs_an_nft_and_its_own_metadata() {
+    let mut fixture = Fixture::new();
+    let fungible = fixture.mint_nft(2, true);
+    assert_eq!(
+        fixture.list(
+            &fungible.mint,
+            1,
+            Some(NftMetadata::address(&fungible.mint))
+        ),
+        Err(program_error(ErrorCode::NotAnNft))
+    );
+
+    let nft = fixture.mint_nft(1, true);
+    assert_eq!(
+        fixture.list(&nft.mint, 0, Some(NftMetadata::address(&nft.mint))),
+        Err(program_error(ErrorCode::NotAnNft))
+    );
+
+    // Metadata recording another mint, as only a fake could at this address.
+    let other = Nft {
+        mint: Pubkey::new_unique(),
+        ..fixture.mint_nft(1, true)
+    };
+    fixture.set_metadata(&nft.mint, &other, true);
+    assert_eq!(
+        fixture.list(&nft.mint, 1, Some(NftMetadata::address(&nft.mint))),
+        Err(program_error(ErrorCode::NftMetadataMismatch))
+    );
+}
+
+#[test]
+fn listings_take_no_counter_offers() {
+    let mut fixture = Fixture::new();
+    let nft = fixture.mint_nft(1, true);
+    fixture.list_nft(&nft);
+    let listing = fixture.listing();
+    let counter_offer = escrow::CounterOffer::pda(&listing, &fixture.taker).0;
+
+    let instruction = Instruction {
+        program_id: escrow::ID,
+        accounts: escrow::accounts::MakeCounterOffer {
+            counter_party: fixture.taker,
+            offer: listing,
+            vault: get_associated_token_address(&listing, &nft.mint),
+            token_mint_a: nft.mint,
+            token_mint_b: fixture.mint_b,
+            counter_party_token_account_b: get_associated_token_address(
+                &fixture.taker,
+                &fixture.mint_b,
+            ),
+            counter_offer,
+            counter_vault: get_associated_token_address(&counter_offer, &fixture.mint_b),
+            config: Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0,
+            associated_token_program: associated_token::ID,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::MakeCounterOffer {
+            token_b_amount: TOKEN_B_WANTED_AMOUNT / 2,
+        }
+        .data(),
+    };
+    assert_eq!(
+        fixture
+            .runtime
+            .process_transaction(&[instruction], &[fixture.taker]),
+        Err(program_error(ErrorCode::NftListing))
+    );
+}
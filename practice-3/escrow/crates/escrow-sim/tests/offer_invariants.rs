@@ -0,0 +1,618 @@
+//! Property tests over random sequences of makes, takes and cancels.
+//!
+//! Each case builds mints with random decimals on SPL Token or Token-2022,
+//! funds a few users, then applies random operations. A model predicts
+//! whether each operation succeeds and what it moves; after every step the
+//! runtime must agree with the model, and tokens must be conserved, no vault
+//! may outlive its offer and every lamport of rent must be back with whoever
+//! paid it.
+
+use std::collections::HashMap;
+
+use anchor_lang::{
+    prelude::Pubkey,
+    solana_program::{instruction::Instruction, sysvar},
+    system_program, AccountSerialize, Discriminator, InstructionData, Space, ToAccountMetas,
+};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use escrow::{Config, Offer, ANCHOR_DISCRIMINATOR, CONFIG_SEED};
+use escrow_sim::{Account, Runtime};
+use proptest::{prelude::*, sample::Index, test_runner::FileFailurePersistence};
+use spl_token_2022::{
+    extension::{ExtensionType, StateWithExtensions},
+    solana_program::{program_option::COption, program_pack::Pack},
+    state::{Account as TokenAccount, Mint},
+};
+
+const USERS: usize = 3;
+const STARTING_LAMPORTS: u64 = 100_000_000_000;
+
+#[derive(Debug, Clone)]
+struct MintSpec {
+    decimals: u8,
+    token_2022: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Operation {
+    Make {
+        maker: usize,
+        mint_a: usize,
+        mint_b: Index,
+        id: u64,
+        token_a_offered_amount: u64,
+        token_b_wanted_amount: u64,
+    },
+    Take {
+        taker: Index,
+        offer: Index,
+    },
+    Cancel {
+        signer: usize,
+        offer: Index,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct ModelOffer {
+    maker: usize,
+    id: u64,
+    mint_a: usize,
+    mint_b: usize,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+}
+
+struct World {
+    runtime: Runtime,
+    users: Vec<Pubkey>,
+    mints: Vec<(Pubkey, Pubkey, u8)>,
+    /// Token balances, keyed by user and mint index. Missing means the user
+    /// has no token account for that mint.
+    balances: HashMap<(usize, usize), u64>,
+    supplies: Vec<u64>,
+    lamports: Vec<u64>,
+    /// Every offer address ever made, so operations also hit closed ones.
+    offer_addresses: Vec<Pubkey>,
+    live_offers: HashMap<Pubkey, ModelOffer>,
+}
+
+impl World {
+    fn new(mint_specs: &[MintSpec], starting_balances: &[Option<u64>]) -> Self {
+        let mut runtime = Runtime::new();
+        let users: Vec<Pubkey> = (0..USERS).map(|_| Pubkey::new_unique()).collect();
+        for user in &users {
+            runtime.airdrop(user, STARTING_LAMPORTS);
+        }
+        let mint_authority = Pubkey::new_unique();
+        runtime.airdrop(&mint_authority, STARTING_LAMPORTS);
+
+        let (config, bump) = Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID);
+        let mut data = Vec::new();
+        Config {
+            admin: Pubkey::new_unique(),
+            paused: false,
+            mint_allowlist_enabled: false,
+            max_referral_fee_bps: 0,
+            bump,
+        }
+        .try_serialize(&mut data)
+        .unwrap();
+        runtime.set_account(
+            config,
+            Account {
+                lamports: runtime.rent().minimum_balance(data.len()),
+                data,
+                owner: escrow::ID,
+                executable: false,
+            },
+        );
+
+        let mints = mint_specs
+            .iter()
+            .map(|spec| {
+                let token_program = if spec.token_2022 {
+                    spl_token_2022::ID
+                } else {
+                    spl_token::ID
+                };
+                let mint = Pubkey::new_unique();
+                let mut data = vec![0; Mint::LEN];
+                Mint {
+                    mint_authority: COption::Some(mint_authority),
+                    supply: 0,
+                    decimals: spec.decimals,
+                    is_initialized: true,
+                    freeze_authority: COption::None,
+                }
+                .pack_into_slice(&mut data);
+                runtime.set_account(
+                    mint,
+                    Account {
+                        lamports: runtime.rent().minimum_balance(data.len()),
+                        data,
+                        owner: token_program,
+                        executable: false,
+                    },
+                );
+                (mint, token_program, spec.decimals)
+            })
+            .collect();
+
+        let mut world = Self {
+            runtime,
+            users,
+            mints,
+            balances: HashMap::new(),
+            supplies: vec![0; mint_specs.len()],
+            lamports: vec![STARTING_LAMPORTS; USERS],
+            offer_addresses: Vec::new(),
+            live_offers: HashMap::new(),
+        };
+
+        // The mint authority pays for the setup, so the users' lamports
+        // only move because of the escrow.
+        for (index, balance) in starting_balances.iter().enumerate() {
+            let Some(balance) = *balance else {
+                continue;
+            };
+            let (user, mint) = (index / mint_specs.len(), index % mint_specs.len());
+            let (mint_address, token_program, _) = world.mints[mint];
+            let token_account = world.token_account(user, mint);
+            world
+                .runtime
+                .process_transaction(
+                    &[
+                        spl_associated_token_account::instruction::create_associated_token_account(
+                            &mint_authority,
+                            &world.users[user],
+                            &mint_address,
+                            &token_program,
+                        ),
+                        spl_token_2022::instruction::mint_to(
+                            &token_program,
+                            &mint_address,
+                            &token_account,
+                            &mint_authority,
+                            &[],
+                            balance,
+                        )
+                        .unwrap(),
+                    ],
+                    &[mint_authority],
+                )
+                .expect("setup failed");
+            world.balances.insert((user, mint), balance);
+            world.supplies[mint] += balance;
+        }
+        world
+    }
+
+    fn token_account(&self, user: usize, mint: usize) -> Pubkey {
+        let (mint, token_program, _) = self.mints[mint];
+        get_associated_token_address_with_program_id(&self.users[user], &mint, &token_program)
+    }
+
+    fn token_account_rent(&self, mint: usize) -> u64 {
+        let (_, token_program, _) = self.mints[mint];
+        let len = if token_program == spl_token_2022::ID {
+            ExtensionType::try_calculate_account_len::<TokenAccount>(&[
+                ExtensionType::ImmutableOwner,
+            ])
+            .unwrap()
+        } else {
+            TokenAccount::LEN
+        };
+        self.runtime.rent().minimum_balance(len)
+    }
+
+    fn offer_rent(&self) -> u64 {
+        self.runtime
+            .rent()
+            .minimum_balance(ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE)
+    }
+
+    fn vault(&self, offer: &Pubkey, mint: usize) -> Pubkey {
+        let (mint, token_program, _) = self.mints[mint];
+        get_associated_token_address_with_program_id(offer, &mint, &token_program)
+    }
+
+    fn apply(&mut self, operation: &Operation) {
+        match *operation {
+            Operation::Make {
+                maker,
+                mint_a,
+                mint_b,
+                id,
+                token_a_offered_amount,
+                token_b_wanted_amount,
+            } => {
+                // Any mint but `mint_a`.
+                let mint_b = (mint_a + 1 + mint_b.index(self.mints.len() - 1)) % self.mints.len();
+                self.make(
+                    maker,
+                    mint_a,
+                    mint_b,
+                    id,
+                    token_a_offered_amount,
+                    token_b_wanted_amount,
+                );
+            }
+            Operation::Take { taker, offer } => {
+                if let Some(&address) = self.pick_offer(offer) {
+                    // Any user but the maker, when the offer is still known.
+                    let taker = match self.live_offers.get(&address) {
+                        Some(live) => (live.maker + 1 + taker.index(USERS - 1)) % USERS,
+                        None => taker.index(USERS),
+                    };
+                    self.take(taker, address);
+                }
+            }
+            Operation::Cancel { signer, offer } => {
+                if let Some(&address) = self.pick_offer(offer) {
+                    self.cancel(signer, address);
+                }
+            }
+        }
+    }
+
+    fn pick_offer(&self, index: Index) -> Option<&Pubkey> {
+        (!self.offer_addresses.is_empty())
+            .then(|| &self.offer_addresses[index.index(self.offer_addresses.len())])
+    }
+
+    fn make(
+        &mut self,
+        maker: usize,
+        mint_a: usize,
+        mint_b: usize,
+        id: u64,
+        token_a_offered_amount: u64,
+        token_b_wanted_amount: u64,
+    ) {
+        let (offer, _) = Offer::pda(&self.users[maker], id);
+        let (mint_a_address, token_program, _) = self.mints[mint_a];
+        let (mint_b_address, token_program_b, _) = self.mints[mint_b];
+
+        let expected = token_program == token_program_b
+            && self
+                .balances
+                .get(&(maker, mint_a))
+                .is_some_and(|balance| *balance >= token_a_offered_amount)
+            && !self.live_offers.contains_key(&offer);
+
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::MakeOffer {
+                maker: self.users[maker],
+                token_mint_a: mint_a_address,
+                token_mint_b: mint_b_address,
+                maker_token_account_a: self.token_account(maker, mint_a),
+                offer,
+                vault: self.vault(&offer, mint_a),
+                config: Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0,
+                mint_registry: None,
+                associated_token_program: associated_token::ID,
+                token_program,
+                system_program: system_program::ID,
+                price_feed: None,
+                nft_metadata: None,
+                rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::MakeOffer {
+                id,
+                token_a_offered_amount,
+                token_b_wanted_amount,
+                other_payments: Vec::new(),
+                peg: None,
+            }
+            .data(),
+        };
+        if !self.execute(&instruction, self.users[maker], expected, "make_offer") {
+            return;
+        }
+
+        *self.balances.get_mut(&(maker, mint_a)).unwrap() -= token_a_offered_amount;
+        self.lamports[maker] -= self.offer_rent() + self.token_account_rent(mint_a);
+        if !self.offer_addresses.contains(&offer) {
+            self.offer_addresses.push(offer);
+        }
+        self.live_offers.insert(
+            offer,
+            ModelOffer {
+                maker,
+                id,
+                mint_a,
+                mint_b,
+                token_a_offered_amount,
+                token_b_wanted_amount,
+            },
+        );
+    }
+
+    fn take(&mut self, taker: usize, address: Pubkey) {
+        let live = self.live_offers.get(&address).cloned();
+        // A closed offer is taken with whatever it last held, which must fail.
+        let offer = live.clone().unwrap_or(ModelOffer {
+            maker: (taker + 1) % USERS,
+            id: 0,
+            mint_a: 0,
+            mint_b: 1,
+            token_a_offered_amount: 0,
+            token_b_wanted_amount: 0,
+        });
+        let expected = live.is_some()
+            && self
+                .balances
+                .get(&(taker, offer.mint_b))
+                .is_some_and(|balance| *balance >= offer.token_b_wanted_amount);
+
+        let (mint_a, token_program, _) = self.mints[offer.mint_a];
+        let (mint_b, ..) = self.mints[offer.mint_b];
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::TakeOffer {
+                taker: self.users[taker],
+                maker: self.users[offer.maker],
+                token_mint_a: mint_a,
+                token_mint_b: mint_b,
+                taker_token_account_a: self.token_account(taker, offer.mint_a),
+                taker_token_account_b: self.token_account(taker, offer.mint_b),
+                maker_token_account_b: self.token_account(offer.maker, offer.mint_b),
+                offer: address,
+                vault: self.vault(&address, offer.mint_a),
+                config: Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0,
+                associated_token_program: associated_token::ID,
+                token_program,
+                system_program: system_program::ID,
+                fill_receipt: None,
+                referrer: None,
+                referrer_token_account_b: None,
+                price_feed: None,
+                nft_metadata: None,
+                rent_payer: None,
+                offer_rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::TakeOffer {
+                referral_fee_bps: 0,
+            }
+            .data(),
+        };
+        if !self.execute(&instruction, self.users[taker], expected, "take_offer") {
+            return;
+        }
+
+        *self.balances.get_mut(&(taker, offer.mint_b)).unwrap() -= offer.token_b_wanted_amount;
+        for (user, mint, amount) in [
+            (offer.maker, offer.mint_b, offer.token_b_wanted_amount),
+            (taker, offer.mint_a, offer.token_a_offered_amount),
+        ] {
+            if !self.balances.contains_key(&(user, mint)) {
+                self.lamports[taker] -= self.token_account_rent(mint);
+            }
+            *self.balances.entry((user, mint)).or_default() += amount;
+        }
+        self.lamports[offer.maker] += self.offer_rent() + self.token_account_rent(offer.mint_a);
+        self.live_offers.remove(&address);
+    }
+
+    fn cancel(&mut self, signer: usize, address: Pubkey) {
+        let live = self.live_offers.get(&address).cloned();
+        let expected = live.as_ref().is_some_and(|offer| offer.maker == signer);
+        let mint_a = live.as_ref().map_or(0, |offer| offer.mint_a);
+
+        let (mint_a_address, token_program, _) = self.mints[mint_a];
+        // The signer poses as the maker, which the program must catch.
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::CancelOffer {
+                maker: self.users[signer],
+                token_mint_a: mint_a_address,
+                maker_token_account_a: self.token_account(signer, mint_a),
+                offer: address,
+                vault: self.vault(&address, mint_a),
+                associated_token_program: associated_token::ID,
+                token_program,
+                system_program: system_program::ID,
+                offer_rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::CancelOffer {}.data(),
+        };
+        if !self.execute(&instruction, self.users[signer], expected, "cancel_offer") {
+            return;
+        }
+
+        let offer = live.unwrap();
+        *self.balances.get_mut(&(offer.maker, offer.mint_a)).unwrap() +=
+            offer.token_a_offered_amount;
+        self.lamports[offer.maker] += self.offer_rent() + self.token_account_rent(offer.mint_a);
+        self.live_offers.remove(&address);
+    }
+
+    /// Runs `instruction` and checks it succeeded exactly when expected.
+    fn execute(
+        &mut self,
+        instruction: &Instruction,
+        signer: Pubkey,
+        expected: bool,
+        name: &str,
+    ) -> bool {
+        let result = self
+            .runtime
+            .process_transaction(std::slice::from_ref(instruction), &[signer]);
+        assert_eq!(
+            result.is_ok(),
+            expected,
+            "{name} was expected to {}, got {result:?}",
+            if expected { "succeed" } else { "fail" }
+        );
+        expected
+    }
+
+    fn token_balance(&self, address: &Pubkey) -> Option<(TokenAccount, Pubkey)> {
+        let account = self.runtime.account(address)?;
+        let state = StateWithExtensions::<TokenAccount>::unpack(&account.data).unwrap();
+        Some((state.base, account.owner))
+    }
+
+    fn check_invariants(&self, total_lamports: u128) {
+        // Every token balance is what the model says.
+        for user in 0..USERS {
+            for mint in 0..self.mints.len() {
+                let actual = self
+                    .token_balance(&self.token_account(user, mint))
+                    .map(|(account, _)| account.amount);
+                assert_eq!(
+                    actual,
+                    self.balances.get(&(user, mint)).copied(),
+                    "balance of user {user} in mint {mint}"
+                );
+            }
+        }
+
+        // Live offers hold exactly their vault.
+        let mut in_vaults = vec![0; self.mints.len()];
+        for (address, offer) in &self.live_offers {
+            let account = self
+                .runtime
+                .account(address)
+                .expect("live offer is missing");
+            assert_eq!(account.owner, escrow::ID);
+            let state = Offer::try_from_versioned(&account.data).unwrap();
+            assert_eq!(state.maker, self.users[offer.maker]);
+            assert_eq!(state.id, offer.id);
+            assert_eq!(state.token_mint_b, self.mints[offer.mint_b].0);
+            assert_eq!(state.token_b_wanted_amount, offer.token_b_wanted_amount);
+
+            let (vault, _) = self
+                .token_balance(&self.vault(address, offer.mint_a))
+                .expect("live offer has no vault");
+            assert_eq!(vault.owner, *address);
+            assert_eq!(vault.amount, offer.token_a_offered_amount);
+            in_vaults[offer.mint_a] += offer.token_a_offered_amount;
+        }
+
+        // Tokens are conserved: users and vaults together hold the supply.
+        for (mint, in_vault) in in_vaults.iter().enumerate() {
+            let held: u64 = (0..USERS)
+                .filter_map(|user| self.balances.get(&(user, mint)))
+                .sum();
+            assert_eq!(
+                held + in_vault,
+                self.supplies[mint],
+                "supply of mint {mint}"
+            );
+            let mint_account = self.runtime.account(&self.mints[mint].0).unwrap();
+            let mint_state = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap();
+            assert_eq!(mint_state.base.supply, self.supplies[mint]);
+        }
+
+        // No offer or vault outlives its offer.
+        for (address, account) in self.runtime.accounts() {
+            if account.owner == escrow::ID && account.data.len() > ANCHOR_DISCRIMINATOR {
+                let is_config = account.data.starts_with(Config::DISCRIMINATOR);
+                assert!(
+                    is_config || self.live_offers.contains_key(&address),
+                    "offer {address} outlived its lifetime"
+                );
+            }
+            let is_token_account = (account.owner == spl_token::ID
+                || account.owner == spl_token_2022::ID)
+                && account.data.len() >= TokenAccount::LEN;
+            if is_token_account {
+                let (token_account, _) = self.token_balance(&address).unwrap();
+                let owned_by_user = self.users.contains(&token_account.owner);
+                assert!(
+                    owned_by_user || self.live_offers.contains_key(&token_account.owner),
+                    "vault {address} outlived offer {}",
+                    token_account.owner
+                );
+            }
+            // LiteSVM gives its builtin programs and sysvars a single lamport.
+            let is_runtime_account = account.executable || account.owner == sysvar::ID;
+            assert!(
+                is_runtime_account
+                    || self
+                        .runtime
+                        .rent()
+                        .is_exempt(account.lamports, account.data.len()),
+                "{address} is not rent exempt"
+            );
+        }
+
+        // Rent went back to whoever paid it.
+        for (user, lamports) in self.lamports.iter().enumerate() {
+            assert_eq!(
+                self.runtime.lamports(&self.users[user]),
+                *lamports,
+                "lamports of user {user}"
+            );
+        }
+        assert_eq!(self.runtime.total_lamports(), total_lamports);
+    }
+}
+
+fn mint_spec() -> impl Strategy<Value = MintSpec> {
+    (0..=9u8, any::<bool>()).prop_map(|(decimals, token_2022)| MintSpec {
+        decimals,
+        token_2022,
+    })
+}
+
+fn operation(mint_count: usize) -> impl Strategy<Value = Operation> {
+    let amount = 0..=1_200_000u64;
+    prop_oneof![
+        3 => (0..USERS, 0..mint_count, any::<Index>(), 0..3u64, amount.clone(), amount)
+            .prop_map(|(maker, mint_a, mint_b, id, token_a_offered_amount, token_b_wanted_amount)| {
+                Operation::Make {
+                    maker,
+                    mint_a,
+                    mint_b,
+                    id,
+                    token_a_offered_amount,
+                    token_b_wanted_amount,
+                }
+            }),
+        2 => (any::<Index>(), any::<Index>())
+            .prop_map(|(taker, offer)| Operation::Take { taker, offer }),
+        1 => (0..USERS, any::<Index>())
+            .prop_map(|(signer, offer)| Operation::Cancel { signer, offer }),
+    ]
+}
+
+fn scenario() -> impl Strategy<Value = (Vec<MintSpec>, Vec<Option<u64>>, Vec<Operation>)> {
+    prop::collection::vec(mint_spec(), 2..=3).prop_flat_map(|mints| {
+        let mint_count = mints.len();
+        (
+            Just(mints),
+            prop::collection::vec(
+                prop::option::weighted(0.8, 0..=1_000_000u64),
+                USERS * mint_count,
+            ),
+            prop::collection::vec(operation(mint_count), 1..24),
+        )
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 128,
+        failure_persistence: Some(Box::new(FileFailurePersistence::WithSource("regressions"))),
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn offer_state_transitions_keep_invariants((mints, balances, operations) in scenario()) {
+        let mut world = World::new(&mints, &balances);
+        let total_lamports = world.runtime.total_lamports();
+        world.check_invariants(total_lamports);
+
+        for operation in &operations {
+            world.apply(operation);
+            world.check_invariants(total_lamports);
+        }
+    }
+}
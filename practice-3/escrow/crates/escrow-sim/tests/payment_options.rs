@@ -0,0 +1,218 @@
+//! Offers that accept several payment mints, made with `other_payments` and
+//! taken in any of them.
+
+mod common;
+
+use anchor_lang::{
+    prelude::{ProgramError, Pubkey},
+    solana_program::instruction::Instruction,
+    system_program, AccountDeserialize, InstructionData, ToAccountMetas,
+};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use common::{Fixture, TAKER_TOKEN_B_BALANCE, TOKEN_A_OFFERED_AMOUNT, TOKEN_B_WANTED_AMOUNT};
+use escrow::{error::ErrorCode, Offer, PaymentOption, CONFIG_SEED, MAX_PAYMENT_OPTIONS};
+use escrow_sim::{Account, ExecutionError};
+use spl_token::{
+    solana_program::program_pack::Pack,
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+
+const OTHER_WANTED_AMOUNT: u64 = 3_000_000;
+const SECOND_OFFER_ID: u64 = 8;
+
+impl Fixture {
+    /// A second offer by the same maker, of 1 base unit of token A.
+    fn make_second_offer(
+        &mut self,
+        other_payments: Vec<PaymentOption>,
+    ) -> Result<(), ExecutionError> {
+        let (offer, _) = Offer::pda(&self.maker, SECOND_OFFER_ID);
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::MakeOffer {
+                maker: self.maker,
+                token_mint_a: self.mint_a,
+                token_mint_b: self.mint_b,
+                maker_token_account_a: get_associated_token_address(&self.maker, &self.mint_a),
+                offer,
+                vault: get_associated_token_address(&offer, &self.mint_a),
+                config: Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0,
+                mint_registry: None,
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                price_feed: None,
+                nft_metadata: None,
+                rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::MakeOffer {
+                id: SECOND_OFFER_ID,
+                token_a_offered_amount: 1,
+                token_b_wanted_amount: TOKEN_B_WANTED_AMOUNT,
+                other_payments,
+                peg: None,
+            }
+            .data(),
+        };
+        self.runtime
+            .process_transaction(&[instruction], &[self.maker])
+    }
+
+    /// Creates a 6 decimal mint the offer does not accept, and a taker token
+    /// account holding `amount` of it.
+    fn give_taker_unlisted_tokens(&mut self, amount: u64) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        let mut mint_data = vec![0; Mint::LEN];
+        Mint {
+            supply: amount,
+            decimals: 6,
+            is_initialized: true,
+            ..Mint::default()
+        }
+        .pack_into_slice(&mut mint_data);
+        let mut account_data = vec![0; TokenAccount::LEN];
+        TokenAccount {
+            mint,
+            owner: self.taker,
+            amount,
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut account_data);
+
+        for (address, data) in [
+            (mint, mint_data),
+            (
+                get_associated_token_address(&self.taker, &mint),
+                account_data,
+            ),
+        ] {
+            let lamports = self.runtime.rent().minimum_balance(data.len());
+            self.runtime.set_account(
+                address,
+                Account {
+                    lamports,
+                    data,
+                    owner: spl_token::ID,
+                    executable: false,
+                },
+            );
+        }
+        mint
+    }
+
+    fn offer_state(&self) -> Offer {
+        let account = self.runtime.account(&self.offer).unwrap();
+        Offer::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+}
+
+fn program_error(error: ErrorCode) -> ExecutionError {
+    ExecutionError::Program(ProgramError::from(anchor_lang::error::Error::from(error)))
+}
+
+fn payment(mint: Pubkey) -> PaymentOption {
+    PaymentOption {
+        mint,
+        amount: OTHER_WANTED_AMOUNT,
+    }
+}
+
+#[test]
+fn offer_stores_the_other_payment_options() {
+    let fixture = Fixture::with_other_payments(&[OTHER_WANTED_AMOUNT, 4_000_000]);
+
+    let offer = fixture.offer_state();
+    assert_eq!(
+        offer.wanted_amount(&fixture.mint_b),
+        Some(TOKEN_B_WANTED_AMOUNT)
+    );
+    assert_eq!(
+        offer.wanted_amount(&fixture.other_mints[0]),
+        Some(OTHER_WANTED_AMOUNT)
+    );
+    assert_eq!(
+        offer.wanted_amount(&fixture.other_mints[1]),
+        Some(4_000_000)
+    );
+    assert_eq!(offer.wanted_amount(&fixture.mint_a), None);
+    assert_eq!(offer.payment_options().count(), 3);
+}
+
+#[test]
+fn taker_pays_with_another_accepted_mint() {
+    let mut fixture = Fixture::with_other_payments(&[OTHER_WANTED_AMOUNT]);
+    let other_mint = fixture.other_mints[0];
+
+    let instruction = fixture.take_offer_paying(&other_mint, None, None, 0);
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .expect("take_offer failed");
+
+    assert!(fixture.runtime.account(&fixture.offer).is_none());
+    assert_eq!(
+        fixture.token_balance(&fixture.taker, &fixture.mint_a),
+        Some(TOKEN_A_OFFERED_AMOUNT)
+    );
+    assert_eq!(
+        fixture.token_balance(&fixture.maker, &other_mint),
+        Some(OTHER_WANTED_AMOUNT)
+    );
+    assert_eq!(
+        fixture.token_balance(&fixture.taker, &other_mint),
+        Some(OTHER_WANTED_AMOUNT)
+    );
+    // Nothing moves in the primary payment mint.
+    assert_eq!(fixture.token_balance(&fixture.maker, &fixture.mint_b), None);
+    assert_eq!(
+        fixture.token_balance(&fixture.taker, &fixture.mint_b),
+        Some(TAKER_TOKEN_B_BALANCE)
+    );
+}
+
+#[test]
+fn unlisted_payment_mint_is_rejected() {
+    let mut fixture = Fixture::with_other_payments(&[OTHER_WANTED_AMOUNT]);
+    let unlisted_mint = fixture.give_taker_unlisted_tokens(TAKER_TOKEN_B_BALANCE);
+
+    let instruction = fixture.take_offer_paying(&unlisted_mint, None, None, 0);
+    assert_eq!(
+        fixture
+            .runtime
+            .process_transaction(&[instruction], &[fixture.taker]),
+        Err(program_error(ErrorCode::PaymentMintNotAccepted))
+    );
+    assert!(fixture.runtime.account(&fixture.offer).is_some());
+}
+
+#[test]
+fn too_many_payment_options_are_rejected() {
+    let mut fixture = Fixture::new();
+    let other_payments = (0..MAX_PAYMENT_OPTIONS)
+        .map(|_| payment(Pubkey::new_unique()))
+        .collect();
+
+    assert_eq!(
+        fixture.make_second_offer(other_payments),
+        Err(program_error(ErrorCode::TooManyPaymentOptions))
+    );
+}
+
+#[test]
+fn duplicate_payment_mints_are_rejected() {
+    let mut fixture = Fixture::new();
+    let mint = Pubkey::new_unique();
+
+    for other_payments in [
+        vec![payment(mint), payment(mint)],
+        vec![payment(fixture.mint_b)],
+        vec![payment(fixture.mint_a)],
+    ] {
+        assert_eq!(
+            fixture.make_second_offer(other_payments),
+            Err(program_error(ErrorCode::DuplicatePaymentMint))
+        );
+    }
+}
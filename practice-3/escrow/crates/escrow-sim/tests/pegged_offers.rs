@@ -0,0 +1,267 @@
+//! Offers whose token B amount follows a mock oracle feed at take time.
+
+mod common;
+
+use anchor_lang::{
+    prelude::{Clock, ProgramError, Pubkey},
+    solana_program::instruction::Instruction,
+    system_program, AccountDeserialize, InstructionData, ToAccountMetas,
+};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use common::{Fixture, TAKER_TOKEN_B_BALANCE, TOKEN_A_OFFERED_AMOUNT, TOKEN_B_WANTED_AMOUNT};
+use escrow::{error::ErrorCode, Offer, PegTerms, PriceFeed, CONFIG_SEED};
+use escrow_sim::ExecutionError;
+
+/// 3 token B per token A.
+const PRICE: i64 = 300;
+const EXPONENT: i32 = -2;
+const MAX_STALENESS_SECS: u32 = 60;
+
+const TERMS: PegTerms = PegTerms {
+    spread_bps: -100,
+    max_staleness_secs: MAX_STALENESS_SECS,
+    max_confidence_bps: 100,
+};
+
+impl Fixture {
+    fn set_price(&mut self, price: i64, confidence: u64, publish_time: Option<i64>) {
+        let instruction = Instruction {
+            program_id: mock_oracle::ID,
+            accounts: mock_oracle::accounts::SetPrice {
+                authority: self.admin,
+                price_feed: self.price_feed.unwrap(),
+            }
+            .to_account_metas(None),
+            data: mock_oracle::instruction::SetPrice {
+                price,
+                confidence,
+                publish_time,
+            }
+            .data(),
+        };
+        self.runtime
+            .process_transaction(&[instruction], &[self.admin])
+            .expect("set_price failed");
+    }
+
+    fn set_unix_timestamp(&mut self, unix_timestamp: i64) {
+        self.runtime.set_clock(Clock {
+            unix_timestamp,
+            ..Clock::default()
+        });
+    }
+
+    fn take(&mut self) -> Result<(), ExecutionError> {
+        let instruction = self.take_offer(None);
+        self.runtime
+            .process_transaction(&[instruction], &[self.taker])
+    }
+
+    fn maker_token_b(&self) -> Option<u64> {
+        self.token_balance(&self.maker, &self.mint_b)
+    }
+
+    /// `make_offer` of a second offer by the same maker, pegged with `terms`
+    /// to whatever `price_feed` is.
+    fn make_pegged_offer(
+        &mut self,
+        price_feed: Option<Pubkey>,
+        terms: PegTerms,
+    ) -> Result<(), ExecutionError> {
+        let (offer, _) = Offer::pda(&self.maker, 8);
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::MakeOffer {
+                maker: self.maker,
+                token_mint_a: self.mint_a,
+                token_mint_b: self.mint_b,
+                maker_token_account_a: get_associated_token_address(&self.maker, &self.mint_a),
+                offer,
+                vault: get_associated_token_address(&offer, &self.mint_a),
+                config: Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0,
+                mint_registry: None,
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                price_feed,
+                nft_metadata: None,
+                rent_payer: None,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::MakeOffer {
+                id: 8,
+                token_a_offered_amount: 0,
+                token_b_wanted_amount: TOKEN_B_WANTED_AMOUNT,
+                other_payments: Vec::new(),
+                peg: Some(terms),
+            }
+            .data(),
+        };
+        self.runtime
+            .process_transaction(&[instruction], &[self.maker])
+    }
+}
+
+fn program_error(error: ErrorCode) -> ExecutionError {
+    ExecutionError::Program(ProgramError::from(anchor_lang::error::Error::from(error)))
+}
+
+#[test]
+fn mock_oracle_feeds_use_the_documented_layout() {
+    let fixture = Fixture::pegged(PRICE, EXPONENT, TERMS);
+    let price_feed = fixture.price_feed.unwrap();
+    let account = fixture.runtime.account(&price_feed).unwrap();
+    assert_eq!(account.owner, mock_oracle::ID);
+
+    let feed = PriceFeed::try_from_account_data(&account.data).unwrap();
+    assert_eq!(
+        feed,
+        PriceFeed {
+            price: PRICE,
+            confidence: 0,
+            exponent: EXPONENT,
+            publish_time: 0,
+        }
+    );
+    let mock = mock_oracle::PriceFeed::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(mock.authority, fixture.admin);
+
+    let offer = Offer::try_deserialize(
+        &mut fixture
+            .runtime
+            .account(&fixture.offer)
+            .unwrap()
+            .data
+            .as_slice(),
+    )
+    .unwrap();
+    let peg = offer.peg.unwrap();
+    assert_eq!(peg.price_feed, price_feed);
+    assert_eq!(peg.oracle_program, mock_oracle::ID);
+    assert_eq!(peg.terms, TERMS);
+}
+
+#[test]
+fn taker_pays_the_feed_price_minus_the_spread() {
+    let mut fixture = Fixture::pegged(PRICE, EXPONENT, TERMS);
+    fixture.take().expect("take_offer failed");
+
+    // 2.5 token A at 3 token B each, less 1%.
+    let wanted = 7_425_000;
+    assert_eq!(fixture.maker_token_b(), Some(wanted));
+    assert_eq!(
+        fixture.token_balance(&fixture.taker, &fixture.mint_b),
+        Some(TAKER_TOKEN_B_BALANCE - wanted)
+    );
+    assert_eq!(
+        fixture.token_balance(&fixture.taker, &fixture.mint_a),
+        Some(TOKEN_A_OFFERED_AMOUNT)
+    );
+}
+
+#[test]
+fn price_is_read_when_the_offer_is_taken() {
+    let mut fixture = Fixture::pegged(PRICE, EXPONENT, TERMS);
+    fixture.set_unix_timestamp(1_000);
+    fixture.set_price(320, 3, None);
+
+    fixture.take().expect("take_offer failed");
+    assert_eq!(fixture.maker_token_b(), Some(7_920_000));
+}
+
+#[test]
+fn wanted_amount_is_the_floor_when_the_feed_is_lower() {
+    let mut fixture = Fixture::pegged(100, EXPONENT, TERMS);
+    fixture.take().expect("take_offer failed");
+
+    assert_eq!(fixture.maker_token_b(), Some(TOKEN_B_WANTED_AMOUNT));
+}
+
+#[test]
+fn stale_price_is_rejected() {
+    let mut fixture = Fixture::pegged(PRICE, EXPONENT, TERMS);
+    fixture.set_unix_timestamp(MAX_STALENESS_SECS as i64 + 1);
+    assert_eq!(fixture.take(), Err(program_error(ErrorCode::StalePrice)));
+
+    fixture.set_price(PRICE, 0, None);
+    fixture.take().expect("take_offer failed");
+}
+
+#[test]
+fn price_published_in_the_future_is_rejected() {
+    let mut fixture = Fixture::pegged(PRICE, EXPONENT, TERMS);
+    fixture.set_unix_timestamp(1_000);
+    fixture.set_price(PRICE, 0, Some(1_001));
+    assert_eq!(
+        fixture.take(),
+        Err(program_error(ErrorCode::PriceFromTheFuture))
+    );
+    assert!(fixture.runtime.account(&fixture.offer).is_some());
+
+    fixture.set_price(PRICE, 0, Some(1_000));
+    fixture.take().expect("take_offer failed");
+}
+
+#[test]
+fn wide_confidence_interval_is_rejected() {
+    let mut fixture = Fixture::pegged(PRICE, EXPONENT, TERMS);
+    // 1% of the price is 3.
+    fixture.set_price(PRICE, 4, None);
+    assert_eq!(
+        fixture.take(),
+        Err(program_error(ErrorCode::PriceConfidenceTooWide))
+    );
+    assert!(fixture.runtime.account(&fixture.offer).is_some());
+}
+
+#[test]
+fn taker_must_pass_the_pegged_feed() {
+    let mut fixture = Fixture::pegged(PRICE, EXPONENT, TERMS);
+    let price_feed = fixture.price_feed.take();
+    assert_eq!(
+        fixture.take(),
+        Err(program_error(ErrorCode::PriceFeedRequired))
+    );
+
+    // Another feed in the same layout, at a much lower price.
+    let other = Fixture::pegged(1, EXPONENT, TERMS);
+    let other_feed = other.price_feed.unwrap();
+    let account = other.runtime.account(&other_feed).unwrap();
+    fixture.runtime.set_account(other_feed, account);
+    fixture.price_feed = Some(other_feed);
+    assert_eq!(
+        fixture.take(),
+        Err(program_error(ErrorCode::PriceFeedMismatch))
+    );
+
+    fixture.price_feed = price_feed;
+    fixture.take().expect("take_offer failed");
+}
+
+#[test]
+fn make_offer_checks_the_feed_and_terms() {
+    let mut fixture = Fixture::pegged(PRICE, EXPONENT, TERMS);
+    let price_feed = fixture.price_feed;
+
+    assert_eq!(
+        fixture.make_pegged_offer(None, TERMS),
+        Err(program_error(ErrorCode::PriceFeedRequired))
+    );
+    assert_eq!(
+        fixture.make_pegged_offer(Some(fixture.maker), TERMS),
+        Err(program_error(ErrorCode::InvalidPriceFeed))
+    );
+    assert_eq!(
+        fixture.make_pegged_offer(
+            price_feed,
+            PegTerms {
+                spread_bps: -10_000,
+                ..TERMS
+            }
+        ),
+        Err(program_error(ErrorCode::InvalidPriceSpread))
+    );
+    fixture
+        .make_pegged_offer(price_feed, TERMS)
+        .expect("make_offer failed");
+}
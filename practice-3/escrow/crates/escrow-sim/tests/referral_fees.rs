@@ -0,0 +1,175 @@
+//! Referral fees paid by `take_offer` and capped by `set_max_referral_fee`.
+
+mod common;
+
+use anchor_lang::{
+    prelude::{ProgramError, Pubkey},
+    solana_program::instruction::Instruction,
+    AccountDeserialize, InstructionData, ToAccountMetas,
+};
+use anchor_spl::associated_token::get_associated_token_address;
+use common::{Fixture, STARTING_LAMPORTS, TAKER_TOKEN_B_BALANCE, TOKEN_B_WANTED_AMOUNT};
+use escrow::{error::ErrorCode, Config, CONFIG_SEED};
+use escrow_sim::ExecutionError;
+
+impl Fixture {
+    fn set_max_referral_fee(
+        &mut self,
+        signer: Pubkey,
+        max_referral_fee_bps: u16,
+    ) -> Result<(), ExecutionError> {
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::SetMaxReferralFee {
+                admin: signer,
+                config: config_address(),
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::SetMaxReferralFee {
+                max_referral_fee_bps,
+            }
+            .data(),
+        };
+        self.runtime.process_transaction(&[instruction], &[signer])
+    }
+
+    fn max_referral_fee_bps(&self) -> u16 {
+        let account = self.runtime.account(&config_address()).unwrap();
+        Config::try_deserialize(&mut account.data.as_slice())
+            .unwrap()
+            .max_referral_fee_bps
+    }
+}
+
+fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0
+}
+
+fn program_error(error: ErrorCode) -> ExecutionError {
+    ExecutionError::Program(ProgramError::from(anchor_lang::error::Error::from(error)))
+}
+
+#[test]
+fn referral_fee_is_paid_on_top_of_the_wanted_amount() {
+    let mut fixture = Fixture::new();
+    fixture.set_max_referral_fee(fixture.admin, 100).unwrap();
+    let referrer = Pubkey::new_unique();
+
+    let instruction = fixture.take_offer_with(None, Some(referrer), 50);
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .expect("take_offer failed");
+
+    // 0.5% of 5 token B.
+    let referral_fee_amount = 25_000;
+    assert_eq!(
+        fixture.token_balance(&referrer, &fixture.mint_b),
+        Some(referral_fee_amount)
+    );
+    assert_eq!(
+        fixture.token_balance(&fixture.maker, &fixture.mint_b),
+        Some(TOKEN_B_WANTED_AMOUNT)
+    );
+    assert_eq!(
+        fixture.token_balance(&fixture.taker, &fixture.mint_b),
+        Some(TAKER_TOKEN_B_BALANCE - TOKEN_B_WANTED_AMOUNT - referral_fee_amount)
+    );
+    // The taker also pays for the referrer's new token account.
+    assert_eq!(
+        fixture.runtime.lamports(&fixture.taker),
+        STARTING_LAMPORTS - 3 * fixture.token_account_rent()
+    );
+}
+
+#[test]
+fn referral_fee_goes_to_an_existing_referrer_account() {
+    let mut fixture = Fixture::new();
+    fixture.set_max_referral_fee(fixture.admin, 100).unwrap();
+    // The maker refers the taker to their own offer, so the referrer token
+    // account is the one `take_offer` creates for the maker's proceeds.
+    let instruction = fixture.take_offer_with(None, Some(fixture.maker), 100);
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .expect("take_offer failed");
+
+    assert_eq!(
+        fixture.token_balance(&fixture.maker, &fixture.mint_b),
+        Some(TOKEN_B_WANTED_AMOUNT + 50_000)
+    );
+    assert_eq!(
+        fixture.runtime.lamports(&fixture.taker),
+        STARTING_LAMPORTS - 2 * fixture.token_account_rent()
+    );
+}
+
+#[test]
+fn referral_fee_above_the_config_cap_is_rejected() {
+    let mut fixture = Fixture::new();
+    let referrer = Pubkey::new_unique();
+
+    let instruction = fixture.take_offer_with(None, Some(referrer), 1);
+    assert_eq!(
+        fixture
+            .runtime
+            .process_transaction(&[instruction], &[fixture.taker]),
+        Err(program_error(ErrorCode::ReferralFeeTooHigh)),
+        "referrals are off until the admin sets a cap"
+    );
+
+    fixture.set_max_referral_fee(fixture.admin, 30).unwrap();
+    let instruction = fixture.take_offer_with(None, Some(referrer), 31);
+    assert_eq!(
+        fixture
+            .runtime
+            .process_transaction(&[instruction], &[fixture.taker]),
+        Err(program_error(ErrorCode::ReferralFeeTooHigh))
+    );
+    assert!(fixture.runtime.account(&fixture.offer).is_some());
+}
+
+#[test]
+fn referral_fee_needs_the_referrer_accounts() {
+    let mut fixture = Fixture::new();
+    fixture.set_max_referral_fee(fixture.admin, 100).unwrap();
+
+    let instruction = fixture.take_offer_with(None, None, 50);
+    assert_eq!(
+        fixture
+            .runtime
+            .process_transaction(&[instruction], &[fixture.taker]),
+        Err(program_error(ErrorCode::ReferrerRequired))
+    );
+}
+
+#[test]
+fn referrer_token_account_must_belong_to_the_referrer() {
+    let mut fixture = Fixture::new();
+    fixture.set_max_referral_fee(fixture.admin, 100).unwrap();
+    let referrer = Pubkey::new_unique();
+
+    let mut instruction = fixture.take_offer_with(None, Some(referrer), 50);
+    instruction.accounts[15].pubkey = get_associated_token_address(&fixture.taker, &fixture.mint_b);
+    assert!(fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .is_err());
+}
+
+#[test]
+fn only_the_admin_sets_the_referral_fee_cap() {
+    let mut fixture = Fixture::new();
+    assert_eq!(
+        fixture.set_max_referral_fee(fixture.maker, 100),
+        Err(program_error(ErrorCode::Unauthorized))
+    );
+    assert_eq!(
+        fixture.set_max_referral_fee(fixture.admin, 10_001),
+        Err(program_error(ErrorCode::InvalidBasisPoints))
+    );
+    assert_eq!(fixture.max_referral_fee_bps(), 0);
+
+    fixture.set_max_referral_fee(fixture.admin, 10_000).unwrap();
+    assert_eq!(fixture.max_referral_fee_bps(), 10_000);
+}
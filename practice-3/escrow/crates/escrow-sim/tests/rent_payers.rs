@@ -0,0 +1,326 @@
+//! Who pays each rent deposit, and who gets it back: the maker or a relayer
+//! sponsoring the offer for `offer` and `vault`, the taker or a relayer for
+//! the token accounts `take_offer` creates.
+
+mod common;
+
+use anchor_lang::{
+    prelude::{ProgramError, Pubkey},
+    solana_program::instruction::Instruction,
+    system_program, AccountDeserialize, InstructionData, ToAccountMetas,
+};
+use anchor_spl::associated_token::{self, get_associated_token_address};
+use common::{Fixture, OFFER_ID, STARTING_LAMPORTS, TOKEN_A_OFFERED_AMOUNT, TOKEN_B_WANTED_AMOUNT};
+use escrow::{error::ErrorCode, FillReceipt, Offer, CONFIG_SEED};
+use escrow_sim::ExecutionError;
+
+struct Sponsored {
+    fixture: Fixture,
+    relayer: Pubkey,
+}
+
+impl Sponsored {
+    /// The fixture offer, cancelled and made again with a relayer paying its
+    /// rent.
+    fn new() -> Self {
+        let mut fixture = Fixture::new();
+        let relayer = Pubkey::new_unique();
+        fixture.runtime.airdrop(&relayer, STARTING_LAMPORTS);
+        let mut sponsored = Self { fixture, relayer };
+
+        sponsored
+            .cancel(None)
+            .expect("cancelling the fixture offer failed");
+        sponsored
+            .make(Some(relayer))
+            .expect("making the sponsored offer failed");
+        sponsored
+    }
+
+    fn make(&mut self, rent_payer: Option<Pubkey>) -> Result<(), ExecutionError> {
+        let fixture = &mut self.fixture;
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::MakeOffer {
+                maker: fixture.maker,
+                token_mint_a: fixture.mint_a,
+                token_mint_b: fixture.mint_b,
+                maker_token_account_a: get_associated_token_address(
+                    &fixture.maker,
+                    &fixture.mint_a,
+                ),
+                offer: fixture.offer,
+                vault: get_associated_token_address(&fixture.offer, &fixture.mint_a),
+                config: config(),
+                mint_registry: None,
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                price_feed: None,
+                nft_metadata: None,
+                rent_payer,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::MakeOffer {
+                id: OFFER_ID,
+                token_a_offered_amount: TOKEN_A_OFFERED_AMOUNT,
+                token_b_wanted_amount: TOKEN_B_WANTED_AMOUNT,
+                other_payments: Vec::new(),
+                peg: None,
+            }
+            .data(),
+        };
+        let signers: Vec<_> = std::iter::once(fixture.maker).chain(rent_payer).collect();
+        fixture
+            .runtime
+            .process_transaction(&[instruction], &signers)
+    }
+
+    fn cancel(&mut self, offer_rent_payer: Option<Pubkey>) -> Result<(), ExecutionError> {
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::CancelOffer {
+                maker: self.fixture.maker,
+                token_mint_a: self.fixture.mint_a,
+                maker_token_account_a: get_associated_token_address(
+                    &self.fixture.maker,
+                    &self.fixture.mint_a,
+                ),
+                offer: self.fixture.offer,
+                vault: get_associated_token_address(&self.fixture.offer, &self.fixture.mint_a),
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                offer_rent_payer,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::CancelOffer {}.data(),
+        };
+        self.fixture
+            .runtime
+            .process_transaction(&[instruction], &[self.fixture.maker])
+    }
+
+    fn take(
+        &mut self,
+        fill_receipt: Option<Pubkey>,
+        rent_payer: Option<Pubkey>,
+        offer_rent_payer: Option<Pubkey>,
+    ) -> Result<(), ExecutionError> {
+        let fixture = &self.fixture;
+        let instruction = Instruction {
+            program_id: escrow::ID,
+            accounts: escrow::accounts::TakeOffer {
+                taker: fixture.taker,
+                maker: fixture.maker,
+                token_mint_a: fixture.mint_a,
+                token_mint_b: fixture.mint_b,
+                taker_token_account_a: get_associated_token_address(
+                    &fixture.taker,
+                    &fixture.mint_a,
+                ),
+                taker_token_account_b: get_associated_token_address(
+                    &fixture.taker,
+                    &fixture.mint_b,
+                ),
+                maker_token_account_b: get_associated_token_address(
+                    &fixture.maker,
+                    &fixture.mint_b,
+                ),
+                offer: fixture.offer,
+                vault: get_associated_token_address(&fixture.offer, &fixture.mint_a),
+                config: config(),
+                associated_token_program: associated_token::ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                fill_receipt,
+                referrer: None,
+                referrer_token_account_b: None,
+                price_feed: None,
+                nft_metadata: None,
+                rent_payer,
+                offer_rent_payer,
+            }
+            .to_account_metas(None),
+            data: escrow::instruction::TakeOffer {
+                referral_fee_bps: 0,
+            }
+            .data(),
+        };
+        let signers: Vec<_> = std::iter::once(fixture.taker).chain(rent_payer).collect();
+        self.fixture
+            .runtime
+            .process_transaction(&[instruction], &signers)
+    }
+
+    fn lamports(&self, address: &Pubkey) -> u64 {
+        self.fixture.runtime.lamports(address)
+    }
+
+    /// Rent of the offer account and its vault.
+    fn offer_rent(&self) -> u64 {
+        self.lamports(&self.fixture.offer) + self.fixture.token_account_rent()
+    }
+}
+
+fn config() -> Pubkey {
+    Pubkey::find_program_address(&[CONFIG_SEED], &escrow::ID).0
+}
+
+fn program_error(error: ErrorCode) -> ExecutionError {
+    ExecutionError::Program(ProgramError::from(anchor_lang::error::Error::from(error)))
+}
+
+#[test]
+fn makers_pay_for_their_own_offers_by_default() {
+    let mut fixture = Fixture::new();
+    let offer_rent = fixture.runtime.lamports(&fixture.offer) + fixture.token_account_rent();
+    assert_eq!(
+        fixture.runtime.lamports(&fixture.maker),
+        STARTING_LAMPORTS - offer_rent
+    );
+    let account = fixture.runtime.account(&fixture.offer).unwrap();
+    let offer = Offer::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(offer.rent_payer, fixture.maker);
+
+    let instruction = fixture.take_offer(None);
+    fixture
+        .runtime
+        .process_transaction(&[instruction], &[fixture.taker])
+        .unwrap();
+
+    // The maker gets the offer and vault back, the taker pays for its token A
+    // account and the maker's token B account.
+    assert_eq!(fixture.runtime.lamports(&fixture.maker), STARTING_LAMPORTS);
+    assert_eq!(
+        fixture.runtime.lamports(&fixture.taker),
+        STARTING_LAMPORTS - 2 * fixture.token_account_rent()
+    );
+}
+
+#[test]
+fn relayer_pays_for_a_sponsored_offer() {
+    let sponsored = Sponsored::new();
+    let fixture = &sponsored.fixture;
+
+    let account = fixture.runtime.account(&fixture.offer).unwrap();
+    let offer = Offer::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(offer.maker, fixture.maker);
+    assert_eq!(offer.rent_payer, sponsored.relayer);
+
+    assert_eq!(sponsored.lamports(&fixture.maker), STARTING_LAMPORTS);
+    assert_eq!(
+        sponsored.lamports(&sponsored.relayer),
+        STARTING_LAMPORTS - sponsored.offer_rent()
+    );
+}
+
+#[test]
+fn sponsored_take_refunds_the_relayer_and_costs_the_parties_nothing() {
+    let mut sponsored = Sponsored::new();
+    let relayer = sponsored.relayer;
+    let receipt = FillReceipt::pda(&sponsored.fixture.offer, &sponsored.fixture.taker).0;
+
+    sponsored
+        .take(Some(receipt), Some(relayer), Some(relayer))
+        .unwrap();
+
+    let fixture = &sponsored.fixture;
+    assert!(fixture.runtime.account(&fixture.offer).is_none());
+    assert_eq!(
+        fixture.token_balance(&fixture.maker, &fixture.mint_b),
+        Some(TOKEN_B_WANTED_AMOUNT)
+    );
+    // The relayer gets the offer and vault back, and pays for the taker's
+    // token A account and the maker's token B account.
+    assert_eq!(
+        sponsored.lamports(&relayer),
+        STARTING_LAMPORTS - 2 * fixture.token_account_rent()
+    );
+    assert_eq!(sponsored.lamports(&fixture.maker), STARTING_LAMPORTS);
+    // The fill receipt is the taker's own, and closing it refunds them.
+    assert_eq!(
+        sponsored.lamports(&fixture.taker),
+        STARTING_LAMPORTS - sponsored.lamports(&receipt)
+    );
+}
+
+#[test]
+fn unsponsored_take_of_a_sponsored_offer_still_refunds_the_relayer() {
+    let mut sponsored = Sponsored::new();
+    let relayer = sponsored.relayer;
+
+    sponsored.take(None, None, Some(relayer)).unwrap();
+
+    let fixture = &sponsored.fixture;
+    assert_eq!(sponsored.lamports(&relayer), STARTING_LAMPORTS);
+    assert_eq!(sponsored.lamports(&fixture.maker), STARTING_LAMPORTS);
+    assert_eq!(
+        sponsored.lamports(&fixture.taker),
+        STARTING_LAMPORTS - 2 * fixture.token_account_rent()
+    );
+}
+
+#[test]
+fn sponsored_take_of_an_unsponsored_offer_refunds_the_maker() {
+    let mut fixture = Fixture::new();
+    let relayer = Pubkey::new_unique();
+    fixture.runtime.airdrop(&relayer, STARTING_LAMPORTS);
+    let mut sponsored = Sponsored { fixture, relayer };
+
+    sponsored.take(None, Some(relayer), None).unwrap();
+
+    let fixture = &sponsored.fixture;
+    assert_eq!(sponsored.lamports(&fixture.maker), STARTING_LAMPORTS);
+    assert_eq!(sponsored.lamports(&fixture.taker), STARTING_LAMPORTS);
+    assert_eq!(
+        sponsored.lamports(&relayer),
+        STARTING_LAMPORTS - 2 * fixture.token_account_rent()
+    );
+}
+
+#[test]
+fn cancelling_a_sponsored_offer_refunds_the_relayer() {
+    let mut sponsored = Sponsored::new();
+    let relayer = sponsored.relayer;
+
+    sponsored.cancel(Some(relayer)).unwrap();
+
+    let fixture = &sponsored.fixture;
+    assert!(fixture.runtime.account(&fixture.offer).is_none());
+    assert_eq!(
+        fixture.token_balance(&fixture.maker, &fixture.mint_a),
+        Some(TOKEN_A_OFFERED_AMOUNT)
+    );
+    assert_eq!(sponsored.lamports(&relayer), STARTING_LAMPORTS);
+    assert_eq!(sponsored.lamports(&fixture.maker), STARTING_LAMPORTS);
+}
+
+#[test]
+fn sponsored_offers_only_close_into_their_rent_payer() {
+    let mut sponsored = Sponsored::new();
+    let maker = sponsored.fixture.maker;
+    let taker = sponsored.fixture.taker;
+
+    assert_eq!(
+        sponsored.take(None, None, None),
+        Err(program_error(ErrorCode::RentPayerRequired))
+    );
+    assert_eq!(
+        sponsored.take(None, None, Some(taker)),
+        Err(program_error(ErrorCode::RentPayerMismatch))
+    );
+    assert_eq!(
+        sponsored.cancel(None),
+        Err(program_error(ErrorCode::RentPayerRequired))
+    );
+    assert_eq!(
+        sponsored.cancel(Some(maker)),
+        Err(program_error(ErrorCode::RentPayerMismatch))
+    );
+    assert!(sponsored
+        .fixture
+        .runtime
+        .account(&sponsored.fixture.offer)
+        .is_some());
+}
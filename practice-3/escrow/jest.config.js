@@ -1,5 +1,8 @@
 const MILLISECONDS = 1000;
 
 module.exports = {
-    testTimeout: 60 * MILLISECONDS
+    testTimeout: 60 * MILLISECONDS,
+    // Test files share the escrow config, and pausing it in one file must not
+    // fail offers made in another.
+    maxWorkers: 1,
 }
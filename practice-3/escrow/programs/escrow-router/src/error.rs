@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Both offers are the same account")]
+    SameOffer,
+    #[msg("Maker account does not match the offer")]
+    MakerMismatch,
+    #[msg("Cheapest offer wants more token B than the taker allowed")]
+    PriceAboveLimit,
+    #[msg("Offer does not accept token B as payment")]
+    PaymentMintNotAccepted,
+    #[msg("Pegged offers are priced at take time and can not be compared")]
+    PeggedOffer,
+    #[msg("NFT listings pay creator royalties, which the router does not collect")]
+    NftListing,
+    #[msg("Sponsored offers refund their rent to an account the router does not pass")]
+    SponsoredOffer,
+}
@@ -0,0 +1,184 @@
+// `#[program]` in anchor-lang 0.31.1 still expands to `AccountInfo::realloc`.
+#![allow(deprecated)]
+
+pub mod error;
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+use escrow::{program::Escrow, Offer};
+
+use crate::error::ErrorCode;
+
+declare_id!("9isV8kGt8aYqjn7td6RzmSHftf1MJhTBudExrM8HHRMC");
+
+/// Example of a program composing with the escrow: given two offers of the
+/// same token A that both accept token B, takes the one with the lower token
+/// B price per token A.
+#[program]
+pub mod escrow_router {
+    use super::*;
+
+    pub fn take_best_offer(context: Context<TakeBestOffer>, max_token_b_amount: u64) -> Result<()> {
+        let accounts = &context.accounts;
+
+        let token_mint_b = accounts.token_mint_b.key();
+        let first_wanted = accounts
+            .first_offer
+            .wanted_amount(&token_mint_b)
+            .ok_or(ErrorCode::PaymentMintNotAccepted)?;
+        let second_wanted = accounts
+            .second_offer
+            .wanted_amount(&token_mint_b)
+            .ok_or(ErrorCode::PaymentMintNotAccepted)?;
+
+        let first_is_cheaper = is_cheaper(
+            first_wanted,
+            accounts.first_vault.amount,
+            second_wanted,
+            accounts.second_vault.amount,
+        );
+        let (maker, maker_token_account_b, offer, vault, wanted) = if first_is_cheaper {
+            (
+                &accounts.first_maker,
+                &accounts.first_maker_token_account_b,
+                &accounts.first_offer,
+                &accounts.first_vault,
+                first_wanted,
+            )
+        } else {
+            (
+                &accounts.second_maker,
+                &accounts.second_maker_token_account_b,
+                &accounts.second_offer,
+                &accounts.second_vault,
+                second_wanted,
+            )
+        };
+
+        require!(wanted <= max_token_b_amount, ErrorCode::PriceAboveLimit);
+        msg!("Taking offer {} by {}", offer.key(), maker.key());
+
+        let cpi_accounts = escrow::cpi::accounts::TakeOffer {
+            taker: accounts.taker.to_account_info(),
+            maker: maker.to_account_info(),
+            token_mint_a: accounts.token_mint_a.to_account_info(),
+            token_mint_b: accounts.token_mint_b.to_account_info(),
+            taker_token_account_a: accounts.taker_token_account_a.to_account_info(),
+            taker_token_account_b: accounts.taker_token_account_b.to_account_info(),
+            maker_token_account_b: maker_token_account_b.to_account_info(),
+            offer: offer.to_account_info(),
+            vault: vault.to_account_info(),
+            associated_token_program: accounts.associated_token_program.to_account_info(),
+            token_program: accounts.token_program.to_account_info(),
+            system_program: accounts.system_program.to_account_info(),
+            config: accounts.config.to_account_info(),
+            fill_receipt: None,
+            referrer: None,
+            referrer_token_account_b: None,
+            price_feed: None,
+            nft_metadata: None,
+            rent_payer: None,
+            offer_rent_payer: None,
+        };
+
+        escrow::cpi::take_offer(
+            CpiContext::new(accounts.escrow_program.to_account_info(), cpi_accounts),
+            0,
+        )
+    }
+}
+
+/// Compares `wanted_b / offered_a` of two offers without losing precision.
+/// Ties go to the first offer.
+fn is_cheaper(
+    first_wanted: u64,
+    first_offered: u64,
+    second_wanted: u64,
+    second_offered: u64,
+) -> bool {
+    first_wanted as u128 * second_offered as u128 <= second_wanted as u128 * first_offered as u128
+}
+
+#[derive(Accounts)]
+pub struct TakeBestOffer<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    pub token_mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Validated, and created if missing, by the escrow program.
+    #[account(mut)]
+    pub taker_token_account_a: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub first_maker: SystemAccount<'info>,
+
+    /// CHECK: Validated, and created if missing, by the escrow program.
+    #[account(mut)]
+    pub first_maker_token_account_b: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = token_mint_a,
+        constraint = first_offer.maker == first_maker.key() @ ErrorCode::MakerMismatch,
+        constraint = first_offer.peg.is_none() @ ErrorCode::PeggedOffer,
+        constraint = first_offer.nft_collection.is_none() @ ErrorCode::NftListing,
+        constraint = first_offer.rent_payer == first_maker.key() @ ErrorCode::SponsoredOffer,
+    )]
+    pub first_offer: Box<Account<'info, Offer>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = first_offer,
+        associated_token::token_program = token_program,
+    )]
+    pub first_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub second_maker: SystemAccount<'info>,
+
+    /// CHECK: Validated, and created if missing, by the escrow program.
+    #[account(mut)]
+    pub second_maker_token_account_b: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = token_mint_a,
+        constraint = second_offer.maker == second_maker.key() @ ErrorCode::MakerMismatch,
+        constraint = second_offer.peg.is_none() @ ErrorCode::PeggedOffer,
+        constraint = second_offer.nft_collection.is_none() @ ErrorCode::NftListing,
+        constraint = second_offer.rent_payer == second_maker.key() @ ErrorCode::SponsoredOffer,
+        constraint = second_offer.key() != first_offer.key() @ ErrorCode::SameOffer,
+    )]
+    pub second_offer: Box<Account<'info, Offer>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = second_offer,
+        associated_token::token_program = token_program,
+    )]
+    pub second_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub escrow_program: Program<'info, Escrow>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Validated by the escrow program.
+    pub config: UncheckedAccount<'info>,
+}
@@ -3,4 +3,37 @@ use anchor_lang::prelude::*;
 #[constant]
 pub const SEED: &str = "anchor";
 
+#[constant]
+pub const OFFER_SEED: &[u8] = b"offer";
+
+/// Layout version written by `make_offer`. Older offers are brought up to date
+/// by `migrate_offer`.
+#[constant]
+pub const OFFER_VERSION: u8 = 2;
+
+#[constant]
+pub const CONFIG_SEED: &[u8] = b"config";
+
+#[constant]
+pub const COUNTER_OFFER_SEED: &[u8] = b"counter_offer";
+
+#[constant]
+pub const FILL_RECEIPT_SEED: &[u8] = b"fill_receipt";
+
+#[constant]
+pub const MINT_REGISTRY_SEED: &[u8] = b"mint_registry";
+
+/// Denominator of fees expressed in basis points.
+#[constant]
+pub const BASIS_POINTS: u16 = 10_000;
+
+pub const MAX_ALLOWED_MINTS: usize = 32;
+
+/// Most (mint, amount) pairs one offer accepts as payment, counting
+/// `Offer::token_mint_b`.
+pub const MAX_PAYMENT_OPTIONS: usize = 4;
+
+/// Share of an NFT's royalties that all its creators add up to, in percent.
+pub const CREATOR_SHARES: u8 = 100;
+
 pub const ANCHOR_DISCRIMINATOR: usize = 8;
@@ -4,4 +4,74 @@ use anchor_lang::prelude::*;
 pub enum ErrorCode {
     #[msg("Custom error message")]
     CustomError,
+    #[msg("The escrow is paused")]
+    Paused,
+    #[msg("Only the config admin can do this")]
+    Unauthorized,
+    #[msg("Signer is not the program upgrade authority")]
+    NotUpgradeAuthority,
+    #[msg("Pause flag already has this value")]
+    PauseUnchanged,
+    #[msg("Mint allowlist is enabled, the mint registry account is required")]
+    MintRegistryRequired,
+    #[msg("Mint is not in the allowlist")]
+    MintNotAllowed,
+    #[msg("Mint is already in the allowlist")]
+    MintAlreadyAllowed,
+    #[msg("Mint registry is full")]
+    MintRegistryFull,
+    #[msg("Offer already uses the current layout")]
+    OfferAlreadyMigrated,
+    #[msg("Offer data does not match any known layout")]
+    UnknownOfferLayout,
+    #[msg("Basis points can not exceed 10000")]
+    InvalidBasisPoints,
+    #[msg("Referral fee is above the maximum set in the config")]
+    ReferralFeeTooHigh,
+    #[msg("A referral fee needs the referrer and its token B account")]
+    ReferrerRequired,
+    #[msg("Offer lists more payment options than allowed")]
+    TooManyPaymentOptions,
+    #[msg("Payment mints must differ from each other and from token A")]
+    DuplicatePaymentMint,
+    #[msg("Offer does not accept this mint as payment")]
+    PaymentMintNotAccepted,
+    #[msg("Pegged offers need the price feed account")]
+    PriceFeedRequired,
+    #[msg("Price feed is not the one the offer is pegged to")]
+    PriceFeedMismatch,
+    #[msg("Price feed data does not match the expected layout")]
+    InvalidPriceFeed,
+    #[msg("Price spread must be within 10000 basis points")]
+    InvalidPriceSpread,
+    #[msg("Price feed has not been updated recently enough")]
+    StalePrice,
+    #[msg("Price feed confidence interval is too wide")]
+    PriceConfidenceTooWide,
+    #[msg("Pegged token B amount does not fit in a u64")]
+    PriceOutOfRange,
+    #[msg("Counter offer must escrow some token B")]
+    InvalidCounterOfferAmount,
+    #[msg("Offer changed since the counter offer was made")]
+    CounterOfferOutdated,
+    #[msg("NFT listings must offer the single token of a mint with supply 1 and no decimals")]
+    NotAnNft,
+    #[msg("Metadata account does not belong to token A")]
+    NftMetadataMismatch,
+    #[msg("NFT is not part of a verified collection")]
+    NftCollectionNotVerified,
+    #[msg("NFT listings need the metadata account to pay royalties")]
+    NftMetadataRequired,
+    #[msg("Royalty accounts must be the token B ATAs of the NFT creators, in order")]
+    RoyaltyAccountMismatch,
+    #[msg("Not supported for NFT listings")]
+    NftListing,
+    #[msg("Sponsored offers need their rent payer to refund the rent to")]
+    RentPayerRequired,
+    #[msg("Account is not the rent payer of the offer")]
+    RentPayerMismatch,
+    #[msg("Only the maker can pay to migrate an offer")]
+    MigrationPayerNotMaker,
+    #[msg("Price feed was published later than the current time")]
+    PriceFromTheFuture,
 }
@@ -0,0 +1,109 @@
+use anchor_lang::prelude::*;
+
+use crate::{PaymentOption, PegTerms};
+
+#[event]
+pub struct Paused {
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Unpaused {
+    pub admin: Pubkey,
+    pub timestamp: i64,
+}
+
+// Offer lifecycle events carry enough to build an index of offers from
+// transaction logs alone, without reading closed accounts.
+
+#[event]
+pub struct OfferMade {
+    pub offer: Pubkey,
+    pub maker: Pubkey,
+    pub id: u64,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_offered_amount: u64,
+    pub token_b_wanted_amount: u64,
+    pub other_payments: Vec<PaymentOption>,
+}
+
+/// Follows `OfferMade` for offers whose token B amount tracks a price feed.
+#[event]
+pub struct OfferPegged {
+    pub offer: Pubkey,
+    pub price_feed: Pubkey,
+    pub terms: PegTerms,
+}
+
+/// Follows the `OfferMade` of an NFT listing.
+#[event]
+pub struct NftListed {
+    pub offer: Pubkey,
+    pub collection: Pubkey,
+}
+
+#[event]
+pub struct OfferTaken {
+    pub offer: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_amount: u64,
+    /// The price, paid to the maker less any royalties, not counting the
+    /// referral fee.
+    pub token_b_amount: u64,
+    pub referrer: Option<Pubkey>,
+    /// Token B the taker paid the referrer on top of `token_b_amount`.
+    pub referral_fee_amount: u64,
+}
+
+/// One creator's cut of an NFT sale, taken out of what the maker receives.
+/// Logged before the sale's `OfferTaken`.
+#[event]
+pub struct RoyaltyPaid {
+    pub offer: Pubkey,
+    pub creator: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CounterOfferMade {
+    pub counter_offer: Pubkey,
+    pub offer: Pubkey,
+    pub counter_party: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_b_amount: u64,
+}
+
+/// Followed by the `OfferTaken` of the offer it settled.
+#[event]
+pub struct CounterOfferAccepted {
+    pub counter_offer: Pubkey,
+    pub offer: Pubkey,
+    pub counter_party: Pubkey,
+}
+
+/// The counter party got their token B back, because the maker rejected the
+/// counter offer or they withdrew it.
+#[event]
+pub struct CounterOfferClosed {
+    pub counter_offer: Pubkey,
+    pub offer: Pubkey,
+    pub counter_party: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_b_amount: u64,
+    pub rejected: bool,
+}
+
+#[event]
+pub struct OfferCancelled {
+    pub offer: Pubkey,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_amount: u64,
+}
@@ -0,0 +1,219 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{
+    error::ErrorCode, Config, CounterOffer, CounterOfferAccepted, Offer, OfferTaken, CONFIG_SEED,
+    COUNTER_OFFER_SEED, OFFER_SEED,
+};
+
+/// Settles an offer at the counter party's price: the counter vault goes to
+/// the maker and the offer vault to the counter party, and both vaults,
+/// the offer and the counter offer are closed. The maker pays for any token
+/// account either side is missing, and the offer's rent goes back to whoever
+/// paid it.
+#[derive(Accounts)]
+pub struct AcceptCounterOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mut)]
+    pub counter_party: SystemAccount<'info>,
+
+    pub token_mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = counter_party,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_party_token_account_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = token_mint_a,
+        constraint = offer.nft_collection.is_none() @ ErrorCode::NftListing,
+        seeds = [OFFER_SEED, maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Box<Account<'info, Offer>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        close = counter_party,
+        has_one = offer,
+        has_one = counter_party,
+        has_one = token_mint_a,
+        has_one = token_mint_b,
+        constraint = counter_offer.token_a_amount == vault.amount
+            @ ErrorCode::CounterOfferOutdated,
+    )]
+    pub counter_offer: Box<Account<'info, CounterOffer>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = counter_offer,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Must be `offer.rent_payer`, checked in
+    /// `Offer::rent_refund_destination`. Only needed for sponsored offers.
+    #[account(mut)]
+    pub offer_rent_payer: Option<UncheckedAccount<'info>>,
+}
+
+pub fn send_counter_tokens_to_maker(context: &Context<AcceptCounterOffer>) -> Result<()> {
+    let counter_offer = &context.accounts.counter_offer;
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        COUNTER_OFFER_SEED,
+        counter_offer.offer.as_ref(),
+        counter_offer.counter_party.as_ref(),
+        &[counter_offer.bump],
+    ]];
+
+    let accounts = TransferChecked {
+        from: context.accounts.counter_vault.to_account_info(),
+        mint: context.accounts.token_mint_b.to_account_info(),
+        to: context.accounts.maker_token_account_b.to_account_info(),
+        authority: counter_offer.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(
+        context.accounts.token_program.to_account_info(),
+        accounts,
+        &signer_seeds,
+    );
+    transfer_checked(
+        cpi_context,
+        context.accounts.counter_vault.amount,
+        context.accounts.token_mint_b.decimals,
+    )?;
+    // The counter party paid for the counter vault, so its rent goes back to
+    // them.
+    let accounts = CloseAccount {
+        account: context.accounts.counter_vault.to_account_info(),
+        destination: context.accounts.counter_party.to_account_info(),
+        authority: counter_offer.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(
+        context.accounts.token_program.to_account_info(),
+        accounts,
+        &signer_seeds,
+    );
+
+    close_account(cpi_context)
+}
+
+pub fn withdraw_and_close_offer_vault(context: Context<AcceptCounterOffer>) -> Result<()> {
+    let rent_refund_destination = context.accounts.offer.rent_refund_destination(
+        &context.accounts.maker.to_account_info(),
+        context
+            .accounts
+            .offer_rent_payer
+            .as_ref()
+            .map(|offer_rent_payer| offer_rent_payer.as_ref()),
+    )?;
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        OFFER_SEED,
+        context.accounts.maker.to_account_info().key.as_ref(),
+        &context.accounts.offer.id.to_le_bytes()[..],
+        &[context.accounts.offer.bump],
+    ]];
+
+    let accounts = TransferChecked {
+        from: context.accounts.vault.to_account_info(),
+        mint: context.accounts.token_mint_a.to_account_info(),
+        to: context
+            .accounts
+            .counter_party_token_account_a
+            .to_account_info(),
+        authority: context.accounts.offer.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(
+        context.accounts.token_program.to_account_info(),
+        accounts,
+        &signer_seeds,
+    );
+    transfer_checked(
+        cpi_context,
+        context.accounts.vault.amount,
+        context.accounts.token_mint_a.decimals,
+    )?;
+
+    let accounts = CloseAccount {
+        account: context.accounts.vault.to_account_info(),
+        destination: rent_refund_destination.clone(),
+        authority: context.accounts.offer.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(
+        context.accounts.token_program.to_account_info(),
+        accounts,
+        &signer_seeds,
+    );
+
+    close_account(cpi_context)?;
+    context.accounts.offer.close(rent_refund_destination)?;
+
+    emit!(CounterOfferAccepted {
+        counter_offer: context.accounts.counter_offer.key(),
+        offer: context.accounts.offer.key(),
+        counter_party: context.accounts.counter_party.key(),
+    });
+    // Indexers see the offer filled like any other take.
+    emit!(OfferTaken {
+        offer: context.accounts.offer.key(),
+        maker: context.accounts.maker.key(),
+        taker: context.accounts.counter_party.key(),
+        token_mint_a: context.accounts.token_mint_a.key(),
+        token_mint_b: context.accounts.token_mint_b.key(),
+        token_a_amount: context.accounts.vault.amount,
+        token_b_amount: context.accounts.counter_vault.amount,
+        referrer: None,
+        referral_fee_amount: 0,
+    });
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token_interface::Mint;
+
+use crate::{
+    error::ErrorCode, Config, MintRegistry, CONFIG_SEED, MAX_ALLOWED_MINTS, MINT_REGISTRY_SEED,
+};
+
+#[derive(Accounts)]
+pub struct AddAllowedMint<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [MINT_REGISTRY_SEED],
+        bump = mint_registry.bump,
+    )]
+    pub mint_registry: Account<'info, MintRegistry>,
+
+    // Taking the mint as an account, rather than a `Pubkey` argument, makes
+    // sure only real mints end up in the registry.
+    pub mint: InterfaceAccount<'info, Mint>,
+}
+
+pub fn push_mint(context: Context<AddAllowedMint>) -> Result<()> {
+    let mint = context.accounts.mint.key();
+    let registry = &mut context.accounts.mint_registry;
+
+    require!(!registry.contains(&mint), ErrorCode::MintAlreadyAllowed);
+    require!(
+        registry.mints.len() < MAX_ALLOWED_MINTS,
+        ErrorCode::MintRegistryFull
+    );
+
+    registry.mints.push(mint);
+    Ok(())
+}
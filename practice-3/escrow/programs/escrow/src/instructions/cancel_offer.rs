@@ -0,0 +1,116 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{Offer, OfferCancelled, OFFER_SEED};
+
+/// Returns the vault to the maker and closes the offer, refunding its rent to
+/// whoever paid it. Deliberately does not read the config, so makers can
+/// withdraw while the escrow is paused.
+#[derive(Accounts)]
+pub struct CancelOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    pub token_mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = maker,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        has_one = maker,
+        has_one = token_mint_a,
+        seeds = [OFFER_SEED, maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: Must be `offer.rent_payer`, checked in
+    /// `Offer::rent_refund_destination`. Only needed for sponsored offers.
+    #[account(mut)]
+    pub offer_rent_payer: Option<UncheckedAccount<'info>>,
+}
+
+pub fn refund_and_close_vault(context: Context<CancelOffer>) -> Result<()> {
+    let rent_refund_destination = context.accounts.offer.rent_refund_destination(
+        &context.accounts.maker.to_account_info(),
+        context
+            .accounts
+            .offer_rent_payer
+            .as_ref()
+            .map(|offer_rent_payer| offer_rent_payer.as_ref()),
+    )?;
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        OFFER_SEED,
+        context.accounts.maker.to_account_info().key.as_ref(),
+        &context.accounts.offer.id.to_le_bytes()[..],
+        &[context.accounts.offer.bump],
+    ]];
+
+    let accounts = TransferChecked {
+        from: context.accounts.vault.to_account_info(),
+        mint: context.accounts.token_mint_a.to_account_info(),
+        to: context.accounts.maker_token_account_a.to_account_info(),
+        authority: context.accounts.offer.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(
+        context.accounts.token_program.to_account_info(),
+        accounts,
+        &signer_seeds,
+    );
+    transfer_checked(
+        cpi_context,
+        context.accounts.vault.amount,
+        context.accounts.token_mint_a.decimals,
+    )?;
+
+    let accounts = CloseAccount {
+        account: context.accounts.vault.to_account_info(),
+        destination: rent_refund_destination.clone(),
+        authority: context.accounts.offer.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(
+        context.accounts.token_program.to_account_info(),
+        accounts,
+        &signer_seeds,
+    );
+
+    close_account(cpi_context)?;
+    context.accounts.offer.close(rent_refund_destination)?;
+
+    emit!(OfferCancelled {
+        offer: context.accounts.offer.key(),
+        maker: context.accounts.maker.key(),
+        token_mint_a: context.accounts.token_mint_a.key(),
+        token_mint_b: context.accounts.offer.token_mint_b,
+        token_a_amount: context.accounts.vault.amount,
+    });
+    Ok(())
+}
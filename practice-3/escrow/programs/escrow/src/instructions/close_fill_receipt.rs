@@ -0,0 +1,20 @@
+use anchor_lang::prelude::*;
+
+use crate::{FillReceipt, FILL_RECEIPT_SEED};
+
+/// Closes a receipt written by `take_offer` and returns its rent to the taker
+/// who paid for it.
+#[derive(Accounts)]
+pub struct CloseFillReceipt<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(
+        mut,
+        close = taker,
+        has_one = taker,
+        seeds = [FILL_RECEIPT_SEED, fill_receipt.offer.as_ref(), taker.key().as_ref()],
+        bump = fill_receipt.bump
+    )]
+    pub fill_receipt: Account<'info, FillReceipt>,
+}
@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, program::Escrow, Config, ANCHOR_DISCRIMINATOR, CONFIG_SEED};
+
+/// Creates the config. Only the program upgrade authority can do this, so
+/// nobody can front-run the deployer and claim the admin role.
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ANCHOR_DISCRIMINATOR + Config::INIT_SPACE,
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, Escrow>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ErrorCode::NotUpgradeAuthority
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn save_config(context: Context<InitializeConfig>) -> Result<()> {
+    context.accounts.config.set_inner(Config {
+        admin: context.accounts.admin.key(),
+        paused: false,
+        mint_allowlist_enabled: false,
+        max_referral_fee_bps: 0,
+        bump: context.bumps.config,
+    });
+    Ok(())
+}
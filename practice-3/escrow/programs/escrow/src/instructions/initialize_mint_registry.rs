@@ -0,0 +1,37 @@
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::ErrorCode, Config, MintRegistry, ANCHOR_DISCRIMINATOR, CONFIG_SEED, MINT_REGISTRY_SEED,
+};
+
+#[derive(Accounts)]
+pub struct InitializeMintRegistry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ANCHOR_DISCRIMINATOR + MintRegistry::INIT_SPACE,
+        seeds = [MINT_REGISTRY_SEED],
+        bump
+    )]
+    pub mint_registry: Account<'info, MintRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn save_mint_registry(context: Context<InitializeMintRegistry>) -> Result<()> {
+    context.accounts.mint_registry.set_inner(MintRegistry {
+        mints: Vec::new(),
+        bump: context.bumps.mint_registry,
+    });
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    error::ErrorCode, Config, CounterOffer, CounterOfferMade, Offer, ANCHOR_DISCRIMINATOR,
+    CONFIG_SEED, COUNTER_OFFER_SEED,
+};
+
+/// Escrows `token_b_amount` of one of the offer's payment mints as a
+/// proposal to the maker, who can settle it with `accept_counter_offer`.
+#[derive(Accounts)]
+pub struct MakeCounterOffer<'info> {
+    #[account(mut)]
+    pub counter_party: Signer<'info>,
+
+    #[account(
+        has_one = token_mint_a,
+        constraint = offer.wanted_amount(&token_mint_b.key()).is_some()
+            @ ErrorCode::PaymentMintNotAccepted,
+        // Accepting does not pay creator royalties.
+        constraint = offer.nft_collection.is_none() @ ErrorCode::NftListing,
+    )]
+    pub offer: Box<Account<'info, Offer>>,
+
+    #[account(
+        associated_token::mint = token_mint_a,
+        associated_token::authority = offer,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = counter_party,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_party_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = counter_party,
+        space = ANCHOR_DISCRIMINATOR + CounterOffer::INIT_SPACE,
+        seeds = [COUNTER_OFFER_SEED, offer.key().as_ref(), counter_party.key().as_ref()],
+        bump
+    )]
+    pub counter_offer: Box<Account<'info, CounterOffer>>,
+
+    #[account(
+        init,
+        payer = counter_party,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = counter_offer,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused,
+    )]
+    pub config: Box<Account<'info, Config>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn send_counter_tokens_to_vault(
+    context: &Context<MakeCounterOffer>,
+    token_b_amount: u64,
+) -> Result<()> {
+    require!(token_b_amount > 0, ErrorCode::InvalidCounterOfferAmount);
+
+    let transfer_accounts = TransferChecked {
+        from: context
+            .accounts
+            .counter_party_token_account_b
+            .to_account_info(),
+        mint: context.accounts.token_mint_b.to_account_info(),
+        to: context.accounts.counter_vault.to_account_info(),
+        authority: context.accounts.counter_party.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new(
+        context.accounts.token_program.to_account_info(),
+        transfer_accounts,
+    );
+
+    transfer_checked(
+        cpi_context,
+        token_b_amount,
+        context.accounts.token_mint_b.decimals,
+    )
+}
+
+pub fn save_counter_offer(context: Context<MakeCounterOffer>, token_b_amount: u64) -> Result<()> {
+    context.accounts.counter_offer.set_inner(CounterOffer {
+        offer: context.accounts.offer.key(),
+        counter_party: context.accounts.counter_party.key(),
+        token_mint_a: context.accounts.token_mint_a.key(),
+        token_a_amount: context.accounts.vault.amount,
+        token_mint_b: context.accounts.token_mint_b.key(),
+        token_b_amount,
+        bump: context.bumps.counter_offer,
+    });
+
+    emit!(CounterOfferMade {
+        counter_offer: context.accounts.counter_offer.key(),
+        offer: context.accounts.offer.key(),
+        counter_party: context.accounts.counter_party.key(),
+        token_mint_b: context.accounts.token_mint_b.key(),
+        token_b_amount,
+    });
+    Ok(())
+}
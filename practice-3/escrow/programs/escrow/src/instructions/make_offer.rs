@@ -5,8 +5,29 @@ use anchor_spl::{
     token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
 
-use crate::{Offer, ANCHOR_DISCRIMINATOR};
+use crate::{
+    error::ErrorCode, Config, MintRegistry, NftListed, NftMetadata, Offer, OfferMade, OfferPegged,
+    PaymentOption, PegTerms, PriceFeed, PricePeg, ANCHOR_DISCRIMINATOR, CONFIG_SEED,
+    MAX_PAYMENT_OPTIONS, MINT_REGISTRY_SEED, OFFER_SEED, OFFER_VERSION,
+};
 
+/// Accounts for `make_offer`, in the order CPI callers must pass them:
+///
+/// 0. `[writable, signer]` maker
+/// 1. `[]` token_mint_a
+/// 2. `[]` token_mint_b
+/// 3. `[writable]` maker_token_account_a
+/// 4. `[writable]` offer, see [`Offer::pda`]
+/// 5. `[writable]` vault, token A ATA owned by `offer`
+/// 6. `[]` associated_token_program
+/// 7. `[]` token_program
+/// 8. `[]` system_program
+/// 9. `[]` config, must not be paused
+/// 10. `[]` mint_registry, optional, required while the mint allowlist is enabled
+/// 11. `[]` price_feed, optional, required for pegged offers
+/// 12. `[]` nft_metadata, optional, makes the offer an NFT listing
+/// 13. `[writable, signer]` rent_payer, optional, pays the rent of `offer` and
+///     `vault` instead of the maker, and gets it back when the offer closes
 #[derive(Accounts)]
 #[instruction(id: u64)]
 pub struct MakeOffer<'info> {
@@ -28,16 +49,16 @@ pub struct MakeOffer<'info> {
     pub maker_token_account_a: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init,
-        payer = maker,
+        payer = rent_payer.as_ref().unwrap_or(&maker),
         space = ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE,
-        seeds = [b"offer", maker.key().as_ref(), id.to_le_bytes().as_ref()],
+        seeds = [OFFER_SEED, maker.key().as_ref(), id.to_le_bytes().as_ref()],
         bump
     )]
     pub offer: Account<'info, Offer>,
 
     #[account(
         init,
-        payer = maker,
+        payer = rent_payer.as_ref().unwrap_or(&maker),
         associated_token::mint = token_mint_a,
         associated_token::authority = offer,
         associated_token::token_program = token_program
@@ -47,6 +68,122 @@ pub struct MakeOffer<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(seeds = [MINT_REGISTRY_SEED], bump = mint_registry.bump)]
+    pub mint_registry: Option<Account<'info, MintRegistry>>,
+
+    /// CHECK: Any account in the `PriceFeed` layout. The maker chooses which
+    /// feed to trust; its owner is recorded so it can not change later.
+    pub price_feed: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Metaplex metadata of `token_mint_a`, checked in
+    /// `check_nft_listing`.
+    pub nft_metadata: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub rent_payer: Option<Signer<'info>>,
+}
+
+/// `other_payments` are only known by address. A mint that is not a mint of
+/// `token_program` can never be paid with, which only limits the maker.
+pub fn check_payment_options(
+    context: &Context<MakeOffer>,
+    other_payments: &[PaymentOption],
+) -> Result<()> {
+    require!(
+        other_payments.len() < MAX_PAYMENT_OPTIONS,
+        ErrorCode::TooManyPaymentOptions
+    );
+
+    let mut seen = vec![
+        context.accounts.token_mint_a.key(),
+        context.accounts.token_mint_b.key(),
+    ];
+    for payment in other_payments {
+        require!(
+            !seen.contains(&payment.mint),
+            ErrorCode::DuplicatePaymentMint
+        );
+        seen.push(payment.mint);
+    }
+    Ok(())
+}
+
+pub fn check_mints_allowed(
+    context: &Context<MakeOffer>,
+    other_payments: &[PaymentOption],
+) -> Result<()> {
+    if !context.accounts.config.mint_allowlist_enabled {
+        return Ok(());
+    }
+
+    let registry = context
+        .accounts
+        .mint_registry
+        .as_ref()
+        .ok_or(ErrorCode::MintRegistryRequired)?;
+    require!(
+        registry.contains(&context.accounts.token_mint_a.key())
+            && registry.contains(&context.accounts.token_mint_b.key())
+            && other_payments
+                .iter()
+                .all(|payment| registry.contains(&payment.mint)),
+        ErrorCode::MintNotAllowed
+    );
+    Ok(())
+}
+
+/// Checks the peg terms and that the feed reads as a price today, and returns
+/// what the offer stores.
+pub fn check_peg(
+    context: &Context<MakeOffer>,
+    terms: Option<PegTerms>,
+) -> Result<Option<PricePeg>> {
+    let Some(terms) = terms else {
+        return Ok(None);
+    };
+    terms.validate()?;
+    let price_feed = context
+        .accounts
+        .price_feed
+        .as_ref()
+        .ok_or(ErrorCode::PriceFeedRequired)?;
+    PriceFeed::try_from_account_data(&price_feed.try_borrow_data()?)?;
+
+    Ok(Some(PricePeg {
+        price_feed: price_feed.key(),
+        oracle_program: *price_feed.owner,
+        terms,
+    }))
+}
+
+/// With the metadata account passed, the offer is an NFT listing: token A
+/// must be an NFT in a verified collection, which is returned for the offer
+/// to store.
+pub fn check_nft_listing(
+    context: &Context<MakeOffer>,
+    token_a_offered_amount: u64,
+) -> Result<Option<Pubkey>> {
+    let Some(nft_metadata) = context.accounts.nft_metadata.as_ref() else {
+        return Ok(None);
+    };
+    let token_mint_a = &context.accounts.token_mint_a;
+    require!(
+        token_mint_a.supply == 1 && token_mint_a.decimals == 0 && token_a_offered_amount == 1,
+        ErrorCode::NotAnNft
+    );
+    let metadata = NftMetadata::try_from_account(nft_metadata, &token_mint_a.key())?;
+    let collection = metadata
+        .collection
+        .ok_or(ErrorCode::NftCollectionNotVerified)?;
+    Ok(Some(collection))
 }
 
 pub fn send_offered_tokens_to_vault(
@@ -72,14 +209,56 @@ pub fn send_offered_tokens_to_vault(
     )
 }
 
-pub fn save_offer(context: Context<MakeOffer>, id: u64, token_b_wanted_amount: u64) -> Result<()> {
+pub fn save_offer(
+    context: Context<MakeOffer>,
+    id: u64,
+    token_a_offered_amount: u64,
+    token_b_wanted_amount: u64,
+    other_payments: Vec<PaymentOption>,
+    peg: Option<PricePeg>,
+    nft_collection: Option<Pubkey>,
+) -> Result<()> {
     context.accounts.offer.set_inner(Offer {
+        version: OFFER_VERSION,
         id,
         maker: context.accounts.maker.key(),
         token_mint_a: context.accounts.token_mint_a.key(),
         token_mint_b: context.accounts.token_mint_b.key(),
         token_b_wanted_amount,
         bump: context.bumps.offer,
+        other_payments: other_payments.clone(),
+        peg: peg.clone(),
+        nft_collection,
+        rent_payer: context
+            .accounts
+            .rent_payer
+            .as_ref()
+            .unwrap_or(&context.accounts.maker)
+            .key(),
+    });
+
+    emit!(OfferMade {
+        offer: context.accounts.offer.key(),
+        maker: context.accounts.maker.key(),
+        id,
+        token_mint_a: context.accounts.token_mint_a.key(),
+        token_mint_b: context.accounts.token_mint_b.key(),
+        token_a_offered_amount,
+        token_b_wanted_amount,
+        other_payments,
     });
+    if let Some(peg) = peg {
+        emit!(OfferPegged {
+            offer: context.accounts.offer.key(),
+            price_feed: peg.price_feed,
+            terms: peg.terms,
+        });
+    }
+    if let Some(collection) = nft_collection {
+        emit!(NftListed {
+            offer: context.accounts.offer.key(),
+            collection,
+        });
+    }
     Ok(())
 }
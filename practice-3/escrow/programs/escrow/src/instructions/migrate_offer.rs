@@ -0,0 +1,89 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+
+use crate::{error::ErrorCode, Offer, ANCHOR_DISCRIMINATOR, OFFER_SEED};
+
+/// Rewrites an offer stored in an older layout into the current one, so that
+/// `take_offer` and `cancel_offer` can load it. `payer` must be the maker: it
+/// covers any rent increase, and gets back both the rent freed by a smaller
+/// layout and, when the offer closes, all of its rent. Offers do not record
+/// who else paid part of their rent, so nobody else could be refunded.
+#[derive(Accounts)]
+pub struct MigrateOffer<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: `Account<Offer>` can not decode old layouts. The owner is checked
+    /// here, the discriminator, maker and address in `resize_and_rewrite`.
+    #[account(mut, owner = crate::ID)]
+    pub offer: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn resize_and_rewrite(context: Context<MigrateOffer>) -> Result<()> {
+    let offer_info = context.accounts.offer.to_account_info();
+    let new_len = ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE;
+
+    let (offer, old_len) = {
+        let data = offer_info.try_borrow_data()?;
+        require!(
+            !Offer::is_current_layout(&data),
+            ErrorCode::OfferAlreadyMigrated
+        );
+        (Offer::try_from_versioned(&data)?, data.len())
+    };
+
+    require_keys_eq!(offer.maker, context.accounts.maker.key());
+    require_keys_eq!(
+        context.accounts.payer.key(),
+        offer.maker,
+        ErrorCode::MigrationPayerNotMaker
+    );
+    let expected_address = Pubkey::create_program_address(
+        &[
+            OFFER_SEED,
+            offer.maker.as_ref(),
+            &offer.id.to_le_bytes(),
+            &[offer.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(|_| ErrorCode::UnknownOfferLayout)?;
+    require_keys_eq!(expected_address, offer_info.key());
+
+    let required_lamports = Rent::get()?.minimum_balance(new_len);
+    let current_lamports = offer_info.lamports();
+    if required_lamports > current_lamports {
+        let cpi_context = CpiContext::new(
+            context.accounts.system_program.to_account_info(),
+            Transfer {
+                from: context.accounts.payer.to_account_info(),
+                to: offer_info.clone(),
+            },
+        );
+        transfer(cpi_context, required_lamports - current_lamports)?;
+    } else if current_lamports > required_lamports {
+        let refund = current_lamports - required_lamports;
+        offer_info.sub_lamports(refund)?;
+        context.accounts.maker.add_lamports(refund)?;
+    }
+
+    offer_info.resize(new_len)?;
+
+    let mut data = offer_info.try_borrow_mut_data()?;
+    offer.try_serialize(&mut &mut data[..])?;
+
+    msg!(
+        "Migrated offer {} from {} to {} bytes",
+        offer_info.key(),
+        old_len,
+        new_len
+    );
+    Ok(())
+}
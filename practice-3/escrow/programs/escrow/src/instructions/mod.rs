@@ -1,5 +1,47 @@
+pub mod accept_counter_offer;
+pub use accept_counter_offer::*;
+
+pub mod add_allowed_mint;
+pub use add_allowed_mint::*;
+
+pub mod cancel_offer;
+pub use cancel_offer::*;
+
+pub mod close_fill_receipt;
+pub use close_fill_receipt::*;
+
+pub mod initialize_config;
+pub use initialize_config::*;
+
+pub mod initialize_mint_registry;
+pub use initialize_mint_registry::*;
+
+pub mod make_counter_offer;
+pub use make_counter_offer::*;
+
 pub mod make_offer;
 pub use make_offer::*;
 
+pub mod migrate_offer;
+pub use migrate_offer::*;
+
+pub mod reject_counter_offer;
+pub use reject_counter_offer::*;
+
+pub mod remove_allowed_mint;
+pub use remove_allowed_mint::*;
+
+pub mod set_mint_allowlist_enabled;
+pub use set_mint_allowlist_enabled::*;
+
+pub mod set_max_referral_fee;
+pub use set_max_referral_fee::*;
+
+pub mod set_paused;
+pub use set_paused::*;
+
 pub mod take_offer;
 pub use take_offer::*;
+
+pub mod withdraw_counter_offer;
+pub use withdraw_counter_offer::*;
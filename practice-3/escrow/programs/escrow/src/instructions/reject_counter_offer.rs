@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+use crate::{refund_and_close_counter_vault, CounterOffer, Offer};
+
+/// Lets the maker turn down a counter offer, refunding the counter party.
+#[derive(Accounts)]
+pub struct RejectCounterOffer<'info> {
+    pub maker: Signer<'info>,
+
+    #[account(mut)]
+    pub counter_party: SystemAccount<'info>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = counter_party,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_party_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(has_one = maker)]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        close = counter_party,
+        has_one = offer,
+        has_one = counter_party,
+        has_one = token_mint_b,
+    )]
+    pub counter_offer: Account<'info, CounterOffer>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = counter_offer,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+pub fn refund_rejected_counter_offer(context: Context<RejectCounterOffer>) -> Result<()> {
+    let accounts = context.accounts;
+    refund_and_close_counter_vault(
+        &accounts.counter_offer,
+        &accounts.counter_vault,
+        &accounts.token_mint_b,
+        &accounts.counter_party_token_account_b,
+        &accounts.counter_party,
+        &accounts.token_program,
+        true,
+    )
+}
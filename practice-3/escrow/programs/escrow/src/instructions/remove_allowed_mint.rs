@@ -0,0 +1,35 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, Config, MintRegistry, CONFIG_SEED, MINT_REGISTRY_SEED};
+
+#[derive(Accounts)]
+pub struct RemoveAllowedMint<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [MINT_REGISTRY_SEED],
+        bump = mint_registry.bump,
+    )]
+    pub mint_registry: Account<'info, MintRegistry>,
+}
+
+/// Takes the mint as a `Pubkey`, so a mint that was closed after being
+/// allowed can still be removed.
+pub fn remove_mint(context: Context<RemoveAllowedMint>, mint: Pubkey) -> Result<()> {
+    let mints = &mut context.accounts.mint_registry.mints;
+    let index = mints
+        .iter()
+        .position(|allowed| *allowed == mint)
+        .ok_or(ErrorCode::MintNotAllowed)?;
+
+    mints.swap_remove(index);
+    Ok(())
+}
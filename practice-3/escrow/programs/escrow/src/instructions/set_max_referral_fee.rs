@@ -0,0 +1,28 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, Config, BASIS_POINTS, CONFIG_SEED};
+
+#[derive(Accounts)]
+pub struct SetMaxReferralFee<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+pub fn update_max_referral_fee(
+    context: Context<SetMaxReferralFee>,
+    max_referral_fee_bps: u16,
+) -> Result<()> {
+    require!(
+        max_referral_fee_bps <= BASIS_POINTS,
+        ErrorCode::InvalidBasisPoints
+    );
+    context.accounts.config.max_referral_fee_bps = max_referral_fee_bps;
+    Ok(())
+}
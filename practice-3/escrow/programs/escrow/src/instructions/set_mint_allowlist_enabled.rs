@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, Config, MintRegistry, CONFIG_SEED, MINT_REGISTRY_SEED};
+
+/// Requires the registry to exist, so the allowlist can not be enabled before
+/// there is anything to check mints against.
+#[derive(Accounts)]
+pub struct SetMintAllowlistEnabled<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(seeds = [MINT_REGISTRY_SEED], bump = mint_registry.bump)]
+    pub mint_registry: Account<'info, MintRegistry>,
+}
+
+pub fn update_mint_allowlist_flag(
+    context: Context<SetMintAllowlistEnabled>,
+    enabled: bool,
+) -> Result<()> {
+    context.accounts.config.mint_allowlist_enabled = enabled;
+    Ok(())
+}
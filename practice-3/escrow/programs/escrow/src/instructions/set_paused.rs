@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, Config, Paused, Unpaused, CONFIG_SEED};
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ ErrorCode::Unauthorized,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+pub fn update_pause_flag(context: Context<SetPaused>, paused: bool) -> Result<()> {
+    let config = &mut context.accounts.config;
+    require!(config.paused != paused, ErrorCode::PauseUnchanged);
+    config.paused = paused;
+
+    let admin = context.accounts.admin.key();
+    let timestamp = Clock::get()?.unix_timestamp;
+    if paused {
+        emit!(Paused { admin, timestamp });
+    } else {
+        emit!(Unpaused { admin, timestamp });
+    }
+    Ok(())
+}
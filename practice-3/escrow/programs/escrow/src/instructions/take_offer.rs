@@ -1,15 +1,49 @@
 use anchor_lang::prelude::*;
 
 use anchor_spl::{
-    associated_token::AssociatedToken,
+    associated_token::{get_associated_token_address_with_program_id, AssociatedToken},
     token_interface::{
         close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
         TransferChecked,
     },
 };
 
-use crate::Offer;
+use crate::{
+    error::ErrorCode, Config, FillReceipt, NftMetadata, Offer, OfferTaken, PriceFeed, RoyaltyPaid,
+    ANCHOR_DISCRIMINATOR, BASIS_POINTS, CONFIG_SEED, FILL_RECEIPT_SEED, OFFER_SEED,
+};
 
+/// Accounts for `take_offer`, in the order CPI callers must pass them:
+///
+/// 0. `[writable, signer]` taker
+/// 1. `[writable]` maker
+/// 2. `[]` token_mint_a
+/// 3. `[]` token_mint_b, any of the mints the offer accepts as payment
+/// 4. `[writable]` taker_token_account_a, created if missing
+/// 5. `[writable]` taker_token_account_b
+/// 6. `[writable]` maker_token_account_b, created if missing
+/// 7. `[writable]` offer
+/// 8. `[writable]` vault
+/// 9. `[]` associated_token_program
+/// 10. `[]` token_program
+/// 11. `[]` system_program
+/// 12. `[]` config, must not be paused
+/// 13. `[writable]` fill_receipt, optional, see [`FillReceipt::pda`]
+/// 14. `[]` referrer, optional, required when `referral_fee_bps` is not zero
+/// 15. `[writable]` referrer_token_account_b, optional, created if missing
+/// 16. `[]` price_feed, optional, required to pay a pegged offer in `token_mint_b`
+/// 17. `[]` nft_metadata, optional, required for NFT listings
+/// 18. `[writable, signer]` rent_payer, optional, pays for the token accounts
+///     created here instead of the taker
+/// 19. `[writable]` offer_rent_payer, optional, the `rent_payer` of a
+///     sponsored offer, which gets back the rent of `offer` and `vault`
+///
+/// The fill receipt is always paid for by the taker, who gets its rent back
+/// from `close_fill_receipt`. Token accounts are never closed here, so their
+/// rent stays with them. Trailing optional accounts may be left out entirely instead of being
+/// passed as the program id. NFT listings then take one remaining account
+/// per creator in the metadata, in order: the creator's `token_mint_b` ATA,
+/// `[writable]`, which must already exist.
 #[derive(Accounts)]
 pub struct TakeOffer<'info> {
     #[account(mut)]
@@ -23,7 +57,7 @@ pub struct TakeOffer<'info> {
     pub token_mint_b: InterfaceAccount<'info, Mint>,
     #[account(
         init_if_needed,
-        payer = taker,
+        payer = rent_payer.as_ref().unwrap_or(&taker),
         associated_token::mint = token_mint_a,
         associated_token::authority = taker,
         associated_token::token_program = token_program,
@@ -39,7 +73,7 @@ pub struct TakeOffer<'info> {
     pub taker_token_account_b: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init_if_needed,
-        payer = taker,
+        payer = rent_payer.as_ref().unwrap_or(&taker),
         associated_token::mint = token_mint_b,
         associated_token::authority = maker,
         associated_token::token_program = token_program,
@@ -48,14 +82,14 @@ pub struct TakeOffer<'info> {
 
     #[account(
         mut,
-        close = maker,
         has_one = maker,
         has_one = token_mint_a,
-        has_one = token_mint_b,
-        // seeds = [b"offer", maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
+        constraint = offer.wanted_amount(&token_mint_b.key()).is_some()
+            @ ErrorCode::PaymentMintNotAccepted,
+        // seeds = [OFFER_SEED, maker.key().as_ref(), offer.id.to_le_bytes().as_ref()],
         // bump = offer.bump
     )]
-    offer: Account<'info, Offer>,
+    pub offer: Account<'info, Offer>,
 
     #[account(
         mut,
@@ -68,9 +102,201 @@ pub struct TakeOffer<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump = config.bump,
+        constraint = !config.paused @ ErrorCode::Paused,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = taker,
+        space = ANCHOR_DISCRIMINATOR + FillReceipt::INIT_SPACE,
+        seeds = [FILL_RECEIPT_SEED, offer.key().as_ref(), taker.key().as_ref()],
+        bump
+    )]
+    pub fill_receipt: Option<Account<'info, FillReceipt>>,
+
+    /// CHECK: Any address can earn referral fees. It is only used as the
+    /// owner of `referrer_token_account_b`.
+    pub referrer: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        init_if_needed,
+        payer = rent_payer.as_ref().unwrap_or(&taker),
+        associated_token::mint = token_mint_b,
+        associated_token::authority = referrer,
+        associated_token::token_program = token_program,
+    )]
+    pub referrer_token_account_b: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// CHECK: Must be the feed `offer.peg` names, checked in `token_b_amount`.
+    pub price_feed: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Metaplex metadata of `token_mint_a`, checked in `pay_royalties`.
+    pub nft_metadata: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub rent_payer: Option<Signer<'info>>,
+
+    /// CHECK: Must be `offer.rent_payer`, checked in
+    /// `Offer::rent_refund_destination`.
+    #[account(mut)]
+    pub offer_rent_payer: Option<UncheckedAccount<'info>>,
+}
+
+impl TakeOffer<'_> {
+    /// What the maker wants in the mint the taker pays with. A pegged offer
+    /// wants the feed price of the vault, but never less than its floor.
+    fn token_b_amount(&self) -> Result<u64> {
+        let token_mint_b = self.token_mint_b.key();
+        let floor = self
+            .offer
+            .wanted_amount(&token_mint_b)
+            .ok_or_else(|| error!(ErrorCode::PaymentMintNotAccepted))?;
+        let Some(peg) = self
+            .offer
+            .peg
+            .as_ref()
+            .filter(|_| token_mint_b == self.offer.token_mint_b)
+        else {
+            return Ok(floor);
+        };
+
+        let price_feed = self
+            .price_feed
+            .as_ref()
+            .ok_or(ErrorCode::PriceFeedRequired)?;
+        require_keys_eq!(
+            price_feed.key(),
+            peg.price_feed,
+            ErrorCode::PriceFeedMismatch
+        );
+        require_keys_eq!(
+            *price_feed.owner,
+            peg.oracle_program,
+            ErrorCode::PriceFeedMismatch
+        );
+        let feed = PriceFeed::try_from_account_data(&price_feed.try_borrow_data()?)?;
+        let pegged = peg.wanted_amount(
+            &feed,
+            Clock::get()?.unix_timestamp,
+            self.vault.amount,
+            self.token_mint_a.decimals,
+            self.token_mint_b.decimals,
+        )?;
+        Ok(pegged.max(floor))
+    }
+}
+
+/// Pays the referrer `referral_fee_bps` of the wanted amount, rounded down, on
+/// top of what the maker gets. Returns the amount paid.
+pub fn pay_referral_fee(ctx: &Context<TakeOffer>, referral_fee_bps: u16) -> Result<u64> {
+    if referral_fee_bps == 0 {
+        return Ok(0);
+    }
+    require!(
+        referral_fee_bps <= ctx.accounts.config.max_referral_fee_bps,
+        ErrorCode::ReferralFeeTooHigh
+    );
+    let (Some(_), Some(referrer_token_account_b)) = (
+        ctx.accounts.referrer.as_ref(),
+        ctx.accounts.referrer_token_account_b.as_ref(),
+    ) else {
+        return err!(ErrorCode::ReferrerRequired);
+    };
+
+    // At most `BASIS_POINTS`, so the fee never exceeds the wanted amount.
+    let referral_fee_amount = (ctx.accounts.token_b_amount()? as u128 * referral_fee_bps as u128
+        / BASIS_POINTS as u128) as u64;
+    if referral_fee_amount == 0 {
+        return Ok(0);
+    }
+
+    let transfer_accounts = TransferChecked {
+        from: ctx.accounts.taker_token_account_b.to_account_info(),
+        mint: ctx.accounts.token_mint_b.to_account_info(),
+        to: referrer_token_account_b.to_account_info(),
+        authority: ctx.accounts.taker.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        transfer_accounts,
+    );
+    transfer_checked(
+        cpi_context,
+        referral_fee_amount,
+        ctx.accounts.token_mint_b.decimals,
+    )?;
+    Ok(referral_fee_amount)
+}
+
+/// Pays the creators of a listed NFT their royalties out of the wanted
+/// amount, from the remaining accounts. Returns the amount paid, which the
+/// maker does not receive.
+pub fn pay_royalties<'info>(ctx: &Context<'_, '_, '_, 'info, TakeOffer<'info>>) -> Result<u64> {
+    if ctx.accounts.offer.nft_collection.is_none() {
+        return Ok(0);
+    }
+    let nft_metadata = ctx
+        .accounts
+        .nft_metadata
+        .as_ref()
+        .ok_or(ErrorCode::NftMetadataRequired)?;
+    let metadata = NftMetadata::try_from_account(nft_metadata, &ctx.accounts.token_mint_a.key())?;
+    let royalties = metadata.royalties(ctx.accounts.token_b_amount()?);
+    require_eq!(
+        ctx.remaining_accounts.len(),
+        royalties.len(),
+        ErrorCode::RoyaltyAccountMismatch
+    );
+
+    let mut royalty_amount: u64 = 0;
+    for ((creator, amount), creator_token_account_b) in
+        royalties.into_iter().zip(ctx.remaining_accounts)
+    {
+        require_keys_eq!(
+            creator_token_account_b.key(),
+            get_associated_token_address_with_program_id(
+                &creator,
+                &ctx.accounts.token_mint_b.key(),
+                &ctx.accounts.token_program.key(),
+            ),
+            ErrorCode::RoyaltyAccountMismatch
+        );
+        if amount == 0 {
+            continue;
+        }
+
+        let transfer_accounts = TransferChecked {
+            from: ctx.accounts.taker_token_account_b.to_account_info(),
+            mint: ctx.accounts.token_mint_b.to_account_info(),
+            to: creator_token_account_b.clone(),
+            authority: ctx.accounts.taker.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_accounts,
+        );
+        transfer_checked(cpi_context, amount, ctx.accounts.token_mint_b.decimals)?;
+
+        emit!(RoyaltyPaid {
+            offer: ctx.accounts.offer.key(),
+            creator,
+            token_mint_b: ctx.accounts.token_mint_b.key(),
+            amount,
+        });
+        royalty_amount += amount;
+    }
+    Ok(royalty_amount)
 }
 
-pub fn send_wanted_tokens_to_maker(ctx: &Context<TakeOffer>) -> Result<()> {
+/// Sends the wanted amount, less the royalties already paid out of it.
+pub fn send_wanted_tokens_to_maker(ctx: &Context<TakeOffer>, royalty_amount: u64) -> Result<()> {
     let transfer_accounts = TransferChecked {
         from: ctx.accounts.taker_token_account_b.to_account_info(),
         mint: ctx.accounts.token_mint_b.to_account_info(),
@@ -84,14 +310,54 @@ pub fn send_wanted_tokens_to_maker(ctx: &Context<TakeOffer>) -> Result<()> {
     );
     transfer_checked(
         cpi_context,
-        ctx.accounts.offer.token_b_wanted_amount,
+        ctx.accounts.token_b_amount()? - royalty_amount,
         ctx.accounts.token_mint_b.decimals,
     )
 }
 
-pub fn withdraw_and_close_vault(ctx: Context<TakeOffer>) -> Result<()> {
+pub fn save_fill_receipt(ctx: &mut Context<TakeOffer>) -> Result<()> {
+    // Only set when the receipt account was passed, and so initialized.
+    let Some(bump) = ctx.bumps.fill_receipt else {
+        return Ok(());
+    };
+
+    let token_a_amount = ctx.accounts.vault.amount;
+    let token_b_amount = ctx.accounts.token_b_amount()?;
+    let token_a_decimals = ctx.accounts.token_mint_a.decimals;
+    let clock = Clock::get()?;
+    let receipt = FillReceipt {
+        offer: ctx.accounts.offer.key(),
+        maker: ctx.accounts.maker.key(),
+        taker: ctx.accounts.taker.key(),
+        token_mint_a: ctx.accounts.token_mint_a.key(),
+        token_mint_b: ctx.accounts.token_mint_b.key(),
+        token_a_amount,
+        token_b_amount,
+        token_a_decimals,
+        token_b_decimals: ctx.accounts.token_mint_b.decimals,
+        price: FillReceipt::price(token_a_amount, token_b_amount, token_a_decimals),
+        slot: clock.slot,
+        unix_timestamp: clock.unix_timestamp,
+        bump,
+    };
+    if let Some(fill_receipt) = ctx.accounts.fill_receipt.as_mut() {
+        fill_receipt.set_inner(receipt);
+    }
+    Ok(())
+}
+
+/// Sends the vault to the taker, then closes the vault and the offer and
+/// returns their rent to whoever paid it.
+pub fn withdraw_and_close_vault(ctx: Context<TakeOffer>, referral_fee_amount: u64) -> Result<()> {
+    let rent_refund_destination = ctx.accounts.offer.rent_refund_destination(
+        &ctx.accounts.maker.to_account_info(),
+        ctx.accounts
+            .offer_rent_payer
+            .as_ref()
+            .map(|offer_rent_payer| offer_rent_payer.as_ref()),
+    )?;
     let signer_seeds: [&[&[u8]]; 1] = [&[
-        b"offer",
+        OFFER_SEED,
         ctx.accounts.maker.to_account_info().key.as_ref(),
         &ctx.accounts.offer.id.to_le_bytes()[..],
         &[ctx.accounts.offer.bump],
@@ -116,7 +382,7 @@ pub fn withdraw_and_close_vault(ctx: Context<TakeOffer>) -> Result<()> {
     )?;
     let accounts = CloseAccount {
         account: ctx.accounts.vault.to_account_info(),
-        destination: ctx.accounts.taker.to_account_info(),
+        destination: rent_refund_destination.clone(),
         authority: ctx.accounts.offer.to_account_info(),
     };
 
@@ -126,5 +392,23 @@ pub fn withdraw_and_close_vault(ctx: Context<TakeOffer>) -> Result<()> {
         &signer_seeds,
     );
 
-    close_account(cpi_context)
+    close_account(cpi_context)?;
+    ctx.accounts.offer.close(rent_refund_destination)?;
+
+    emit!(OfferTaken {
+        offer: ctx.accounts.offer.key(),
+        maker: ctx.accounts.maker.key(),
+        taker: ctx.accounts.taker.key(),
+        token_mint_a: ctx.accounts.token_mint_a.key(),
+        token_mint_b: ctx.accounts.token_mint_b.key(),
+        token_a_amount: ctx.accounts.vault.amount,
+        token_b_amount: ctx.accounts.token_b_amount()?,
+        referrer: ctx
+            .accounts
+            .referrer
+            .as_ref()
+            .map(|referrer| referrer.key()),
+        referral_fee_amount,
+    });
+    Ok(())
 }
@@ -0,0 +1,112 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token_interface::{
+    close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+    TransferChecked,
+};
+
+use crate::{CounterOffer, CounterOfferClosed, COUNTER_OFFER_SEED};
+
+/// Returns the counter vault to the counter party and closes the counter
+/// offer. Works whether or not the offer is still open, and while the escrow
+/// is paused.
+#[derive(Accounts)]
+pub struct WithdrawCounterOffer<'info> {
+    #[account(mut)]
+    pub counter_party: Signer<'info>,
+
+    pub token_mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = counter_party,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_party_token_account_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        close = counter_party,
+        has_one = counter_party,
+        has_one = token_mint_b,
+        seeds = [COUNTER_OFFER_SEED, counter_offer.offer.as_ref(), counter_party.key().as_ref()],
+        bump = counter_offer.bump
+    )]
+    pub counter_offer: Account<'info, CounterOffer>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint_b,
+        associated_token::authority = counter_offer,
+        associated_token::token_program = token_program,
+    )]
+    pub counter_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+pub fn refund_withdrawn_counter_offer(context: Context<WithdrawCounterOffer>) -> Result<()> {
+    let accounts = context.accounts;
+    refund_and_close_counter_vault(
+        &accounts.counter_offer,
+        &accounts.counter_vault,
+        &accounts.token_mint_b,
+        &accounts.counter_party_token_account_b,
+        &accounts.counter_party,
+        &accounts.token_program,
+        false,
+    )
+}
+
+/// Sends everything in `counter_vault` back to the counter party, closes it
+/// to them and emits `CounterOfferClosed`. Shared by withdrawing and
+/// rejecting.
+pub fn refund_and_close_counter_vault<'info>(
+    counter_offer: &Account<'info, CounterOffer>,
+    counter_vault: &InterfaceAccount<'info, TokenAccount>,
+    token_mint_b: &InterfaceAccount<'info, Mint>,
+    counter_party_token_account_b: &InterfaceAccount<'info, TokenAccount>,
+    counter_party: &AccountInfo<'info>,
+    token_program: &Interface<'info, TokenInterface>,
+    rejected: bool,
+) -> Result<()> {
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        COUNTER_OFFER_SEED,
+        counter_offer.offer.as_ref(),
+        counter_offer.counter_party.as_ref(),
+        &[counter_offer.bump],
+    ]];
+
+    let accounts = TransferChecked {
+        from: counter_vault.to_account_info(),
+        mint: token_mint_b.to_account_info(),
+        to: counter_party_token_account_b.to_account_info(),
+        authority: counter_offer.to_account_info(),
+    };
+
+    let cpi_context =
+        CpiContext::new_with_signer(token_program.to_account_info(), accounts, &signer_seeds);
+    transfer_checked(cpi_context, counter_vault.amount, token_mint_b.decimals)?;
+
+    let accounts = CloseAccount {
+        account: counter_vault.to_account_info(),
+        destination: counter_party.clone(),
+        authority: counter_offer.to_account_info(),
+    };
+
+    let cpi_context =
+        CpiContext::new_with_signer(token_program.to_account_info(), accounts, &signer_seeds);
+
+    close_account(cpi_context)?;
+
+    emit!(CounterOfferClosed {
+        counter_offer: counter_offer.key(),
+        offer: counter_offer.offer,
+        counter_party: counter_offer.counter_party,
+        token_mint_b: token_mint_b.key(),
+        token_b_amount: counter_vault.amount,
+        rejected,
+    });
+    Ok(())
+}
@@ -1,11 +1,16 @@
+// `#[program]` in anchor-lang 0.31.1 still expands to `AccountInfo::realloc`.
+#![allow(deprecated)]
+
 pub mod constants;
 pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod state;
 
 use anchor_lang::prelude::*;
 
 pub use constants::*;
+pub use events::*;
 pub use instructions::*;
 pub use state::*;
 
@@ -20,13 +25,103 @@ pub mod escrow {
         id: u64,
         token_a_offered_amount: u64,
         token_b_wanted_amount: u64,
+        other_payments: Vec<PaymentOption>,
+        peg: Option<PegTerms>,
     ) -> Result<()> {
+        instructions::make_offer::check_payment_options(&context, &other_payments)?;
+        instructions::make_offer::check_mints_allowed(&context, &other_payments)?;
+        let peg = instructions::make_offer::check_peg(&context, peg)?;
+        let nft_collection =
+            instructions::make_offer::check_nft_listing(&context, token_a_offered_amount)?;
         instructions::make_offer::send_offered_tokens_to_vault(&context, token_a_offered_amount)?;
-        instructions::make_offer::save_offer(context, id, token_b_wanted_amount)
+        instructions::make_offer::save_offer(
+            context,
+            id,
+            token_a_offered_amount,
+            token_b_wanted_amount,
+            other_payments,
+            peg,
+            nft_collection,
+        )
+    }
+
+    pub fn take_offer<'info>(
+        mut context: Context<'_, '_, '_, 'info, TakeOffer<'info>>,
+        referral_fee_bps: u16,
+    ) -> Result<()> {
+        let referral_fee_amount =
+            instructions::take_offer::pay_referral_fee(&context, referral_fee_bps)?;
+        let royalty_amount = instructions::take_offer::pay_royalties(&context)?;
+        instructions::take_offer::send_wanted_tokens_to_maker(&context, royalty_amount)?;
+        instructions::take_offer::save_fill_receipt(&mut context)?;
+        instructions::take_offer::withdraw_and_close_vault(context, referral_fee_amount)
+    }
+
+    pub fn cancel_offer(context: Context<CancelOffer>) -> Result<()> {
+        instructions::cancel_offer::refund_and_close_vault(context)
+    }
+
+    pub fn make_counter_offer(
+        context: Context<MakeCounterOffer>,
+        token_b_amount: u64,
+    ) -> Result<()> {
+        instructions::make_counter_offer::send_counter_tokens_to_vault(&context, token_b_amount)?;
+        instructions::make_counter_offer::save_counter_offer(context, token_b_amount)
+    }
+
+    pub fn accept_counter_offer(context: Context<AcceptCounterOffer>) -> Result<()> {
+        instructions::accept_counter_offer::send_counter_tokens_to_maker(&context)?;
+        instructions::accept_counter_offer::withdraw_and_close_offer_vault(context)
+    }
+
+    pub fn reject_counter_offer(context: Context<RejectCounterOffer>) -> Result<()> {
+        instructions::reject_counter_offer::refund_rejected_counter_offer(context)
+    }
+
+    pub fn withdraw_counter_offer(context: Context<WithdrawCounterOffer>) -> Result<()> {
+        instructions::withdraw_counter_offer::refund_withdrawn_counter_offer(context)
+    }
+
+    pub fn close_fill_receipt(_context: Context<CloseFillReceipt>) -> Result<()> {
+        // The `close` constraint returns the rent once the checks pass.
+        Ok(())
+    }
+
+    pub fn migrate_offer(context: Context<MigrateOffer>) -> Result<()> {
+        instructions::migrate_offer::resize_and_rewrite(context)
+    }
+
+    pub fn initialize_config(context: Context<InitializeConfig>) -> Result<()> {
+        instructions::initialize_config::save_config(context)
+    }
+
+    pub fn initialize_mint_registry(context: Context<InitializeMintRegistry>) -> Result<()> {
+        instructions::initialize_mint_registry::save_mint_registry(context)
+    }
+
+    pub fn set_mint_allowlist_enabled(
+        context: Context<SetMintAllowlistEnabled>,
+        enabled: bool,
+    ) -> Result<()> {
+        instructions::set_mint_allowlist_enabled::update_mint_allowlist_flag(context, enabled)
+    }
+
+    pub fn add_allowed_mint(context: Context<AddAllowedMint>) -> Result<()> {
+        instructions::add_allowed_mint::push_mint(context)
+    }
+
+    pub fn remove_allowed_mint(context: Context<RemoveAllowedMint>, mint: Pubkey) -> Result<()> {
+        instructions::remove_allowed_mint::remove_mint(context, mint)
+    }
+
+    pub fn set_max_referral_fee(
+        context: Context<SetMaxReferralFee>,
+        max_referral_fee_bps: u16,
+    ) -> Result<()> {
+        instructions::set_max_referral_fee::update_max_referral_fee(context, max_referral_fee_bps)
     }
 
-    pub fn take_offer(context: Context<TakeOffer>) -> Result<()> {
-        instructions::take_offer::send_wanted_tokens_to_maker(&context)?;
-        instructions::take_offer::withdraw_and_close_vault(context)
+    pub fn set_paused(context: Context<SetPaused>, paused: bool) -> Result<()> {
+        instructions::set_paused::update_pause_flag(context, paused)
     }
 }
@@ -0,0 +1,17 @@
+use anchor_lang::prelude::*;
+
+/// Deployment-wide settings, stored at `[CONFIG_SEED]`.
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,
+    /// Blocks `make_offer` and `take_offer`. `cancel_offer` keeps working so
+    /// makers can always get their tokens back.
+    pub paused: bool,
+    /// When set, `make_offer` only accepts mints listed in the `MintRegistry`.
+    pub mint_allowlist_enabled: bool,
+    /// Highest referral fee `take_offer` accepts, in basis points of the
+    /// wanted token B amount. Zero turns referrals off.
+    pub max_referral_fee_bps: u16,
+    pub bump: u8,
+}
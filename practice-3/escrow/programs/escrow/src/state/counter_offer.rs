@@ -0,0 +1,34 @@
+use anchor_lang::prelude::*;
+
+use crate::COUNTER_OFFER_SEED;
+
+/// A prospective taker's proposal to fill an `Offer` for a different amount
+/// of one of its payment mints. The proposed token B sits in a vault, the
+/// `counter_offer`'s ATA, until the maker accepts or rejects it or the
+/// counter party withdraws it.
+///
+/// Stored at `[COUNTER_OFFER_SEED, offer, counter_party]`, so each counter
+/// party has at most one open counter per offer.
+#[account]
+#[derive(InitSpace)]
+pub struct CounterOffer {
+    pub offer: Pubkey,
+    pub counter_party: Pubkey,
+    pub token_mint_a: Pubkey,
+    /// Token A in the offer's vault when the counter was made. Accepting
+    /// fails if it changed, which can only happen when the maker reused the
+    /// offer address for a new offer.
+    pub token_a_amount: u64,
+    pub token_mint_b: Pubkey,
+    pub token_b_amount: u64,
+    pub bump: u8,
+}
+
+impl CounterOffer {
+    pub fn pda(offer: &Pubkey, counter_party: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[COUNTER_OFFER_SEED, offer.as_ref(), counter_party.as_ref()],
+            &crate::ID,
+        )
+    }
+}
@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+use crate::FILL_RECEIPT_SEED;
+
+/// Record of a taken offer, kept after `take_offer` closes the `Offer`.
+/// Written only when the taker passes the account, paid for by the taker and
+/// closed by them with `close_fill_receipt`.
+///
+/// Stored at `[FILL_RECEIPT_SEED, offer, taker]`. Offer addresses are reused
+/// when a maker reuses an id, so an old receipt has to be closed before the
+/// same taker can record another fill of that address.
+#[account]
+#[derive(InitSpace)]
+pub struct FillReceipt {
+    pub offer: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_a_amount: u64,
+    pub token_b_amount: u64,
+    pub token_a_decimals: u8,
+    pub token_b_decimals: u8,
+    /// Token B base units paid per whole token A, rounded down.
+    pub price: u64,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+    pub bump: u8,
+}
+
+impl FillReceipt {
+    pub fn pda(offer: &Pubkey, taker: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[FILL_RECEIPT_SEED, offer.as_ref(), taker.as_ref()],
+            &crate::ID,
+        )
+    }
+
+    /// `token_b_amount` scaled to one whole token A. Zero when no token A
+    /// changed hands, and saturates at `u64::MAX` when it does not fit.
+    pub fn price(token_a_amount: u64, token_b_amount: u64, token_a_decimals: u8) -> u64 {
+        if token_a_amount == 0 {
+            return 0;
+        }
+        10u128
+            .checked_pow(token_a_decimals.into())
+            .and_then(|scale| (token_b_amount as u128).checked_mul(scale))
+            .and_then(|scaled| u64::try_from(scaled / token_a_amount as u128).ok())
+            .unwrap_or(u64::MAX)
+    }
+}
@@ -0,0 +1,19 @@
+use anchor_lang::prelude::*;
+
+use crate::MAX_ALLOWED_MINTS;
+
+/// Mints accepted by `make_offer` while `Config::mint_allowlist_enabled` is
+/// set. Stored at `[MINT_REGISTRY_SEED]`.
+#[account]
+#[derive(InitSpace)]
+pub struct MintRegistry {
+    #[max_len(MAX_ALLOWED_MINTS)]
+    pub mints: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl MintRegistry {
+    pub fn contains(&self, mint: &Pubkey) -> bool {
+        self.mints.contains(mint)
+    }
+}
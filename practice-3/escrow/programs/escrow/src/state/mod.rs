@@ -1,2 +1,20 @@
+pub mod config;
+pub use config::*;
+
+pub mod counter_offer;
+pub use counter_offer::*;
+
+pub mod fill_receipt;
+pub use fill_receipt::*;
+
+pub mod mint_registry;
+pub use mint_registry::*;
+
+pub mod nft_metadata;
+pub use nft_metadata::*;
+
 pub mod offer;
 pub use offer::*;
+
+pub mod price_feed;
+pub use price_feed::*;
@@ -0,0 +1,80 @@
+use anchor_lang::prelude::*;
+use anchor_spl::metadata::{mpl_token_metadata::accounts::Metadata, MetadataAccount};
+
+use crate::{error::ErrorCode, BASIS_POINTS, CREATOR_SHARES};
+
+/// The parts of an NFT's Metaplex metadata that listings use.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NftMetadata {
+    pub collection: Option<Pubkey>,
+    pub seller_fee_basis_points: u16,
+    /// Creator addresses and their percentage of the royalties.
+    pub creators: Vec<(Pubkey, u8)>,
+}
+
+impl NftMetadata {
+    /// Address of the metadata of `mint`.
+    pub fn address(mint: &Pubkey) -> Pubkey {
+        Metadata::find_pda(mint).0
+    }
+
+    /// Reads the metadata of `mint` from `account`, which must be owned by
+    /// the Token Metadata program. That program only writes a mint's metadata
+    /// at the address derived from it, so the recorded mint is enough to
+    /// tell the account belongs to `mint`.
+    pub fn try_from_account(account: &AccountInfo, mint: &Pubkey) -> Result<Self> {
+        Self::try_from_account_data(account.owner, &account.try_borrow_data()?, mint)
+    }
+
+    pub fn try_from_account_data(owner: &Pubkey, data: &[u8], mint: &Pubkey) -> Result<Self> {
+        require_keys_eq!(
+            *owner,
+            anchor_spl::metadata::ID,
+            ErrorCode::NftMetadataMismatch
+        );
+        let metadata = MetadataAccount::try_deserialize(&mut &data[..])
+            .map_err(|_| ErrorCode::NftMetadataMismatch)?;
+        require_keys_eq!(metadata.mint, *mint, ErrorCode::NftMetadataMismatch);
+        Ok(Self::from(&*metadata))
+    }
+
+    /// What each creator gets out of a sale at `price`, in creator order.
+    /// Each cut is rounded down; the rounding stays with the seller. The cuts
+    /// never add up to more than `seller_fee_basis_points` of `price`, even
+    /// if the shares add up to more than `CREATOR_SHARES`.
+    pub fn royalties(&self, price: u64) -> Vec<(Pubkey, u64)> {
+        let basis_points = self.seller_fee_basis_points.min(BASIS_POINTS);
+        // At most `price`, so every cut fits in a `u64`.
+        let total = (price as u128 * basis_points as u128 / BASIS_POINTS as u128) as u64;
+        let mut left = total;
+        self.creators
+            .iter()
+            .map(|(creator, share)| {
+                let cut = (total as u128 * *share as u128 / CREATOR_SHARES as u128) as u64;
+                let cut = cut.min(left);
+                left -= cut;
+                (*creator, cut)
+            })
+            .collect()
+    }
+}
+
+impl From<&Metadata> for NftMetadata {
+    /// Only a verified collection counts; anyone can name an unverified one.
+    fn from(metadata: &Metadata) -> Self {
+        Self {
+            collection: metadata
+                .collection
+                .as_ref()
+                .filter(|collection| collection.verified)
+                .map(|collection| collection.key),
+            seller_fee_basis_points: metadata.seller_fee_basis_points,
+            creators: metadata
+                .creators
+                .iter()
+                .flatten()
+                .map(|creator| (creator.address, creator.share))
+                .collect(),
+        }
+    }
+}
@@ -1,8 +1,128 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, Discriminator};
+
+use crate::{
+    error::ErrorCode, PricePeg, ANCHOR_DISCRIMINATOR, MAX_PAYMENT_OPTIONS, OFFER_SEED,
+    OFFER_VERSION,
+};
+
+/// A mint the maker accepts as payment, and how much of it they want.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Debug, PartialEq, Eq)]
+pub struct PaymentOption {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
 
 #[account]
 #[derive(InitSpace)]
 pub struct Offer {
+    /// Always first, so any later layout can be told apart by this byte alone.
+    pub version: u8,
+    pub id: u64,
+    pub maker: Pubkey,
+    pub token_mint_a: Pubkey,
+    /// The payment mint passed to `make_offer` as an account.
+    pub token_mint_b: Pubkey,
+    /// For pegged offers, the least token B the maker takes, whatever the
+    /// feed says.
+    pub token_b_wanted_amount: u64,
+    pub bump: u8,
+    /// Mints the maker accepts instead of `token_mint_b`. Every offer is
+    /// allocated with room for all of them.
+    #[max_len(MAX_PAYMENT_OPTIONS - 1)]
+    pub other_payments: Vec<PaymentOption>,
+    /// Set when the token B amount follows a price feed. Only applies to
+    /// `token_mint_b`, `other_payments` keep their fixed amounts.
+    pub peg: Option<PricePeg>,
+    /// Set for NFT listings: the verified Metaplex collection of
+    /// `token_mint_a`. Taking a listing pays its creators their royalties.
+    pub nft_collection: Option<Pubkey>,
+    /// Paid the rent of the offer and its vault, and gets it back when the
+    /// offer closes. The maker, unless a relayer sponsored the offer.
+    pub rent_payer: Pubkey,
+}
+
+impl Offer {
+    /// Amount of `mint` the maker wants, if they accept it as payment. For a
+    /// pegged offer's `token_mint_b` this is only the floor.
+    pub fn wanted_amount(&self, mint: &Pubkey) -> Option<u64> {
+        if *mint == self.token_mint_b {
+            return Some(self.token_b_wanted_amount);
+        }
+        self.other_payments
+            .iter()
+            .find(|payment| payment.mint == *mint)
+            .map(|payment| payment.amount)
+    }
+
+    /// Every accepted payment, starting with `token_mint_b`.
+    pub fn payment_options(&self) -> impl Iterator<Item = PaymentOption> + '_ {
+        std::iter::once(PaymentOption {
+            mint: self.token_mint_b,
+            amount: self.token_b_wanted_amount,
+        })
+        .chain(self.other_payments.iter().cloned())
+    }
+
+    /// Address of the offer `maker` creates with `id`, as derived by `make_offer`.
+    pub fn pda(maker: &Pubkey, id: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[OFFER_SEED, maker.as_ref(), &id.to_le_bytes()], &crate::ID)
+    }
+
+    /// Where the rent of the offer and its vault goes when the offer closes:
+    /// back to `rent_payer`, passed as `offer_rent_payer` unless it is the
+    /// maker.
+    pub fn rent_refund_destination<'info>(
+        &self,
+        maker: &AccountInfo<'info>,
+        offer_rent_payer: Option<&AccountInfo<'info>>,
+    ) -> Result<AccountInfo<'info>> {
+        if self.rent_payer == self.maker {
+            return Ok(maker.clone());
+        }
+        let offer_rent_payer = offer_rent_payer.ok_or(ErrorCode::RentPayerRequired)?;
+        require_keys_eq!(
+            offer_rent_payer.key(),
+            self.rent_payer,
+            ErrorCode::RentPayerMismatch
+        );
+        Ok(offer_rent_payer.clone())
+    }
+
+    /// Decodes offer account data written with any layout this program ever
+    /// used, upgrading it to the current one. Versioned layouts are told
+    /// apart by their `version` byte; only the first layout had none, and is
+    /// recognized by its length, which versioned offers never have.
+    pub fn try_from_versioned(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() >= ANCHOR_DISCRIMINATOR
+                && data[..ANCHOR_DISCRIMINATOR] == *Offer::DISCRIMINATOR,
+            ErrorCode::UnknownOfferLayout
+        );
+        let mut body = &data[ANCHOR_DISCRIMINATOR..];
+
+        if body.len() == OfferV1::LEN {
+            return Ok(OfferV1::deserialize(&mut body)?.upgrade());
+        }
+        require!(
+            body.first() == Some(&OFFER_VERSION),
+            ErrorCode::UnknownOfferLayout
+        );
+        Ok(Offer::deserialize(&mut body)?)
+    }
+
+    /// Whether `data` is an offer already stored in the current layout, so
+    /// `migrate_offer` has nothing to do.
+    pub fn is_current_layout(data: &[u8]) -> bool {
+        data.len() == ANCHOR_DISCRIMINATOR + Offer::INIT_SPACE
+            && data[..ANCHOR_DISCRIMINATOR] == *Offer::DISCRIMINATOR
+            && data[ANCHOR_DISCRIMINATOR] == OFFER_VERSION
+    }
+}
+
+/// Offer layout before the `version` field was introduced. Accounts in this
+/// layout are 8 + `OfferV1::LEN` bytes long.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct OfferV1 {
     pub id: u64,
     pub maker: Pubkey,
     pub token_mint_a: Pubkey,
@@ -10,3 +130,23 @@ pub struct Offer {
     pub token_b_wanted_amount: u64,
     pub bump: u8,
 }
+
+impl OfferV1 {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 8 + 1;
+
+    pub fn upgrade(self) -> Offer {
+        Offer {
+            version: OFFER_VERSION,
+            id: self.id,
+            maker: self.maker,
+            token_mint_a: self.token_mint_a,
+            token_mint_b: self.token_mint_b,
+            token_b_wanted_amount: self.token_b_wanted_amount,
+            bump: self.bump,
+            other_payments: Vec::new(),
+            peg: None,
+            nft_collection: None,
+            rent_payer: self.maker,
+        }
+    }
+}
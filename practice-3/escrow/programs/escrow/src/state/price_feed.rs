@@ -0,0 +1,131 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::ErrorCode, BASIS_POINTS};
+
+/// A price read from a feed account, of one whole token A in whole token B:
+/// `price * 10^exponent`, give or take `confidence * 10^exponent`.
+///
+/// Feeds can belong to any program, as long as the account is laid out as:
+///
+/// | offset | size | field                                  |
+/// |--------|------|----------------------------------------|
+/// | 0      | 8    | discriminator, ignored                 |
+/// | 8      | 32   | authority, ignored                     |
+/// | 40     | 8    | `price`, `i64`, must be positive       |
+/// | 48     | 8    | `confidence`, `u64`                    |
+/// | 56     | 4    | `exponent`, `i32`                      |
+/// | 60     | 8    | `publish_time`, `i64` unix timestamp   |
+///
+/// All little-endian. The `mock-oracle` program writes feeds in this layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceFeed {
+    pub price: i64,
+    pub confidence: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+impl PriceFeed {
+    pub const LEN: usize = 68;
+
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self> {
+        require!(data.len() >= Self::LEN, ErrorCode::InvalidPriceFeed);
+        let bytes = |offset: usize, len: usize| &data[offset..offset + len];
+        let feed = Self {
+            price: i64::from_le_bytes(bytes(40, 8).try_into().unwrap()),
+            confidence: u64::from_le_bytes(bytes(48, 8).try_into().unwrap()),
+            exponent: i32::from_le_bytes(bytes(56, 4).try_into().unwrap()),
+            publish_time: i64::from_le_bytes(bytes(60, 8).try_into().unwrap()),
+        };
+        require!(feed.price > 0, ErrorCode::InvalidPriceFeed);
+        Ok(feed)
+    }
+}
+
+/// How a pegged offer prices token B, chosen by the maker.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PegTerms {
+    /// Added to the feed price, so -100 sells at the feed price minus 1%.
+    pub spread_bps: i16,
+    /// Oldest feed price, in seconds, a taker can fill at.
+    pub max_staleness_secs: u32,
+    /// Widest confidence interval, relative to the price, a taker can fill at.
+    pub max_confidence_bps: u16,
+}
+
+impl PegTerms {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.spread_bps.unsigned_abs() < BASIS_POINTS,
+            ErrorCode::InvalidPriceSpread
+        );
+        require!(
+            self.max_confidence_bps <= BASIS_POINTS,
+            ErrorCode::InvalidBasisPoints
+        );
+        Ok(())
+    }
+}
+
+/// Stored on an offer whose `token_mint_b` price follows a feed.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Debug, PartialEq, Eq)]
+pub struct PricePeg {
+    pub price_feed: Pubkey,
+    /// Owner of `price_feed` when the offer was made. A feed that changes
+    /// owner is no longer trusted.
+    pub oracle_program: Pubkey,
+    pub terms: PegTerms,
+}
+
+impl PricePeg {
+    /// Token B base units for `token_a_amount` base units of token A at the
+    /// feed price plus the spread, rounded up in the maker's favour.
+    pub fn wanted_amount(
+        &self,
+        feed: &PriceFeed,
+        now: i64,
+        token_a_amount: u64,
+        token_a_decimals: u8,
+        token_b_decimals: u8,
+    ) -> Result<u64> {
+        require!(feed.publish_time <= now, ErrorCode::PriceFromTheFuture);
+        require!(
+            now.saturating_sub(feed.publish_time) <= self.terms.max_staleness_secs as i64,
+            ErrorCode::StalePrice
+        );
+        require!(
+            feed.confidence as u128 * BASIS_POINTS as u128
+                <= feed.price as u128 * self.terms.max_confidence_bps as u128,
+            ErrorCode::PriceConfidenceTooWide
+        );
+
+        let spread_price = (BASIS_POINTS as i64 + self.terms.spread_bps as i64) as u128;
+        let numerator = (token_a_amount as u128)
+            .checked_mul(feed.price as u128)
+            .and_then(|value| value.checked_mul(spread_price));
+        let scale = feed.exponent as i64 + token_b_decimals as i64 - token_a_decimals as i64;
+        let power = |exponent: i64| {
+            u32::try_from(exponent)
+                .ok()
+                .and_then(|exponent| 10u128.checked_pow(exponent))
+        };
+        let (numerator, denominator) = if scale >= 0 {
+            (
+                numerator
+                    .zip(power(scale))
+                    .and_then(|(n, p)| n.checked_mul(p)),
+                Some(BASIS_POINTS as u128),
+            )
+        } else {
+            (
+                numerator,
+                power(-scale).and_then(|p| p.checked_mul(BASIS_POINTS as u128)),
+            )
+        };
+        let (Some(numerator), Some(denominator)) = (numerator, denominator) else {
+            return err!(ErrorCode::PriceOutOfRange);
+        };
+        u64::try_from(numerator.div_ceil(denominator))
+            .map_err(|_| error!(ErrorCode::PriceOutOfRange))
+    }
+}
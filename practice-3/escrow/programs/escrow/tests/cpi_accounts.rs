@@ -0,0 +1,205 @@
+//! Pins the account order of the instruction builders that CPI callers use.
+//! Reordering fields in an `Accounts` struct is a breaking change for every
+//! program that composes with the escrow, so these tests must be updated
+//! together with the docs on `MakeOffer` and `TakeOffer`.
+
+use anchor_lang::prelude::*;
+use anchor_lang::{InstructionData, ToAccountMetas};
+
+fn unique_keys<const N: usize>() -> [Pubkey; N] {
+    std::array::from_fn(|_| Pubkey::new_unique())
+}
+
+fn flags(metas: &[AccountMeta]) -> Vec<(bool, bool)> {
+    metas.iter().map(|m| (m.is_writable, m.is_signer)).collect()
+}
+
+#[test]
+fn make_offer_accounts_follow_documented_order() {
+    let keys = unique_keys::<14>();
+    let metas = escrow::accounts::MakeOffer {
+        maker: keys[0],
+        token_mint_a: keys[1],
+        token_mint_b: keys[2],
+        maker_token_account_a: keys[3],
+        offer: keys[4],
+        vault: keys[5],
+        associated_token_program: keys[6],
+        token_program: keys[7],
+        system_program: keys[8],
+        config: keys[9],
+        mint_registry: Some(keys[10]),
+        price_feed: Some(keys[11]),
+        nft_metadata: Some(keys[12]),
+        rent_payer: Some(keys[13]),
+    }
+    .to_account_metas(None);
+
+    assert_eq!(metas.iter().map(|m| m.pubkey).collect::<Vec<_>>(), keys);
+    assert_eq!(
+        flags(&metas),
+        [
+            (true, true),
+            (false, false),
+            (false, false),
+            (true, false),
+            (true, false),
+            (true, false),
+            (false, false),
+            (false, false),
+            (false, false),
+            (false, false),
+            (false, false),
+            (false, false),
+            (false, false),
+            (true, true),
+        ]
+    );
+}
+
+#[test]
+fn take_offer_accounts_follow_documented_order() {
+    let keys = unique_keys::<20>();
+    let metas = escrow::accounts::TakeOffer {
+        taker: keys[0],
+        maker: keys[1],
+        token_mint_a: keys[2],
+        token_mint_b: keys[3],
+        taker_token_account_a: keys[4],
+        taker_token_account_b: keys[5],
+        maker_token_account_b: keys[6],
+        offer: keys[7],
+        vault: keys[8],
+        associated_token_program: keys[9],
+        token_program: keys[10],
+        system_program: keys[11],
+        config: keys[12],
+        fill_receipt: Some(keys[13]),
+        referrer: Some(keys[14]),
+        referrer_token_account_b: Some(keys[15]),
+        price_feed: Some(keys[16]),
+        nft_metadata: Some(keys[17]),
+        rent_payer: Some(keys[18]),
+        offer_rent_payer: Some(keys[19]),
+    }
+    .to_account_metas(None);
+
+    assert_eq!(metas.iter().map(|m| m.pubkey).collect::<Vec<_>>(), keys);
+    assert_eq!(
+        flags(&metas),
+        [
+            (true, true),
+            (true, false),
+            (false, false),
+            (false, false),
+            (true, false),
+            (true, false),
+            (true, false),
+            (true, false),
+            (true, false),
+            (false, false),
+            (false, false),
+            (false, false),
+            (false, false),
+            (true, false),
+            (false, false),
+            (true, false),
+            (false, false),
+            (false, false),
+            (true, true),
+            (true, false),
+        ]
+    );
+}
+
+#[test]
+fn take_offer_without_optional_accounts_passes_program_id_in_their_place() {
+    let keys = unique_keys::<13>();
+    let metas = escrow::accounts::TakeOffer {
+        taker: keys[0],
+        maker: keys[1],
+        token_mint_a: keys[2],
+        token_mint_b: keys[3],
+        taker_token_account_a: keys[4],
+        taker_token_account_b: keys[5],
+        maker_token_account_b: keys[6],
+        offer: keys[7],
+        vault: keys[8],
+        associated_token_program: keys[9],
+        token_program: keys[10],
+        system_program: keys[11],
+        config: keys[12],
+        fill_receipt: None,
+        referrer: None,
+        referrer_token_account_b: None,
+        price_feed: None,
+        nft_metadata: None,
+        rent_payer: None,
+        offer_rent_payer: None,
+    }
+    .to_account_metas(None);
+
+    assert_eq!(metas.len(), 20);
+    for meta in &metas[13..] {
+        assert_eq!(meta.pubkey, escrow::ID);
+        assert!(!meta.is_writable);
+    }
+}
+
+#[test]
+fn make_offer_data_is_discriminator_then_arguments() {
+    let data = escrow::instruction::MakeOffer {
+        id: 7,
+        token_a_offered_amount: 1_000,
+        token_b_wanted_amount: 2_000,
+        other_payments: Vec::new(),
+        peg: None,
+    }
+    .data();
+
+    assert_eq!(&data[..8], escrow::instruction::MakeOffer::DISCRIMINATOR);
+    assert_eq!(&data[8..16], 7u64.to_le_bytes());
+    assert_eq!(&data[16..24], 1_000u64.to_le_bytes());
+    assert_eq!(&data[24..32], 2_000u64.to_le_bytes());
+    // An empty `other_payments` is just its length prefix, no `peg` one byte.
+    assert_eq!(&data[32..36], 0u32.to_le_bytes());
+    assert_eq!(&data[36..], [0]);
+}
+
+#[test]
+fn take_offer_data_is_discriminator_then_referral_fee() {
+    let data = escrow::instruction::TakeOffer {
+        referral_fee_bps: 25,
+    }
+    .data();
+
+    assert_eq!(&data[..8], escrow::instruction::TakeOffer::DISCRIMINATOR);
+    assert_eq!(&data[8..], 25u16.to_le_bytes());
+}
+
+#[test]
+fn fill_receipt_pda_matches_take_offer_seeds() {
+    let offer = Pubkey::new_unique();
+    let taker = Pubkey::new_unique();
+    let (address, bump) = escrow::FillReceipt::pda(&offer, &taker);
+
+    let expected = Pubkey::create_program_address(
+        &[b"fill_receipt", offer.as_ref(), taker.as_ref(), &[bump]],
+        &escrow::ID,
+    )
+    .unwrap();
+    assert_eq!(address, expected);
+}
+
+#[test]
+fn offer_pda_matches_make_offer_seeds() {
+    let maker = Pubkey::new_unique();
+    let (address, bump) = escrow::Offer::pda(&maker, 42);
+
+    let expected = Pubkey::create_program_address(
+        &[b"offer", maker.as_ref(), &42u64.to_le_bytes(), &[bump]],
+        &escrow::ID,
+    )
+    .unwrap();
+    assert_eq!(address, expected);
+}
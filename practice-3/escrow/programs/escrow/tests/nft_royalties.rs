@@ -0,0 +1,116 @@
+//! Reading Metaplex metadata for NFT listings and splitting royalties
+//! between creators.
+
+use anchor_lang::prelude::*;
+use anchor_spl::metadata::mpl_token_metadata::{
+    accounts::Metadata,
+    types::{Collection, Creator, Key},
+};
+use borsh::BorshSerialize;
+use escrow::{error::ErrorCode, NftMetadata};
+
+fn metadata(mint: Pubkey, collection_verified: bool) -> Metadata {
+    Metadata {
+        key: Key::MetadataV1,
+        update_authority: Pubkey::new_unique(),
+        mint,
+        name: "Escrow Test".to_string(),
+        symbol: "ESC".to_string(),
+        uri: "https://example.com/nft.json".to_string(),
+        seller_fee_basis_points: 250,
+        creators: Some(vec![Creator {
+            address: Pubkey::new_unique(),
+            verified: true,
+            share: 100,
+        }]),
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+        token_standard: None,
+        collection: Some(Collection {
+            verified: collection_verified,
+            key: Pubkey::new_unique(),
+        }),
+        uses: None,
+        collection_details: None,
+        programmable_config: None,
+    }
+}
+
+fn creators(shares: &[u8]) -> Vec<(Pubkey, u8)> {
+    shares
+        .iter()
+        .map(|share| (Pubkey::new_unique(), *share))
+        .collect()
+}
+
+fn cuts(metadata: &NftMetadata, price: u64) -> Vec<u64> {
+    metadata
+        .royalties(price)
+        .into_iter()
+        .map(|(_, cut)| cut)
+        .collect()
+}
+
+#[test]
+fn reads_the_metadata_of_its_mint() {
+    let mint = Pubkey::new_unique();
+    let source = metadata(mint, true);
+    let data = source.try_to_vec().unwrap();
+
+    let decoded =
+        NftMetadata::try_from_account_data(&anchor_spl::metadata::ID, &data, &mint).unwrap();
+    assert_eq!(decoded.collection, Some(source.collection.unwrap().key));
+    assert_eq!(decoded.seller_fee_basis_points, 250);
+    assert_eq!(decoded.creators.len(), 1);
+
+    let wrong_mint =
+        NftMetadata::try_from_account_data(&anchor_spl::metadata::ID, &data, &Pubkey::new_unique());
+    assert_eq!(wrong_mint, Err(ErrorCode::NftMetadataMismatch.into()));
+    let wrong_owner = NftMetadata::try_from_account_data(&Pubkey::new_unique(), &data, &mint);
+    assert!(wrong_owner.is_err());
+    let truncated =
+        NftMetadata::try_from_account_data(&anchor_spl::metadata::ID, &data[..40], &mint);
+    assert_eq!(truncated, Err(ErrorCode::NftMetadataMismatch.into()));
+}
+
+#[test]
+fn ignores_unverified_collections() {
+    let mint = Pubkey::new_unique();
+    let data = metadata(mint, false).try_to_vec().unwrap();
+
+    let decoded =
+        NftMetadata::try_from_account_data(&anchor_spl::metadata::ID, &data, &mint).unwrap();
+    assert_eq!(decoded.collection, None);
+}
+
+#[test]
+fn splits_royalties_by_share_rounding_down() {
+    let metadata = NftMetadata {
+        collection: None,
+        seller_fee_basis_points: 500,
+        creators: creators(&[70, 30]),
+    };
+    assert_eq!(cuts(&metadata, 5_000_000), [175_000, 75_000]);
+    // 5% of 999 is 49, of which 70% is 34.3 and 30% is 14.7.
+    assert_eq!(cuts(&metadata, 999), [34, 14]);
+    assert_eq!(cuts(&metadata, 0), [0, 0]);
+    assert_eq!(cuts(&metadata, u64::MAX).iter().sum::<u64>(), u64::MAX / 20);
+}
+
+#[test]
+fn royalties_never_exceed_the_seller_fee() {
+    let overshared = NftMetadata {
+        collection: None,
+        seller_fee_basis_points: 1_000,
+        creators: creators(&[80, 80]),
+    };
+    assert_eq!(cuts(&overshared, 1_000), [80, 20]);
+
+    let overcharged = NftMetadata {
+        collection: None,
+        seller_fee_basis_points: u16::MAX,
+        creators: creators(&[100]),
+    };
+    assert_eq!(cuts(&overcharged, 1_000), [1_000]);
+}
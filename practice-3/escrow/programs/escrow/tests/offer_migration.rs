@@ -0,0 +1,106 @@
+//! Decoding of every `Offer` layout the program has written, as used by
+//! `migrate_offer`.
+
+use anchor_lang::prelude::*;
+use anchor_lang::{AccountSerialize, Discriminator};
+use escrow::{Offer, OfferV1, PaymentOption, OFFER_VERSION};
+
+fn v1_offer() -> OfferV1 {
+    OfferV1 {
+        id: 1,
+        maker: Pubkey::new_unique(),
+        token_mint_a: Pubkey::new_unique(),
+        token_mint_b: Pubkey::new_unique(),
+        token_b_wanted_amount: 25_000_000,
+        bump: 253,
+    }
+}
+
+fn v1_account_data(offer: &OfferV1) -> Vec<u8> {
+    let mut data = Offer::DISCRIMINATOR.to_vec();
+    offer.serialize(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn v1_account_is_upgraded_with_all_fields() {
+    let v1 = v1_offer();
+    let data = v1_account_data(&v1);
+    assert_eq!(data.len(), 8 + OfferV1::LEN);
+
+    let offer = Offer::try_from_versioned(&data).unwrap();
+    assert_eq!(offer.version, OFFER_VERSION);
+    assert_eq!(offer.id, v1.id);
+    assert_eq!(offer.maker, v1.maker);
+    assert_eq!(offer.token_mint_a, v1.token_mint_a);
+    assert_eq!(offer.token_mint_b, v1.token_mint_b);
+    assert_eq!(offer.token_b_wanted_amount, v1.token_b_wanted_amount);
+    assert_eq!(offer.bump, v1.bump);
+}
+
+#[test]
+fn upgraded_offer_serializes_to_the_current_layout() {
+    let offer = OfferV1::upgrade(v1_offer());
+
+    let mut data = Vec::new();
+    offer.try_serialize(&mut data).unwrap();
+    // Fits the reallocated account with room for every payment option.
+    assert!(data.len() <= 8 + Offer::INIT_SPACE);
+    assert_eq!(data[8], OFFER_VERSION);
+
+    let decoded = Offer::try_deserialize(&mut &data[..]).unwrap();
+    assert_eq!(decoded.maker, offer.maker);
+    assert_eq!(decoded.token_b_wanted_amount, offer.token_b_wanted_amount);
+}
+
+#[test]
+fn current_layout_decodes_unchanged() {
+    let mut offer = OfferV1::upgrade(v1_offer());
+    offer.other_payments.push(PaymentOption {
+        mint: Pubkey::new_unique(),
+        amount: 40_000_000,
+    });
+    let mut data = Vec::new();
+    offer.try_serialize(&mut data).unwrap();
+    // Accounts are allocated at full size, so the tail is zero padding.
+    data.resize(8 + Offer::INIT_SPACE, 0);
+    assert!(Offer::is_current_layout(&data));
+
+    let decoded = Offer::try_from_versioned(&data).unwrap();
+    assert_eq!(decoded.version, OFFER_VERSION);
+    assert_eq!(decoded.id, offer.id);
+    assert_eq!(decoded.other_payments, offer.other_payments);
+}
+
+#[test]
+fn only_the_current_version_is_the_current_layout() {
+    assert!(!Offer::is_current_layout(&v1_account_data(&v1_offer())));
+
+    let mut data = Vec::new();
+    OfferV1::upgrade(v1_offer())
+        .try_serialize(&mut data)
+        .unwrap();
+    data.resize(8 + Offer::INIT_SPACE, 0);
+    data[8] = OFFER_VERSION + 1;
+    assert!(!Offer::is_current_layout(&data));
+}
+
+#[test]
+fn unknown_layouts_are_rejected() {
+    let mut wrong_discriminator = v1_account_data(&v1_offer());
+    wrong_discriminator[0] ^= 0xff;
+    assert!(Offer::try_from_versioned(&wrong_discriminator).is_err());
+
+    let mut truncated = v1_account_data(&v1_offer());
+    truncated.pop();
+    assert!(Offer::try_from_versioned(&truncated).is_err());
+
+    let mut future_version = Vec::new();
+    OfferV1::upgrade(v1_offer())
+        .try_serialize(&mut future_version)
+        .unwrap();
+    future_version[8] = OFFER_VERSION + 1;
+    assert!(Offer::try_from_versioned(&future_version).is_err());
+
+    assert!(Offer::try_from_versioned(&[]).is_err());
+}
@@ -0,0 +1,160 @@
+//! Reading price feed accounts and pricing pegged offers from them.
+
+use anchor_lang::prelude::*;
+use escrow::{error::ErrorCode, PegTerms, PriceFeed, PricePeg};
+
+fn feed_account_data(price: i64, confidence: u64, exponent: i32, publish_time: i64) -> Vec<u8> {
+    let mut data = vec![0xaa; 8];
+    data.extend_from_slice(Pubkey::new_unique().as_ref());
+    data.extend_from_slice(&price.to_le_bytes());
+    data.extend_from_slice(&confidence.to_le_bytes());
+    data.extend_from_slice(&exponent.to_le_bytes());
+    data.extend_from_slice(&publish_time.to_le_bytes());
+    data
+}
+
+fn peg(spread_bps: i16) -> PricePeg {
+    PricePeg {
+        price_feed: Pubkey::new_unique(),
+        oracle_program: Pubkey::new_unique(),
+        terms: PegTerms {
+            spread_bps,
+            max_staleness_secs: 60,
+            max_confidence_bps: 100,
+        },
+    }
+}
+
+/// 2.5 token B per token A, published at time 1_000.
+fn feed() -> PriceFeed {
+    PriceFeed {
+        price: 250,
+        confidence: 0,
+        exponent: -2,
+        publish_time: 1_000,
+    }
+}
+
+#[test]
+fn reads_the_documented_layout() {
+    let data = feed_account_data(12_345, 67, -4, 1_700_000_000);
+    assert_eq!(data.len(), PriceFeed::LEN);
+
+    assert_eq!(
+        PriceFeed::try_from_account_data(&data).unwrap(),
+        PriceFeed {
+            price: 12_345,
+            confidence: 67,
+            exponent: -4,
+            publish_time: 1_700_000_000,
+        }
+    );
+}
+
+#[test]
+fn rejects_short_data_and_non_positive_prices() {
+    let data = feed_account_data(1, 0, 0, 0);
+    assert_eq!(
+        PriceFeed::try_from_account_data(&data[..PriceFeed::LEN - 1]),
+        Err(ErrorCode::InvalidPriceFeed.into())
+    );
+    for price in [0, -5] {
+        assert_eq!(
+            PriceFeed::try_from_account_data(&feed_account_data(price, 0, 0, 0)),
+            Err(ErrorCode::InvalidPriceFeed.into())
+        );
+    }
+}
+
+#[test]
+fn prices_token_a_at_the_feed_price_plus_spread() {
+    // 2 token A with 9 decimals, priced in token B with 6 decimals.
+    let token_a_amount = 2_000_000_000;
+    assert_eq!(
+        peg(0).wanted_amount(&feed(), 1_000, token_a_amount, 9, 6),
+        Ok(5_000_000)
+    );
+    assert_eq!(
+        peg(-100).wanted_amount(&feed(), 1_000, token_a_amount, 9, 6),
+        Ok(4_950_000)
+    );
+    assert_eq!(
+        peg(250).wanted_amount(&feed(), 1_000, token_a_amount, 9, 6),
+        Ok(5_125_000)
+    );
+    // Token B with more decimals than token A.
+    assert_eq!(
+        peg(0).wanted_amount(&feed(), 1_000, 2_000_000, 6, 9),
+        Ok(5_000_000_000)
+    );
+}
+
+#[test]
+fn rounds_up_for_the_maker() {
+    // 0.0025 and 7.5 base units of token B.
+    assert_eq!(peg(0).wanted_amount(&feed(), 1_000, 1, 9, 6), Ok(1));
+    assert_eq!(peg(0).wanted_amount(&feed(), 1_000, 3, 6, 6), Ok(8));
+    assert_eq!(peg(0).wanted_amount(&feed(), 1_000, 0, 9, 6), Ok(0));
+}
+
+#[test]
+fn rejects_stale_prices() {
+    let peg = peg(0);
+    assert!(peg.wanted_amount(&feed(), 1_060, 1_000, 6, 6).is_ok());
+    assert_eq!(
+        peg.wanted_amount(&feed(), 1_061, 1_000, 6, 6),
+        Err(ErrorCode::StalePrice.into())
+    );
+}
+
+#[test]
+fn rejects_wide_confidence_intervals() {
+    let peg = peg(0);
+    // 1% of the price is allowed, a hair more is not.
+    let mut feed = PriceFeed {
+        price: 10_000,
+        confidence: 100,
+        ..feed()
+    };
+    assert!(peg.wanted_amount(&feed, 1_000, 1_000, 6, 6).is_ok());
+    feed.confidence = 101;
+    assert_eq!(
+        peg.wanted_amount(&feed, 1_000, 1_000, 6, 6),
+        Err(ErrorCode::PriceConfidenceTooWide.into())
+    );
+}
+
+#[test]
+fn rejects_amounts_that_do_not_fit() {
+    let feed = PriceFeed {
+        price: i64::MAX,
+        exponent: 10,
+        ..feed()
+    };
+    assert_eq!(
+        peg(0).wanted_amount(&feed, 1_000, u64::MAX, 0, 0),
+        Err(ErrorCode::PriceOutOfRange.into())
+    );
+}
+
+#[test]
+fn validates_peg_terms() {
+    let terms = |spread_bps, max_confidence_bps| PegTerms {
+        spread_bps,
+        max_staleness_secs: 60,
+        max_confidence_bps,
+    };
+    assert!(terms(-9_999, 10_000).validate().is_ok());
+    assert_eq!(
+        terms(-10_000, 0).validate(),
+        Err(ErrorCode::InvalidPriceSpread.into())
+    );
+    assert_eq!(
+        terms(10_000, 0).validate(),
+        Err(ErrorCode::InvalidPriceSpread.into())
+    );
+    assert_eq!(
+        terms(0, 10_001).validate(),
+        Err(ErrorCode::InvalidBasisPoints.into())
+    );
+}
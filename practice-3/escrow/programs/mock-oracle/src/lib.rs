@@ -0,0 +1,80 @@
+// `#[program]` in anchor-lang 0.31.1 still expands to `AccountInfo::realloc`.
+#![allow(deprecated)]
+
+use anchor_lang::prelude::*;
+
+declare_id!("H1yjHWH9HPVKrRr2akwwRmq1nekm9ENDyRqqpuSKJ6ga");
+
+/// A stand-in for a live price feed in local tests: whoever creates a feed
+/// sets its price by hand. Feeds use the layout pegged escrow offers read,
+/// see `escrow::PriceFeed`.
+#[program]
+pub mod mock_oracle {
+    use super::*;
+
+    pub fn initialize_price_feed(
+        context: Context<InitializePriceFeed>,
+        price: i64,
+        confidence: u64,
+        exponent: i32,
+    ) -> Result<()> {
+        context.accounts.price_feed.set_inner(PriceFeed {
+            authority: context.accounts.authority.key(),
+            price,
+            confidence,
+            exponent,
+            publish_time: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Publishes a new price, stamped with the current time unless
+    /// `publish_time` says otherwise, so tests can make a feed go stale.
+    pub fn set_price(
+        context: Context<SetPrice>,
+        price: i64,
+        confidence: u64,
+        publish_time: Option<i64>,
+    ) -> Result<()> {
+        let price_feed = &mut context.accounts.price_feed;
+        price_feed.price = price;
+        price_feed.confidence = confidence;
+        price_feed.publish_time = match publish_time {
+            Some(publish_time) => publish_time,
+            None => Clock::get()?.unix_timestamp,
+        };
+        Ok(())
+    }
+}
+
+/// One price of a whole base token in whole quote tokens:
+/// `price * 10^exponent`, give or take `confidence * 10^exponent`.
+#[account]
+#[derive(InitSpace)]
+pub struct PriceFeed {
+    pub authority: Pubkey,
+    pub price: i64,
+    pub confidence: u64,
+    pub exponent: i32,
+    /// Unix timestamp of the last `set_price`.
+    pub publish_time: i64,
+}
+
+#[derive(Accounts)]
+pub struct InitializePriceFeed<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(init, payer = authority, space = 8 + PriceFeed::INIT_SPACE)]
+    pub price_feed: Account<'info, PriceFeed>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut, has_one = authority)]
+    pub price_feed: Account<'info, PriceFeed>,
+}
@@ -0,0 +1,188 @@
+import { expect, describe, beforeAll, test } from "@jest/globals";
+import * as anchor from "@coral-xyz/anchor";
+import { type Program, BN } from "@coral-xyz/anchor";
+import { Escrow } from "../target/types/escrow";
+import {
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+
+import { confirmTransaction, makeKeypairs } from "@solana-developers/helpers";
+
+import {
+  TOKEN_PROGRAM,
+  areBnEqual,
+  createTokenAndMintTo,
+  ensureConfigInitialized,
+  getRandomBigNumber,
+  getTokenBalanceOn,
+} from "./helpers";
+
+expect.addEqualityTesters([areBnEqual]);
+
+describe("escrow counter offers", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const provider = anchor.getProvider();
+  const connection = provider.connection;
+
+  const program = anchor.workspace.Escrow as Program<Escrow>;
+
+  const [alice, bob, usdcMint, wifMint] = makeKeypairs(4);
+
+  const [aliceWifAccount, bobUsdcAccount, bobWifAccount] = [
+    [alice, wifMint],
+    [bob, usdcMint],
+    [bob, wifMint],
+  ].map(([owner, mint]) =>
+    getAssociatedTokenAddressSync(
+      mint.publicKey,
+      owner.publicKey,
+      false,
+      TOKEN_PROGRAM
+    )
+  );
+
+  const getTokenBalance = getTokenBalanceOn(connection);
+
+  const makeOfferTx = async () => {
+    const offerId = getRandomBigNumber();
+    const transactionSignature = await program.methods
+      .makeOffer(offerId, new BN(10_000_000), new BN(100_000_000), [], null)
+      .accounts({
+        maker: alice.publicKey,
+        tokenMintA: usdcMint.publicKey,
+        tokenMintB: wifMint.publicKey,
+        tokenProgram: TOKEN_PROGRAM,
+      })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, transactionSignature);
+
+    const [offerAddress, _offerBump] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("offer"),
+        alice.publicKey.toBuffer(),
+        offerId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    return offerAddress;
+  };
+
+  const makeCounterOfferTx = async (offer: PublicKey, amount: BN) => {
+    const transactionSignature = await program.methods
+      .makeCounterOffer(amount)
+      .accounts({
+        counterParty: bob.publicKey,
+        offer,
+        tokenMintA: usdcMint.publicKey,
+        tokenMintB: wifMint.publicKey,
+        tokenProgram: TOKEN_PROGRAM,
+      })
+      .signers([bob])
+      .rpc();
+    await confirmTransaction(connection, transactionSignature);
+
+    const [counterOfferAddress, _counterOfferBump] =
+      PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("counter_offer"),
+          offer.toBuffer(),
+          bob.publicKey.toBuffer(),
+        ],
+        program.programId
+      );
+    return counterOfferAddress;
+  };
+
+  beforeAll(async () => {
+    await ensureConfigInitialized(program);
+
+    const giveSolIxs = [alice, bob].map((owner) =>
+      SystemProgram.transfer({
+        fromPubkey: provider.publicKey,
+        toPubkey: owner.publicKey,
+        lamports: 10 * LAMPORTS_PER_SOL,
+      })
+    );
+
+    const usdcSetupIxs = await createTokenAndMintTo(
+      connection,
+      provider.publicKey,
+      usdcMint.publicKey,
+      6,
+      alice.publicKey,
+      [{ recepient: alice.publicKey, amount: 100_000_000 }]
+    );
+
+    const wifSetupIxs = await createTokenAndMintTo(
+      connection,
+      provider.publicKey,
+      wifMint.publicKey,
+      6,
+      bob.publicKey,
+      [{ recepient: bob.publicKey, amount: 300_000_000 }]
+    );
+
+    let tx = new Transaction();
+    tx.instructions = [...giveSolIxs, ...usdcSetupIxs, ...wifSetupIxs];
+    await provider.sendAndConfirm(tx, [alice, bob, usdcMint, wifMint]);
+  });
+
+  test("Bob counters, Alice rejects, and Bob is refunded", async () => {
+    const offer = await makeOfferTx();
+    const counterOffer = await makeCounterOfferTx(offer, new BN(80_000_000));
+
+    const counterOfferAccount = await program.account.counterOffer.fetch(
+      counterOffer
+    );
+    expect(counterOfferAccount.counterParty).toEqual(bob.publicKey);
+    expect(counterOfferAccount.tokenBAmount).toEqual(new BN(80_000_000));
+    expect(await getTokenBalance(bobWifAccount)).toEqual(new BN(220_000_000));
+
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .rejectCounterOffer()
+        .accounts({
+          maker: alice.publicKey,
+          offer,
+          counterOffer,
+          tokenProgram: TOKEN_PROGRAM,
+        })
+        .signers([alice])
+        .rpc()
+    );
+
+    expect(await getTokenBalance(bobWifAccount)).toEqual(new BN(300_000_000));
+    expect(await connection.getAccountInfo(counterOffer)).toBeNull();
+  });
+
+  test("Alice accepts a counter, settling both vaults", async () => {
+    const offer = await makeOfferTx();
+    const counterOffer = await makeCounterOfferTx(offer, new BN(80_000_000));
+
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .acceptCounterOffer()
+        .accounts({
+          maker: alice.publicKey,
+          offer,
+          counterOffer,
+          tokenProgram: TOKEN_PROGRAM,
+        })
+        .signers([alice])
+        .rpc()
+    );
+
+    expect(await getTokenBalance(aliceWifAccount)).toEqual(new BN(80_000_000));
+    expect(await getTokenBalance(bobUsdcAccount)).toEqual(new BN(10_000_000));
+    expect(await connection.getAccountInfo(offer)).toBeNull();
+    expect(await connection.getAccountInfo(counterOffer)).toBeNull();
+  });
+});
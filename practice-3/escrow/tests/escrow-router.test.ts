@@ -0,0 +1,207 @@
+import { expect, describe, beforeAll, test } from "@jest/globals";
+import * as anchor from "@coral-xyz/anchor";
+import { type Program, BN } from "@coral-xyz/anchor";
+import { Escrow } from "../target/types/escrow";
+import { EscrowRouter } from "../target/types/escrow_router";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+
+import { confirmTransaction, makeKeypairs } from "@solana-developers/helpers";
+
+import {
+  TOKEN_PROGRAM,
+  areBnEqual,
+  createTokenAndMintTo,
+  ensureConfigInitialized,
+  getConfigAddress,
+  getRandomBigNumber,
+  getTokenBalanceOn,
+} from "./helpers";
+
+expect.addEqualityTesters([areBnEqual]);
+
+// Exercises the escrow through CPI: the router program picks the cheaper of
+// two offers and calls `take_offer` on the escrow program on the taker's
+// behalf.
+describe("escrow router", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const provider = anchor.getProvider();
+  const connection = provider.connection;
+
+  const escrow = anchor.workspace.Escrow as Program<Escrow>;
+  const router = anchor.workspace.EscrowRouter as Program<EscrowRouter>;
+
+  const [alice, carol, bob, usdcMint, wifMint] = makeKeypairs(5);
+
+  const ata = (mint: PublicKey, owner: PublicKey) =>
+    getAssociatedTokenAddressSync(mint, owner, true, TOKEN_PROGRAM);
+
+  const getTokenBalance = getTokenBalanceOn(connection);
+
+  beforeAll(async () => {
+    await ensureConfigInitialized(escrow);
+
+    const giveSolIxs = [alice, carol, bob].map((owner) =>
+      SystemProgram.transfer({
+        fromPubkey: provider.publicKey,
+        toPubkey: owner.publicKey,
+        lamports: 10 * LAMPORTS_PER_SOL,
+      })
+    );
+
+    const usdcSetupIxs = await createTokenAndMintTo(
+      connection,
+      provider.publicKey,
+      usdcMint.publicKey,
+      6,
+      alice.publicKey,
+      [
+        { recepient: alice.publicKey, amount: 100_000_000 },
+        { recepient: carol.publicKey, amount: 100_000_000 },
+      ]
+    );
+
+    const wifSetupIxs = await createTokenAndMintTo(
+      connection,
+      provider.publicKey,
+      wifMint.publicKey,
+      6,
+      bob.publicKey,
+      [{ recepient: bob.publicKey, amount: 300_000_000 }]
+    );
+
+    let tx = new Transaction();
+    tx.instructions = [...giveSolIxs, ...usdcSetupIxs, ...wifSetupIxs];
+
+    await provider.sendAndConfirm(tx, [alice, bob, usdcMint, wifMint]);
+  });
+
+  const makeOffer = async (
+    maker: Keypair,
+    offeredAmount: BN,
+    wantedAmount: BN
+  ): Promise<PublicKey> => {
+    const offerId = getRandomBigNumber();
+
+    const transactionSignature = await escrow.methods
+      .makeOffer(offerId, offeredAmount, wantedAmount, [], null)
+      .accounts({
+        maker: maker.publicKey,
+        tokenMintA: usdcMint.publicKey,
+        tokenMintB: wifMint.publicKey,
+        tokenProgram: TOKEN_PROGRAM,
+      })
+      .signers([maker])
+      .rpc();
+
+    await confirmTransaction(connection, transactionSignature);
+
+    const [offerAddress, _offerBump] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("offer"),
+        maker.publicKey.toBuffer(),
+        offerId.toArrayLike(Buffer, "le", 8),
+      ],
+      escrow.programId
+    );
+    return offerAddress;
+  };
+
+  const takeBestOfferTx = (
+    first: { maker: Keypair; offer: PublicKey },
+    second: { maker: Keypair; offer: PublicKey },
+    maxTokenBAmount: BN
+  ) =>
+    router.methods
+      .takeBestOffer(maxTokenBAmount)
+      .accountsPartial({
+        taker: bob.publicKey,
+        tokenMintA: usdcMint.publicKey,
+        tokenMintB: wifMint.publicKey,
+        takerTokenAccountA: ata(usdcMint.publicKey, bob.publicKey),
+        takerTokenAccountB: ata(wifMint.publicKey, bob.publicKey),
+        firstMaker: first.maker.publicKey,
+        firstMakerTokenAccountB: ata(wifMint.publicKey, first.maker.publicKey),
+        firstOffer: first.offer,
+        firstVault: ata(usdcMint.publicKey, first.offer),
+        secondMaker: second.maker.publicKey,
+        secondMakerTokenAccountB: ata(
+          wifMint.publicKey,
+          second.maker.publicKey
+        ),
+        secondOffer: second.offer,
+        secondVault: ata(usdcMint.publicKey, second.offer),
+        config: getConfigAddress(escrow.programId),
+        escrowProgram: escrow.programId,
+        tokenProgram: TOKEN_PROGRAM,
+      })
+      .signers([bob])
+      .rpc();
+
+  test("Router takes the cheaper offer through the escrow program", async () => {
+    // Alice wants 10 WIF per USDC, Carol only 5.
+    const aliceOffer = await makeOffer(
+      alice,
+      new BN(10_000_000),
+      new BN(100_000_000)
+    );
+    const carolOffer = await makeOffer(
+      carol,
+      new BN(20_000_000),
+      new BN(100_000_000)
+    );
+
+    const transactionSignature = await takeBestOfferTx(
+      { maker: alice, offer: aliceOffer },
+      { maker: carol, offer: carolOffer },
+      new BN(100_000_000)
+    );
+    await confirmTransaction(connection, transactionSignature);
+
+    expect(await getTokenBalance(ata(usdcMint.publicKey, bob.publicKey))).toEqual(
+      new BN(20_000_000)
+    );
+    expect(await getTokenBalance(ata(wifMint.publicKey, bob.publicKey))).toEqual(
+      new BN(200_000_000)
+    );
+    expect(await getTokenBalance(ata(wifMint.publicKey, carol.publicKey))).toEqual(
+      new BN(100_000_000)
+    );
+
+    // The escrow closed Carol's offer and left Alice's untouched.
+    expect(await connection.getAccountInfo(carolOffer)).toBeNull();
+    const aliceOfferAccount = await escrow.account.offer.fetch(aliceOffer);
+    expect(aliceOfferAccount.tokenBWantedAmount).toEqual(new BN(100_000_000));
+  });
+
+  test("Router refuses when the cheapest offer exceeds the taker's limit", async () => {
+    const aliceOffer = await makeOffer(
+      alice,
+      new BN(10_000_000),
+      new BN(50_000_000)
+    );
+    const carolOffer = await makeOffer(
+      carol,
+      new BN(10_000_000),
+      new BN(60_000_000)
+    );
+
+    await expect(
+      takeBestOfferTx(
+        { maker: alice, offer: aliceOffer },
+        { maker: carol, offer: carolOffer },
+        new BN(40_000_000)
+      )
+    ).rejects.toThrow(/PriceAboveLimit/);
+
+    expect(await connection.getAccountInfo(aliceOffer)).not.toBeNull();
+    expect(await connection.getAccountInfo(carolOffer)).not.toBeNull();
+  });
+});
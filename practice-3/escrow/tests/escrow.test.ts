@@ -3,7 +3,6 @@ import * as anchor from "@coral-xyz/anchor";
 import { type Program, BN } from "@coral-xyz/anchor";
 import { Escrow } from "../target/types/escrow";
 import {
-  Connection,
   Keypair,
   LAMPORTS_PER_SOL,
   PublicKey,
@@ -11,106 +10,20 @@ import {
   Transaction,
   TransactionInstruction,
 } from "@solana/web3.js";
-import {
-  MINT_SIZE,
-  TOKEN_2022_PROGRAM_ID,
-  type TOKEN_PROGRAM_ID,
-  createAssociatedTokenAccountIdempotentInstruction,
-  createInitializeMint2Instruction,
-  createMintToInstruction,
-  getAssociatedTokenAddressSync,
-  getMinimumBalanceForRentExemptMint,
-} from "@solana/spl-token";
-import { randomBytes } from "crypto";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
 
 import { confirmTransaction, makeKeypairs } from "@solana-developers/helpers";
 
-const TOKEN_PROGRAM: typeof TOKEN_2022_PROGRAM_ID | typeof TOKEN_PROGRAM_ID =
-  TOKEN_2022_PROGRAM_ID;
-
-export const getRandomBigNumber = (size: number = 8) => {
-  return new BN(randomBytes(size));
-};
-
-function areBnEqual(a: unknown, b: unknown): boolean | undefined {
-  const isABn = a instanceof BN;
-  const isBBn = b instanceof BN;
-
-  if (isABn && isBBn) {
-    return a.eq(b);
-  } else if (isABn === isBBn) {
-    return undefined;
-  } else {
-    return false;
-  }
-}
-expect.addEqualityTesters([areBnEqual]);
-
-const createTokenAndMintTo = async (
-  connection: Connection,
-  payer: PublicKey,
-  tokenMint: PublicKey,
-  decimals: number,
-  mintAuthority: PublicKey,
-  mintTo: Array<{ recepient: PublicKey; amount: number }>
-): Promise<Array<TransactionInstruction>> => {
-  let minimumLamports = await getMinimumBalanceForRentExemptMint(connection);
-
-  let createTokeIxs = [
-    SystemProgram.createAccount({
-      fromPubkey: payer,
-      newAccountPubkey: tokenMint,
-      lamports: minimumLamports,
-      space: MINT_SIZE,
-      programId: TOKEN_PROGRAM,
-    }),
-    createInitializeMint2Instruction(
-      tokenMint,
-      decimals,
-      mintAuthority,
-      null,
-      TOKEN_PROGRAM
-    ),
-  ];
-
-  let mintToIxs = mintTo.flatMap(({ recepient, amount }) => {
-    const ataAddress = getAssociatedTokenAddressSync(
-      tokenMint,
-      recepient,
-      false,
-      TOKEN_PROGRAM
-    );
-
-    return [
-      createAssociatedTokenAccountIdempotentInstruction(
-        payer,
-        ataAddress,
-        recepient,
-        tokenMint,
-        TOKEN_PROGRAM
-      ),
-      createMintToInstruction(
-        tokenMint,
-        ataAddress,
-        mintAuthority,
-        amount,
-        [],
-        TOKEN_PROGRAM
-      ),
-    ];
-  });
-
-  return [...createTokeIxs, ...mintToIxs];
-};
+import {
+  TOKEN_PROGRAM,
+  areBnEqual,
+  createTokenAndMintTo,
+  ensureConfigInitialized,
+  getRandomBigNumber,
+  getTokenBalanceOn,
+} from "./helpers";
 
-const getTokenBalanceOn = (
-  connection: Connection,
-) => async (
-  tokenAccountAddress: PublicKey,
-): Promise<BN> => {
-  const tokenBalance = await connection.getTokenAccountBalance(tokenAccountAddress);
-  return new BN(tokenBalance.value.amount);
-};
+expect.addEqualityTesters([areBnEqual]);
 
 // Jest debug console it too verbose.
 // const jestConsole = console;
@@ -153,6 +66,8 @@ describe("escrow", () => {
   beforeAll(async () => {
     // global.console = require('console');
 
+    await ensureConfigInitialized(program);
+
     const giveAliceAndBobSolIxs: Array<TransactionInstruction> = [
       alice,
       bob,
@@ -220,7 +135,7 @@ describe("escrow", () => {
     vaultAddress: PublicKey;
   }> => {
     const transactionSignature = await program.methods
-      .makeOffer(offerId, offeredAmount, wantedAmount)
+      .makeOffer(offerId, offeredAmount, wantedAmount, [], null)
       .accounts({
         maker: maker.publicKey,
         tokenMintA: offeredTokenMint,
@@ -288,10 +203,13 @@ describe("escrow", () => {
     // >;
 
     const transactionSignature = await program.methods
-      .takeOffer()
+      .takeOffer(0)
       .accounts({
         taker: taker.publicKey,
         offer: offerAddress,
+        // Offers can accept several payment mints, so the one paid with is
+        // not implied by the offer.
+        tokenMintB: wifMint.publicKey,
         // See note in the `makeOfferTx` on why this program address is provided
         // and the rest are not.
         tokenProgram: TOKEN_PROGRAM,
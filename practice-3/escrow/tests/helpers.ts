@@ -0,0 +1,139 @@
+import { type Program, BN } from "@coral-xyz/anchor";
+import { Escrow } from "../target/types/escrow";
+import {
+  Connection,
+  PublicKey,
+  SystemProgram,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import {
+  MINT_SIZE,
+  TOKEN_2022_PROGRAM_ID,
+  type TOKEN_PROGRAM_ID,
+  createAssociatedTokenAccountIdempotentInstruction,
+  createInitializeMint2Instruction,
+  createMintToInstruction,
+  getAssociatedTokenAddressSync,
+  getMinimumBalanceForRentExemptMint,
+} from "@solana/spl-token";
+import { randomBytes } from "crypto";
+
+export const TOKEN_PROGRAM: typeof TOKEN_2022_PROGRAM_ID | typeof TOKEN_PROGRAM_ID =
+  TOKEN_2022_PROGRAM_ID;
+
+export const getRandomBigNumber = (size: number = 8) => {
+  return new BN(randomBytes(size));
+};
+
+export function areBnEqual(a: unknown, b: unknown): boolean | undefined {
+  const isABn = a instanceof BN;
+  const isBBn = b instanceof BN;
+
+  if (isABn && isBBn) {
+    return a.eq(b);
+  } else if (isABn === isBBn) {
+    return undefined;
+  } else {
+    return false;
+  }
+}
+
+export const createTokenAndMintTo = async (
+  connection: Connection,
+  payer: PublicKey,
+  tokenMint: PublicKey,
+  decimals: number,
+  mintAuthority: PublicKey,
+  mintTo: Array<{ recepient: PublicKey; amount: number }>
+): Promise<Array<TransactionInstruction>> => {
+  let minimumLamports = await getMinimumBalanceForRentExemptMint(connection);
+
+  let createTokeIxs = [
+    SystemProgram.createAccount({
+      fromPubkey: payer,
+      newAccountPubkey: tokenMint,
+      lamports: minimumLamports,
+      space: MINT_SIZE,
+      programId: TOKEN_PROGRAM,
+    }),
+    createInitializeMint2Instruction(
+      tokenMint,
+      decimals,
+      mintAuthority,
+      null,
+      TOKEN_PROGRAM
+    ),
+  ];
+
+  let mintToIxs = mintTo.flatMap(({ recepient, amount }) => {
+    const ataAddress = getAssociatedTokenAddressSync(
+      tokenMint,
+      recepient,
+      false,
+      TOKEN_PROGRAM
+    );
+
+    return [
+      createAssociatedTokenAccountIdempotentInstruction(
+        payer,
+        ataAddress,
+        recepient,
+        tokenMint,
+        TOKEN_PROGRAM
+      ),
+      createMintToInstruction(
+        tokenMint,
+        ataAddress,
+        mintAuthority,
+        amount,
+        [],
+        TOKEN_PROGRAM
+      ),
+    ];
+  });
+
+  return [...createTokeIxs, ...mintToIxs];
+};
+
+export const getTokenBalanceOn = (
+  connection: Connection,
+) => async (
+  tokenAccountAddress: PublicKey,
+): Promise<BN> => {
+  const tokenBalance = await connection.getTokenAccountBalance(tokenAccountAddress);
+  return new BN(tokenBalance.value.amount);
+};
+
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey(
+  "BPFLoaderUpgradeab1e11111111111111111111111"
+);
+
+export const getConfigAddress = (programId: PublicKey): PublicKey =>
+  PublicKey.findProgramAddressSync([Buffer.from("config")], programId)[0];
+
+// The config is a singleton shared by every test file, so whichever file runs
+// first creates it.  The provider wallet deployed the program, which makes it
+// the upgrade authority and therefore the admin.
+export const ensureConfigInitialized = async (
+  program: Program<Escrow>
+): Promise<PublicKey> => {
+  const configAddress = getConfigAddress(program.programId);
+  const connection = program.provider.connection;
+
+  if ((await connection.getAccountInfo(configAddress)) === null) {
+    const [programData, _programDataBump] = PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+    );
+
+    await program.methods
+      .initializeConfig()
+      .accounts({
+        admin: program.provider.publicKey,
+        programData,
+      })
+      .rpc();
+  }
+
+  return configAddress;
+};
@@ -0,0 +1,144 @@
+import { expect, describe, beforeAll, test } from "@jest/globals";
+import * as anchor from "@coral-xyz/anchor";
+import { type Program, BN } from "@coral-xyz/anchor";
+import { Escrow } from "../target/types/escrow";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+import { readFileSync } from "fs";
+
+import { confirmTransaction, makeKeypairs } from "@solana-developers/helpers";
+
+import { areBnEqual } from "./helpers";
+
+expect.addEqualityTesters([areBnEqual]);
+
+// `tests/fixtures/offer-v1.json` is loaded into the test validator by
+// `Anchor.toml`.  It holds an offer in the layout used before
+// `Offer::version` was added, made by the keypair in
+// `tests/fixtures/offer-v1-maker.json`.
+const V1_OFFER = new PublicKey("4EDrLG6mdgKaeqTtz9QxRrw9g2oio1zP1SApE7yikieq");
+const V1_OFFER_MAKER = Keypair.fromSecretKey(
+  Uint8Array.from(
+    JSON.parse(readFileSync("tests/fixtures/offer-v1-maker.json", "utf8"))
+  )
+);
+const V1_OFFER_LEN = 121;
+const CURRENT_OFFER_LEN = 319;
+const OFFER_VERSION = 2;
+
+describe("escrow offer migration", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const provider = anchor.getProvider();
+  const connection = provider.connection;
+
+  const program = anchor.workspace.Escrow as Program<Escrow>;
+
+  // Not the maker, so not allowed to pay for the migration.
+  const [carol] = makeKeypairs(1);
+
+  const migrateOfferTx = (payer: Keypair = V1_OFFER_MAKER) =>
+    program.methods
+      .migrateOffer()
+      .accounts({
+        payer: payer.publicKey,
+        offer: V1_OFFER,
+        maker: V1_OFFER_MAKER.publicKey,
+      })
+      .signers([payer])
+      .rpc();
+
+  beforeAll(async () => {
+    let tx = new Transaction();
+    tx.instructions = [carol, V1_OFFER_MAKER].map((keypair) =>
+      SystemProgram.transfer({
+        fromPubkey: provider.publicKey,
+        toPubkey: keypair.publicKey,
+        lamports: LAMPORTS_PER_SOL,
+      })
+    );
+    await provider.sendAndConfirm(tx);
+  });
+
+  test("A v1 offer can not be loaded before migration", async () => {
+    const accountInfo = await connection.getAccountInfo(V1_OFFER);
+    expect(accountInfo!.data.length).toEqual(V1_OFFER_LEN);
+
+    await expect(program.account.offer.fetch(V1_OFFER)).rejects.toThrow();
+  });
+
+  test("Only the maker can pay for the migration", async () => {
+    const offerLamportsBefore = (await connection.getAccountInfo(V1_OFFER))!
+      .lamports;
+    const carolLamportsBefore = await connection.getBalance(carol.publicKey);
+    const makerLamportsBefore = await connection.getBalance(
+      V1_OFFER_MAKER.publicKey
+    );
+
+    await expect(migrateOfferTx(carol)).rejects.toThrow(
+      /MigrationPayerNotMaker/
+    );
+
+    const accountInfo = (await connection.getAccountInfo(V1_OFFER))!;
+    expect(accountInfo.data.length).toEqual(V1_OFFER_LEN);
+    expect(accountInfo.lamports).toEqual(offerLamportsBefore);
+    expect(await connection.getBalance(carol.publicKey)).toEqual(
+      carolLamportsBefore
+    );
+    expect(await connection.getBalance(V1_OFFER_MAKER.publicKey)).toEqual(
+      makerLamportsBefore
+    );
+  });
+
+  test("Migration reallocs the offer and the maker covers the rent", async () => {
+    const offerLamportsBefore = (await connection.getAccountInfo(V1_OFFER))!
+      .lamports;
+    const makerLamportsBefore = await connection.getBalance(
+      V1_OFFER_MAKER.publicKey
+    );
+
+    const transactionSignature = await migrateOfferTx();
+    await confirmTransaction(connection, transactionSignature);
+
+    const transaction = await connection.getTransaction(transactionSignature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const fee = transaction!.meta!.fee;
+
+    const accountInfo = (await connection.getAccountInfo(V1_OFFER))!;
+    const rentExempt = await connection.getMinimumBalanceForRentExemption(
+      CURRENT_OFFER_LEN
+    );
+    expect(accountInfo.data.length).toEqual(CURRENT_OFFER_LEN);
+    expect(accountInfo.lamports).toEqual(rentExempt);
+
+    const rentDifference = rentExempt - offerLamportsBefore;
+    expect(rentDifference).toBeGreaterThan(0);
+    expect(await connection.getBalance(V1_OFFER_MAKER.publicKey)).toEqual(
+      makerLamportsBefore - rentDifference - fee
+    );
+
+    const offer = await program.account.offer.fetch(V1_OFFER);
+    expect(offer.version).toEqual(OFFER_VERSION);
+    expect(offer.id).toEqual(new BN(1));
+    expect(offer.maker).toEqual(V1_OFFER_MAKER.publicKey);
+    expect(offer.rentPayer).toEqual(V1_OFFER_MAKER.publicKey);
+    expect(offer.tokenMintA).toEqual(
+      new PublicKey("So11111111111111111111111111111111111111112")
+    );
+    expect(offer.tokenMintB).toEqual(
+      new PublicKey("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v")
+    );
+    expect(offer.tokenBWantedAmount).toEqual(new BN(25_000_000));
+  });
+
+  test("A migrated offer can not be migrated again", async () => {
+    await expect(migrateOfferTx()).rejects.toThrow(/OfferAlreadyMigrated/);
+  });
+});
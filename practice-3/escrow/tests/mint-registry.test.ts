@@ -0,0 +1,213 @@
+import { expect, describe, beforeAll, afterAll, test } from "@jest/globals";
+import * as anchor from "@coral-xyz/anchor";
+import { type Program, BN } from "@coral-xyz/anchor";
+import { Escrow } from "../target/types/escrow";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+
+import { confirmTransaction, makeKeypairs } from "@solana-developers/helpers";
+
+import {
+  TOKEN_PROGRAM,
+  areBnEqual,
+  createTokenAndMintTo,
+  ensureConfigInitialized,
+  getRandomBigNumber,
+} from "./helpers";
+import {
+  fetchMintAllowlist,
+  getMintRegistryAddress,
+  mintRegistryForMakeOffer,
+} from "../client/mint-registry";
+
+expect.addEqualityTesters([areBnEqual]);
+
+describe("escrow mint registry", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const provider = anchor.getProvider();
+  const connection = provider.connection;
+
+  const program = anchor.workspace.Escrow as Program<Escrow>;
+
+  const [alice, usdcMint, wifMint, scamMint] = makeKeypairs(4);
+
+  const mintRegistry = getMintRegistryAddress(program.programId);
+
+  const makeOfferTx = (
+    tokenMintA: PublicKey,
+    tokenMintB: PublicKey,
+    registry: PublicKey | null
+  ) =>
+    program.methods
+      .makeOffer(
+        getRandomBigNumber(),
+        new BN(1_000_000),
+        new BN(1_000_000),
+        [],
+        null
+      )
+      .accounts({
+        maker: alice.publicKey,
+        tokenMintA,
+        tokenMintB,
+        mintRegistry: registry,
+        tokenProgram: TOKEN_PROGRAM,
+      })
+      .signers([alice])
+      .rpc();
+
+  const addAllowedMint = (mint: PublicKey, admin?: Keypair) =>
+    program.methods
+      .addAllowedMint()
+      .accounts({
+        admin: admin?.publicKey ?? provider.publicKey,
+        mint,
+      })
+      .signers(admin ? [admin] : [])
+      .rpc();
+
+  const removeAllowedMint = (mint: PublicKey) =>
+    program.methods
+      .removeAllowedMint(mint)
+      .accounts({ admin: provider.publicKey })
+      .rpc();
+
+  const setAllowlistEnabled = (enabled: boolean) =>
+    program.methods
+      .setMintAllowlistEnabled(enabled)
+      .accounts({ admin: provider.publicKey })
+      .rpc();
+
+  beforeAll(async () => {
+    await ensureConfigInitialized(program);
+
+    if ((await connection.getAccountInfo(mintRegistry)) === null) {
+      await program.methods
+        .initializeMintRegistry()
+        .accounts({ admin: provider.publicKey })
+        .rpc();
+    }
+
+    const giveAliceSolIx = SystemProgram.transfer({
+      fromPubkey: provider.publicKey,
+      toPubkey: alice.publicKey,
+      lamports: 10 * LAMPORTS_PER_SOL,
+    });
+
+    const mintSetupIxs = await Promise.all(
+      [usdcMint, wifMint, scamMint].map((mint) =>
+        createTokenAndMintTo(
+          connection,
+          provider.publicKey,
+          mint.publicKey,
+          6,
+          alice.publicKey,
+          [{ recepient: alice.publicKey, amount: 100_000_000 }]
+        )
+      )
+    );
+
+    let tx = new Transaction();
+    tx.instructions = [giveAliceSolIx, ...mintSetupIxs.flat()];
+    await provider.sendAndConfirm(tx, [
+      alice,
+      usdcMint,
+      wifMint,
+      scamMint,
+    ]);
+  });
+
+  afterAll(async () => {
+    const { enabled, mints } = await fetchMintAllowlist(program);
+    if (enabled) {
+      await confirmTransaction(connection, await setAllowlistEnabled(false));
+    }
+    for (const mint of [usdcMint, wifMint].map((m) => m.publicKey)) {
+      if (mints.some((allowed) => allowed.equals(mint))) {
+        await confirmTransaction(connection, await removeAllowedMint(mint));
+      }
+    }
+  });
+
+  test("Only the admin can manage the registry", async () => {
+    await expect(addAllowedMint(usdcMint.publicKey, alice)).rejects.toThrow(
+      /Unauthorized/
+    );
+  });
+
+  test("Admin adds mints, and the client helper lists them", async () => {
+    await confirmTransaction(
+      connection,
+      await addAllowedMint(usdcMint.publicKey)
+    );
+    await confirmTransaction(connection, await addAllowedMint(wifMint.publicKey));
+
+    const { enabled, mints } = await fetchMintAllowlist(program);
+    expect(enabled).toBe(false);
+    expect(mints).toContainEqual(usdcMint.publicKey);
+    expect(mints).toContainEqual(wifMint.publicKey);
+    expect(mints).not.toContainEqual(scamMint.publicKey);
+
+    await expect(addAllowedMint(usdcMint.publicKey)).rejects.toThrow(
+      /MintAlreadyAllowed/
+    );
+  });
+
+  test("While disabled, offers in any mint are accepted", async () => {
+    const registry = await mintRegistryForMakeOffer(program);
+    expect(registry).toBeNull();
+
+    await confirmTransaction(
+      connection,
+      await makeOfferTx(scamMint.publicKey, usdcMint.publicKey, registry)
+    );
+  });
+
+  test("While enabled, make_offer checks both mints", async () => {
+    await confirmTransaction(connection, await setAllowlistEnabled(true));
+
+    const registry = await mintRegistryForMakeOffer(program);
+    expect(registry).toEqual(mintRegistry);
+
+    await confirmTransaction(
+      connection,
+      await makeOfferTx(usdcMint.publicKey, wifMint.publicKey, registry)
+    );
+
+    await expect(
+      makeOfferTx(scamMint.publicKey, wifMint.publicKey, registry)
+    ).rejects.toThrow(/MintNotAllowed/);
+    await expect(
+      makeOfferTx(usdcMint.publicKey, scamMint.publicKey, registry)
+    ).rejects.toThrow(/MintNotAllowed/);
+
+    // Leaving the registry out does not bypass the check.
+    await expect(
+      makeOfferTx(usdcMint.publicKey, wifMint.publicKey, null)
+    ).rejects.toThrow(/MintRegistryRequired/);
+  });
+
+  test("Removed mints are rejected again", async () => {
+    await confirmTransaction(
+      connection,
+      await removeAllowedMint(wifMint.publicKey)
+    );
+
+    const { mints } = await fetchMintAllowlist(program);
+    expect(mints).not.toContainEqual(wifMint.publicKey);
+
+    await expect(
+      makeOfferTx(usdcMint.publicKey, wifMint.publicKey, mintRegistry)
+    ).rejects.toThrow(/MintNotAllowed/);
+
+    await expect(removeAllowedMint(wifMint.publicKey)).rejects.toThrow(
+      /MintNotAllowed/
+    );
+  });
+});
@@ -0,0 +1,193 @@
+import { expect, describe, beforeAll, afterAll, test } from "@jest/globals";
+import * as anchor from "@coral-xyz/anchor";
+import { type Program, BN } from "@coral-xyz/anchor";
+import { Escrow } from "../target/types/escrow";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+
+import { confirmTransaction, makeKeypairs } from "@solana-developers/helpers";
+
+import {
+  TOKEN_PROGRAM,
+  areBnEqual,
+  createTokenAndMintTo,
+  ensureConfigInitialized,
+  getRandomBigNumber,
+  getTokenBalanceOn,
+} from "./helpers";
+
+expect.addEqualityTesters([areBnEqual]);
+
+describe("escrow pause", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const provider = anchor.getProvider();
+  const connection = provider.connection;
+
+  const program = anchor.workspace.Escrow as Program<Escrow>;
+
+  const [alice, bob, usdcMint, wifMint] = makeKeypairs(4);
+
+  const aliceUsdcAccount = getAssociatedTokenAddressSync(
+    usdcMint.publicKey,
+    alice.publicKey,
+    false,
+    TOKEN_PROGRAM
+  );
+
+  const getTokenBalance = getTokenBalanceOn(connection);
+
+  const eventParser = new anchor.EventParser(
+    program.programId,
+    new anchor.BorshCoder(program.idl)
+  );
+
+  const eventsOf = async (transactionSignature: string) => {
+    await confirmTransaction(connection, transactionSignature);
+    const transaction = await connection.getTransaction(transactionSignature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    return [...eventParser.parseLogs(transaction!.meta!.logMessages!)];
+  };
+
+  const setPaused = (paused: boolean, admin?: Keypair) =>
+    program.methods
+      .setPaused(paused)
+      .accounts({ admin: admin?.publicKey ?? provider.publicKey })
+      .signers(admin ? [admin] : [])
+      .rpc();
+
+  const makeOfferTx = async (offerId: BN) => {
+    const transactionSignature = await program.methods
+      .makeOffer(offerId, new BN(10_000_000), new BN(100_000_000), [], null)
+      .accounts({
+        maker: alice.publicKey,
+        tokenMintA: usdcMint.publicKey,
+        tokenMintB: wifMint.publicKey,
+        tokenProgram: TOKEN_PROGRAM,
+      })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, transactionSignature);
+
+    const [offerAddress, _offerBump] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("offer"),
+        alice.publicKey.toBuffer(),
+        offerId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    return offerAddress;
+  };
+
+  let openOffer: PublicKey;
+
+  beforeAll(async () => {
+    await ensureConfigInitialized(program);
+
+    const giveSolIxs = [alice, bob].map((owner) =>
+      SystemProgram.transfer({
+        fromPubkey: provider.publicKey,
+        toPubkey: owner.publicKey,
+        lamports: 10 * LAMPORTS_PER_SOL,
+      })
+    );
+
+    const usdcSetupIxs = await createTokenAndMintTo(
+      connection,
+      provider.publicKey,
+      usdcMint.publicKey,
+      6,
+      alice.publicKey,
+      [{ recepient: alice.publicKey, amount: 100_000_000 }]
+    );
+
+    const wifSetupIxs = await createTokenAndMintTo(
+      connection,
+      provider.publicKey,
+      wifMint.publicKey,
+      6,
+      bob.publicKey,
+      [{ recepient: bob.publicKey, amount: 300_000_000 }]
+    );
+
+    let tx = new Transaction();
+    tx.instructions = [...giveSolIxs, ...usdcSetupIxs, ...wifSetupIxs];
+    await provider.sendAndConfirm(tx, [alice, bob, usdcMint, wifMint]);
+
+    openOffer = await makeOfferTx(getRandomBigNumber());
+  });
+
+  afterAll(async () => {
+    // Leave the shared config usable for other test files even if a test
+    // failed half way.
+    const config = await program.account.config.fetch(
+      await ensureConfigInitialized(program)
+    );
+    if (config.paused) {
+      await confirmTransaction(connection, await setPaused(false));
+    }
+  });
+
+  test("Only the admin can pause", async () => {
+    await expect(setPaused(true, bob)).rejects.toThrow(/Unauthorized/);
+  });
+
+  test("Pausing blocks new offers and takes, and emits an event", async () => {
+    const events = await eventsOf(await setPaused(true));
+    expect(events.map((event) => event.name)).toEqual(["paused"]);
+    expect(events[0].data.admin).toEqual(provider.publicKey);
+
+    await expect(makeOfferTx(getRandomBigNumber())).rejects.toThrow(/Paused/);
+
+    await expect(
+      program.methods
+        .takeOffer(0)
+        .accounts({
+          taker: bob.publicKey,
+          offer: openOffer,
+          tokenMintB: wifMint.publicKey,
+          tokenProgram: TOKEN_PROGRAM,
+        })
+        .signers([bob])
+        .rpc()
+    ).rejects.toThrow(/Paused/);
+  });
+
+  test("Maker can still cancel and recover the vault while paused", async () => {
+    expect(await getTokenBalance(aliceUsdcAccount)).toEqual(new BN(90_000_000));
+
+    const transactionSignature = await program.methods
+      .cancelOffer()
+      .accounts({
+        maker: alice.publicKey,
+        offer: openOffer,
+        tokenProgram: TOKEN_PROGRAM,
+      })
+      .signers([alice])
+      .rpc();
+    await confirmTransaction(connection, transactionSignature);
+
+    expect(await getTokenBalance(aliceUsdcAccount)).toEqual(
+      new BN(100_000_000)
+    );
+    expect(await connection.getAccountInfo(openOffer)).toBeNull();
+  });
+
+  test("Unpausing emits an event and allows new offers again", async () => {
+    const events = await eventsOf(await setPaused(false));
+    expect(events.map((event) => event.name)).toEqual(["unpaused"]);
+
+    const offerAddress = await makeOfferTx(getRandomBigNumber());
+    const offerAccount = await program.account.offer.fetch(offerAddress);
+    expect(offerAccount.maker).toEqual(alice.publicKey);
+  });
+});
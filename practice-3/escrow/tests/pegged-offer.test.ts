@@ -0,0 +1,187 @@
+import { expect, describe, beforeAll, test } from "@jest/globals";
+import * as anchor from "@coral-xyz/anchor";
+import { type Program, BN } from "@coral-xyz/anchor";
+import { Escrow } from "../target/types/escrow";
+import { MockOracle } from "../target/types/mock_oracle";
+import {
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+import { makeKeypairs } from "@solana-developers/helpers";
+
+import {
+  TOKEN_PROGRAM,
+  areBnEqual,
+  createTokenAndMintTo,
+  ensureConfigInitialized,
+  getRandomBigNumber,
+  getTokenBalanceOn,
+} from "./helpers";
+
+expect.addEqualityTesters([areBnEqual]);
+
+describe("escrow pegged offers", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const provider = anchor.getProvider();
+  const connection = provider.connection;
+  const program = anchor.workspace.Escrow as Program<Escrow>;
+  const oracle = anchor.workspace.MockOracle as Program<MockOracle>;
+
+  const [alice, bob, usdcMint, wifMint, priceFeed] = makeKeypairs(5);
+
+  const getTokenBalance = getTokenBalanceOn(connection);
+  const ata = (mint: PublicKey, owner: PublicKey) =>
+    getAssociatedTokenAddressSync(mint, owner, true, TOKEN_PROGRAM);
+
+  // Alice sells 10 USDC for WIF at the feed price less 1%, but for no less
+  // than 10 WIF.
+  const pegTerms = {
+    spreadBps: -100,
+    maxStalenessSecs: 60,
+    maxConfidenceBps: 100,
+  };
+
+  beforeAll(async () => {
+    await ensureConfigInitialized(program);
+
+    const usdcSetupIxs = await createTokenAndMintTo(
+      connection,
+      provider.publicKey,
+      usdcMint.publicKey,
+      6,
+      alice.publicKey,
+      [{ recepient: alice.publicKey, amount: 100_000_000 }]
+    );
+    const wifSetupIxs = await createTokenAndMintTo(
+      connection,
+      provider.publicKey,
+      wifMint.publicKey,
+      6,
+      bob.publicKey,
+      [{ recepient: bob.publicKey, amount: 300_000_000 }]
+    );
+
+    const tx = new Transaction();
+    tx.instructions = [
+      ...[alice, bob].map((owner) =>
+        SystemProgram.transfer({
+          fromPubkey: provider.publicKey,
+          toPubkey: owner.publicKey,
+          lamports: 10 * LAMPORTS_PER_SOL,
+        })
+      ),
+      ...usdcSetupIxs,
+      ...wifSetupIxs,
+    ];
+    await provider.sendAndConfirm(tx, [alice, bob, usdcMint, wifMint]);
+
+    // 2.5 WIF per USDC.
+    await oracle.methods
+      .initializePriceFeed(new BN(250), new BN(0), -2)
+      .accounts({
+        authority: provider.publicKey,
+        priceFeed: priceFeed.publicKey,
+      })
+      .signers([priceFeed])
+      .rpc();
+  });
+
+  const makePeggedOffer = async () => {
+    const offerId = getRandomBigNumber();
+    await program.methods
+      .makeOffer(offerId, new BN(10_000_000), new BN(10_000_000), [], pegTerms)
+      .accounts({
+        maker: alice.publicKey,
+        tokenMintA: usdcMint.publicKey,
+        tokenMintB: wifMint.publicKey,
+        tokenProgram: TOKEN_PROGRAM,
+        priceFeed: priceFeed.publicKey,
+      })
+      .signers([alice])
+      .rpc();
+
+    const [offerAddress] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("offer"),
+        alice.publicKey.toBuffer(),
+        offerId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    return offerAddress;
+  };
+
+  const takeOffer = (offerAddress: PublicKey) =>
+    program.methods
+      .takeOffer(0)
+      .accounts({
+        taker: bob.publicKey,
+        offer: offerAddress,
+        tokenMintB: wifMint.publicKey,
+        tokenProgram: TOKEN_PROGRAM,
+        priceFeed: priceFeed.publicKey,
+      })
+      .signers([bob])
+      .rpc();
+
+  const setPrice = (price: number, publishTime: BN | null = null) =>
+    oracle.methods
+      .setPrice(new BN(price), new BN(0), publishTime)
+      .accounts({
+        authority: provider.publicKey,
+        priceFeed: priceFeed.publicKey,
+      })
+      .rpc();
+
+  test("Offer records the feed it is pegged to", async () => {
+    const offerAddress = await makePeggedOffer();
+
+    const offer = await program.account.offer.fetch(offerAddress);
+    expect(offer.peg?.priceFeed).toEqual(priceFeed.publicKey);
+    expect(offer.peg?.oracleProgram).toEqual(oracle.programId);
+    expect(offer.peg?.terms.spreadBps).toEqual(-100);
+  });
+
+  test("Bob pays the feed price at take time, less the spread", async () => {
+    const offerAddress = await makePeggedOffer();
+    const aliceWif = ata(wifMint.publicKey, alice.publicKey);
+    const before = (await connection.getAccountInfo(aliceWif))
+      ? await getTokenBalance(aliceWif)
+      : new BN(0);
+
+    // 3 WIF per USDC by the time Bob takes the offer.
+    await setPrice(300);
+    await takeOffer(offerAddress);
+
+    expect((await getTokenBalance(aliceWif)).sub(before)).toEqual(
+      new BN(29_700_000)
+    );
+    expect(
+      await getTokenBalance(ata(usdcMint.publicKey, bob.publicKey))
+    ).toEqual(new BN(10_000_000));
+  });
+
+  test("Offer can not be taken at a stale price", async () => {
+    const offerAddress = await makePeggedOffer();
+    await setPrice(300, new BN(Math.floor(Date.now() / 1000) - 3_600));
+
+    await expect(takeOffer(offerAddress)).rejects.toThrow(/StalePrice/);
+
+    await setPrice(300);
+    await takeOffer(offerAddress);
+  });
+
+  test("Offer can not be taken at a price published in the future", async () => {
+    const offerAddress = await makePeggedOffer();
+    await setPrice(300, new BN(Math.floor(Date.now() / 1000) + 3_600));
+
+    await expect(takeOffer(offerAddress)).rejects.toThrow(/PriceFromTheFuture/);
+
+    await setPrice(300);
+    await takeOffer(offerAddress);
+  });
+});
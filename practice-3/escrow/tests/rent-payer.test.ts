@@ -0,0 +1,147 @@
+import { expect, describe, beforeAll, test } from "@jest/globals";
+import * as anchor from "@coral-xyz/anchor";
+import { type Program, BN } from "@coral-xyz/anchor";
+import { Escrow } from "../target/types/escrow";
+import {
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+import { getAssociatedTokenAddressSync } from "@solana/spl-token";
+
+import { confirmTransaction, makeKeypairs } from "@solana-developers/helpers";
+
+import {
+  TOKEN_PROGRAM,
+  areBnEqual,
+  createTokenAndMintTo,
+  ensureConfigInitialized,
+  getRandomBigNumber,
+  getTokenBalanceOn,
+} from "./helpers";
+
+expect.addEqualityTesters([areBnEqual]);
+
+describe("escrow sponsored rent", () => {
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const provider = anchor.getProvider();
+  const connection = provider.connection;
+
+  const program = anchor.workspace.Escrow as Program<Escrow>;
+
+  // Carol is a relayer paying rent for Alice and Bob. Transaction fees are
+  // paid by the provider wallet, so only rent moves their balances.
+  const [alice, bob, carol, usdcMint, wifMint] = makeKeypairs(5);
+
+  const aliceWifAccount = getAssociatedTokenAddressSync(
+    wifMint.publicKey,
+    alice.publicKey,
+    false,
+    TOKEN_PROGRAM
+  );
+
+  const getTokenBalance = getTokenBalanceOn(connection);
+
+  const makeSponsoredOfferTx = async () => {
+    const offerId = getRandomBigNumber();
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .makeOffer(offerId, new BN(10_000_000), new BN(100_000_000), [], null)
+        .accounts({
+          maker: alice.publicKey,
+          tokenMintA: usdcMint.publicKey,
+          tokenMintB: wifMint.publicKey,
+          tokenProgram: TOKEN_PROGRAM,
+          rentPayer: carol.publicKey,
+        })
+        .signers([alice, carol])
+        .rpc()
+    );
+
+    const [offerAddress, _offerBump] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("offer"),
+        alice.publicKey.toBuffer(),
+        offerId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    );
+    return offerAddress;
+  };
+
+  beforeAll(async () => {
+    await ensureConfigInitialized(program);
+
+    const giveSolIxs = [alice, bob, carol].map((owner) =>
+      SystemProgram.transfer({
+        fromPubkey: provider.publicKey,
+        toPubkey: owner.publicKey,
+        lamports: 10 * LAMPORTS_PER_SOL,
+      })
+    );
+
+    const usdcSetupIxs = await createTokenAndMintTo(
+      connection,
+      provider.publicKey,
+      usdcMint.publicKey,
+      6,
+      alice.publicKey,
+      [{ recepient: alice.publicKey, amount: 100_000_000 }]
+    );
+
+    const wifSetupIxs = await createTokenAndMintTo(
+      connection,
+      provider.publicKey,
+      wifMint.publicKey,
+      6,
+      bob.publicKey,
+      [{ recepient: bob.publicKey, amount: 300_000_000 }]
+    );
+
+    let tx = new Transaction();
+    tx.instructions = [...giveSolIxs, ...usdcSetupIxs, ...wifSetupIxs];
+    await provider.sendAndConfirm(tx, [alice, bob, usdcMint, wifMint]);
+  });
+
+  test("Carol sponsors an offer and a take, and gets the offer rent back", async () => {
+    const [aliceLamports, bobLamports, carolLamports] = await Promise.all(
+      [alice, bob, carol].map((user) => connection.getBalance(user.publicKey))
+    );
+
+    const offer = await makeSponsoredOfferTx();
+    const offerAccount = await program.account.offer.fetch(offer);
+    expect(offerAccount.rentPayer).toEqual(carol.publicKey);
+    expect(await connection.getBalance(alice.publicKey)).toEqual(aliceLamports);
+
+    await confirmTransaction(
+      connection,
+      await program.methods
+        .takeOffer(0)
+        .accounts({
+          taker: bob.publicKey,
+          offer,
+          tokenMintB: wifMint.publicKey,
+          tokenProgram: TOKEN_PROGRAM,
+          rentPayer: carol.publicKey,
+          offerRentPayer: carol.publicKey,
+        })
+        .signers([bob, carol])
+        .rpc()
+    );
+
+    expect(await getTokenBalance(aliceWifAccount)).toEqual(new BN(100_000_000));
+    expect(await connection.getAccountInfo(offer)).toBeNull();
+    expect(await connection.getBalance(alice.publicKey)).toEqual(aliceLamports);
+    expect(await connection.getBalance(bob.publicKey)).toEqual(bobLamports);
+
+    // Carol keeps paying for the two token accounts the take created.
+    const tokenAccountRent =
+      await connection.getMinimumBalanceForRentExemption(165);
+    expect(await connection.getBalance(carol.publicKey)).toEqual(
+      carolLamports - 2 * tokenAccountRent
+    );
+  });
+});
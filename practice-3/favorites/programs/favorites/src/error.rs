@@ -6,4 +6,34 @@ pub enum ErrorCode {
     CustomError,
     #[msg("Nothing to update")]
     NothingToUpdate,
+    #[msg("Color must not be empty or only whitespace")]
+    ColorEmpty,
+    #[msg("Color must be at most 512 bytes of UTF-8")]
+    ColorTooLong,
+    #[msg("Profile label must be at most 32 bytes")]
+    LabelTooLong,
+    #[msg("Profile index is full, delete a profile first")]
+    TooManyProfiles,
+    #[msg("Delegation has expired")]
+    DelegationExpired,
+    #[msg("Delegation expiry must be in the future")]
+    ExpiryInThePast,
+    #[msg("Favorite food must be at most 64 bytes")]
+    FoodTooLong,
+    #[msg("At most 5 hobbies fit")]
+    TooManyHobbies,
+    #[msg("Each hobby must be at most 32 bytes")]
+    HobbyTooLong,
+    #[msg("Favorites account is in a layout this program does not know")]
+    UnknownFavoritesLayout,
+    #[msg("Favorites account is already in the current layout")]
+    FavoritesAlreadyMigrated,
+    #[msg("Hex colors must be # and six hex digits, like #ff8800")]
+    InvalidHexColor,
+    #[msg("RGB colors must be three numbers from 0 to 255, like rgb(255, 136, 0)")]
+    InvalidRgbColor,
+    #[msg("Color must be a CSS color name, a #RRGGBB hex code or an rgb(r, g, b) tuple")]
+    UnknownColorName,
+    #[msg("Global stats would count below zero")]
+    GlobalStatsUnderflow,
 }
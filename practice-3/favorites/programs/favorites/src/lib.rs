@@ -1,20 +1,84 @@
+// `#[program]` in anchor-lang 0.31.1 expands to an IDL instruction that still
+// calls `AccountInfo::realloc`. It is emitted at the crate root, where only a
+// crate-level attribute reaches it, so the handlers and resize_favorites
+// deny the lint again.
+#![allow(deprecated)]
+
+mod color;
 mod error;
 
+use crate::color::Color;
 use crate::error::ErrorCode;
 
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{
+    allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+};
 
 declare_id!("CnEp3Aj6zUyW4o9oiU4Yv6CLzuxz4eRBNGFBFRmBtFks");
 
 pub const ANCHOR_DISCRIMINATOR_SIZE: usize = 8;
 
+// The longest color we accept as input, in bytes rather than characters.
+// Colors are stored as 7 byte hex codes now, but the first versions of the
+// program stored any text up to this length, so Favorites still has room
+// for it.
+pub const MAX_COLOR_LENGTH: usize = 512;
+
+// Limits of the optional fields, in bytes like the color
+pub const MAX_FOOD_LENGTH: usize = 64;
+pub const MAX_HOBBIES: usize = 5;
+pub const MAX_HOBBY_LENGTH: usize = 32;
+
+// Layout of Favorites that set_favorites and every update write. Version 1
+// had only `number` and `color`, and no version byte.
+pub const FAVORITES_VERSION: u8 = 2;
+
+// Profile labels go into the PDA seeds, and a single seed holds at most 32
+// bytes. The empty label is the default profile, whose PDA is the same as
+// the one used before profiles existed. The label is the first argument of
+// every instruction on a profile, which changed the arguments of the ones
+// that came before profiles: see CHANGELOG.md.
+//
+// Every instruction checks the label on `user`, its first non-init account,
+// before any seed is derived from it. Anchor derives the addresses of `init`
+// accounts before that, so none of them is seeded with the label: the
+// handlers create Favorites PDAs themselves, and a FavoritesHistory is
+// seeded with the address of its Favorites instead.
+pub const MAX_LABEL_LENGTH: usize = 32;
+
+// How many profiles fit in a user's ProfileIndex
+pub const MAX_PROFILES: usize = 10;
+
+// How many past versions of a profile its FavoritesHistory keeps, and how
+// many bytes of each color. Longer colors are cut short in the history.
+pub const HISTORY_CAPACITY: usize = 32;
+pub const HISTORY_COLOR_LENGTH: usize = 63;
+
+// GlobalStats counts up to TRACKED_COLORS different colors by their hex
+// code, and numbers from 0 up to NUMBER_BUCKETS - 1, the last bucket also
+// counting every larger number. Both stay the same size however many
+// different colors and numbers people pick.
+pub const TRACKED_COLORS: usize = 32;
+pub const NUMBER_BUCKETS: usize = 32;
+
+// Length of a color's hex code, like "#663399"
+pub const HEX_COLOR_LENGTH: usize = 7;
+
 // Our Solana program!
+#[deny(deprecated)]
 #[program]
 pub mod favorites {
     use super::*;
 
-    // Our instruction handler! It sets the user's favorite number and color
-    pub fn set_favorites(context: Context<SetFavorites>, number: u64, color: String) -> Result<()> {
+    // Our instruction handler! It sets the favorite number and color of one
+    // of the user's profiles
+    pub fn set_favorites(
+        context: Context<SetFavorites>,
+        label: String,
+        number: u64,
+        color: String,
+    ) -> Result<()> {
         let user_public_key = context.accounts.user.key();
         msg!("Greetings from {}", context.program_id);
         msg!(
@@ -23,18 +87,41 @@ pub mod favorites {
             number,
             color
         );
+        let mut favorites = Favorites::new(number, Color::parse(&color)?.to_string());
 
-        context
-            .accounts
-            .favorites
-            .set_inner(Favorites { number, color });
-        Ok(())
+        let favorites_bump = context.bumps.favorites;
+        let accounts = context.accounts;
+        create_favorites(
+            accounts.favorites.as_ref(),
+            &accounts.user,
+            &accounts.system_program,
+            &label,
+            favorites_bump,
+            favorites.space(),
+        )?;
+        accounts.global_stats.add(&mut favorites);
+        favorites.try_serialize(&mut &mut accounts.favorites.try_borrow_mut_data()?[..])?;
+
+        record_history(&accounts.history, &favorites)?;
+        list_profile(
+            &mut accounts.profile_index,
+            &mut accounts.global_stats,
+            label,
+        )
     }
 
+    // Changes any of the fields of one of the user's profiles, keeping the
+    // ones left as None. Every argument must be sent, None included: see
+    // CHANGELOG.md. The account grows or shrinks to fit: the user pays the
+    // extra rent when it grows and gets the difference back when it shrinks.
     pub fn update_favorites(
         context: Context<UpdateFavorites>,
+        _label: String,
         number: Option<u64>,
         color: Option<String>,
+        food: Option<String>,
+        hobbies: Option<Vec<String>>,
+        token_mint: Option<Pubkey>,
     ) -> Result<()> {
         let user_public_key = context.accounts.user.key();
         msg!("Greetings from {}", context.program_id);
@@ -44,47 +131,545 @@ pub mod favorites {
             number,
             color
         );
-        if number.is_none() && color.is_none() {
+        if number.is_none()
+            && color.is_none()
+            && food.is_none()
+            && hobbies.is_none()
+            && token_mint.is_none()
+        {
             return Err(ErrorCode::NothingToUpdate.into());
         }
+        let color = color.map(|color| stored_color(&color)).transpose()?;
+        validate_optional_fields(&food, &hobbies)?;
 
-        let current_number = context.accounts.favorites.number;
-        let current_color = context.accounts.favorites.color.clone();
+        let accounts = context.accounts;
+        let mut favorites = load_favorites(&accounts.favorites)?;
+        accounts.global_stats.remove(&mut favorites)?;
+        apply_update(&mut favorites, number, color);
+        apply_optional_update(&mut favorites, food, hobbies, token_mint);
+        resize_favorites(
+            accounts.favorites.as_ref(),
+            accounts.user.as_ref(),
+            accounts.user.as_ref(),
+            &accounts.system_program,
+            favorites.space(),
+        )?;
+        accounts.global_stats.add(&mut favorites);
+        store(&accounts.favorites, &favorites)?;
+        record_history(&accounts.history, &favorites)
+    }
 
-        match (number, color) {
-            (number, color) if number.is_some() && color.is_some() => {
-                msg!("Both number and color are provided");
-                context
-                    .accounts
-                    .favorites
-                    .set_inner(Favorites { number: number.unwrap(), color: color.unwrap() });
-            }
-            (Some(num), None) => {
-                msg!("Only number is provided");
-                context
-                    .accounts
-                    .favorites
-                    .set_inner(Favorites {
-                        number: num,
-                        color: current_color,
-                    });
-            }
-            (None, Some(col)) => {
-                msg!("Only color is provided");
-                context
-                    .accounts
-                    .favorites
-                    .set_inner(Favorites {
-                        number: current_number,
-                        color: col,
-                    });
-            }
-            _ => {}
+    // Creates the user's Favorites PDA if it does not exist yet, and otherwise
+    // updates it like update_favorites does. A new account starts out with
+    // number 0 and no color, so creating one needs at least a color.
+    pub fn upsert_favorites(
+        context: Context<UpsertFavorites>,
+        label: String,
+        number: Option<u64>,
+        color: Option<String>,
+        food: Option<String>,
+        hobbies: Option<Vec<String>>,
+        token_mint: Option<Pubkey>,
+    ) -> Result<()> {
+        let user_public_key = context.accounts.user.key();
+        msg!("Greetings from {}", context.program_id);
+        msg!(
+            "User {}'s favorite number is {:?} and favorite color is: {:?}",
+            user_public_key,
+            number,
+            color
+        );
+        if number.is_none()
+            && color.is_none()
+            && food.is_none()
+            && hobbies.is_none()
+            && token_mint.is_none()
+        {
+            return Err(ErrorCode::NothingToUpdate.into());
         }
+        let color = color.map(|color| stored_color(&color)).transpose()?;
+        validate_optional_fields(&food, &hobbies)?;
+
+        let favorites_bump = context.bumps.favorites;
+        let accounts = context.accounts;
+        let is_new = accounts.favorites.data_is_empty();
+        let mut favorites = if is_new {
+            Favorites::new(0, String::new())
+        } else {
+            load_favorites(&accounts.favorites)?
+        };
+        accounts.global_stats.remove(&mut favorites)?;
+        apply_update(&mut favorites, number, color);
+        apply_optional_update(&mut favorites, food, hobbies, token_mint);
+        // Catches a new account that was only given a number
+        require!(!favorites.color.is_empty(), ErrorCode::ColorEmpty);
+
+        if is_new {
+            create_favorites(
+                accounts.favorites.as_ref(),
+                &accounts.user,
+                &accounts.system_program,
+                &label,
+                favorites_bump,
+                favorites.space(),
+            )?;
+        } else {
+            resize_favorites(
+                accounts.favorites.as_ref(),
+                accounts.user.as_ref(),
+                accounts.user.as_ref(),
+                &accounts.system_program,
+                favorites.space(),
+            )?;
+        }
+        accounts.global_stats.add(&mut favorites);
+        store(&accounts.favorites, &favorites)?;
+
+        record_history(&accounts.history, &favorites)?;
+        // Also lists profiles made before the index existed
+        list_profile(
+            &mut accounts.profile_index,
+            &mut accounts.global_stats,
+            label,
+        )
+    }
+
+    // Updates one of the user's profiles on their behalf, like
+    // update_favorites, signed by a wallet they added with add_delegate. The
+    // delegate pays the rent when the account grows, and the user gets it
+    // back when it shrinks, since they paid for it in the first place.
+    pub fn update_favorites_as_delegate(
+        context: Context<UpdateFavoritesAsDelegate>,
+        _label: String,
+        number: Option<u64>,
+        color: Option<String>,
+        food: Option<String>,
+        hobbies: Option<Vec<String>>,
+        token_mint: Option<Pubkey>,
+    ) -> Result<()> {
+        msg!("Greetings from {}", context.program_id);
+        msg!(
+            "Delegate {} sets user {}'s favorite number to {:?} and favorite color to: {:?}",
+            context.accounts.delegate.key(),
+            context.accounts.user.key(),
+            number,
+            color
+        );
+        if let Some(expires_at) = context.accounts.delegation.expires_at {
+            require!(
+                Clock::get()?.unix_timestamp < expires_at,
+                ErrorCode::DelegationExpired
+            );
+        }
+        if number.is_none()
+            && color.is_none()
+            && food.is_none()
+            && hobbies.is_none()
+            && token_mint.is_none()
+        {
+            return Err(ErrorCode::NothingToUpdate.into());
+        }
+        let color = color.map(|color| stored_color(&color)).transpose()?;
+        validate_optional_fields(&food, &hobbies)?;
+
+        let accounts = context.accounts;
+        let mut favorites = load_favorites(&accounts.favorites)?;
+        accounts.global_stats.remove(&mut favorites)?;
+        apply_update(&mut favorites, number, color);
+        apply_optional_update(&mut favorites, food, hobbies, token_mint);
+        resize_favorites(
+            accounts.favorites.as_ref(),
+            accounts.delegate.as_ref(),
+            accounts.user.as_ref(),
+            &accounts.system_program,
+            favorites.space(),
+        )?;
+        accounts.global_stats.add(&mut favorites);
+        store(&accounts.favorites, &favorites)?;
+        record_history(&accounts.history, &favorites)
+    }
+
+    // Lets `delegate` update all of the user's profiles, until `expires_at`
+    // (a unix timestamp) if given. Adding a delegate again replaces its
+    // expiry.
+    pub fn add_delegate(
+        context: Context<AddDelegate>,
+        delegate: Pubkey,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        msg!(
+            "User {} adds delegate {} until {:?}",
+            context.accounts.user.key(),
+            delegate,
+            expires_at
+        );
+        if let Some(expires_at) = expires_at {
+            require!(
+                Clock::get()?.unix_timestamp < expires_at,
+                ErrorCode::ExpiryInThePast
+            );
+        }
+
+        context
+            .accounts
+            .delegation
+            .set_inner(Delegation { expires_at });
         Ok(())
     }
+
+    // Takes a delegate's rights away and gives the user the rent back
+    pub fn remove_delegate(context: Context<RemoveDelegate>, delegate: Pubkey) -> Result<()> {
+        msg!(
+            "User {} removes delegate {}",
+            context.accounts.user.key(),
+            delegate
+        );
+        // The `close` constraint does the work once the handler returns.
+        Ok(())
+    }
+
+    // Closes one of the user's Favorites PDAs and its history, and gives
+    // their rent back to them. They can call set_favorites again afterwards
+    // to start over.
+    pub fn delete_favorites(context: Context<DeleteFavorites>, label: String) -> Result<()> {
+        msg!(
+            "Deleting the {:?} favorites of user {}",
+            label,
+            context.accounts.user.key()
+        );
+        let accounts = context.accounts;
+        let mut favorites = load_favorites(&accounts.favorites)?;
+        let mut global_stats = load_if_exists::<GlobalStats>(&accounts.global_stats)?;
+        if let Some(global_stats) = &mut global_stats {
+            global_stats.remove(&mut favorites)?;
+        }
+        if let Some(mut profile_index) = load_if_exists::<ProfileIndex>(&accounts.profile_index)? {
+            unlist_profile(&mut profile_index, global_stats.as_mut(), &label)?;
+            store(&accounts.profile_index, &profile_index)?;
+        }
+        if let Some(global_stats) = &global_stats {
+            store(&accounts.global_stats, global_stats)?;
+        }
+        close_if_exists::<FavoritesHistory>(&accounts.history, &accounts.user)?;
+        close(&accounts.favorites, &accounts.user)
+    }
+
+    // Rewrites a profile saved in an older layout into the current one, so
+    // the other instructions can load it. The user pays for any extra space
+    // and gets back the rent of any space freed.
+    pub fn migrate_favorites(context: Context<MigrateFavorites>, label: String) -> Result<()> {
+        let favorites_info = context.accounts.favorites.to_account_info();
+        let (favorites, old_len, already_current) = {
+            let data = favorites_info.try_borrow_data()?;
+            let already_current = Favorites::try_deserialize(&mut &data[..]).is_ok_and(|stored| {
+                stored.version == FAVORITES_VERSION && stored.space() == data.len()
+            });
+            (
+                Favorites::try_from_versioned(&data)?,
+                data.len(),
+                already_current,
+            )
+        };
+        require!(!already_current, ErrorCode::FavoritesAlreadyMigrated);
+
+        let new_len = favorites.space();
+        resize_favorites(
+            &favorites_info,
+            context.accounts.user.as_ref(),
+            context.accounts.user.as_ref(),
+            &context.accounts.system_program,
+            new_len,
+        )?;
+        let mut data = favorites_info.try_borrow_mut_data()?;
+        favorites.try_serialize(&mut &mut data[..])?;
+
+        msg!(
+            "Migrated the {:?} favorites of user {} from {} to {} bytes",
+            label,
+            context.accounts.user.key(),
+            old_len,
+            new_len
+        );
+        Ok(())
+    }
+
+    // Creates the GlobalStats singleton. Anyone can pay for it, once, and
+    // the instructions that change favorites need it to exist.
+    pub fn initialize_global_stats(context: Context<InitializeGlobalStats>) -> Result<()> {
+        msg!("Global stats created by {}", context.accounts.payer.key());
+        Ok(())
+    }
+}
+
+// The partial update shared by update_favorites and upsert_favorites: fields
+// left as None keep their current value.
+fn apply_update(favorites: &mut Favorites, number: Option<u64>, color: Option<String>) {
+    match (number, color) {
+        (number, color) if number.is_some() && color.is_some() => {
+            msg!("Both number and color are provided");
+            favorites.number = number.unwrap();
+            favorites.color = color.unwrap();
+        }
+        (Some(num), None) => {
+            msg!("Only number is provided");
+            favorites.number = num;
+        }
+        (None, Some(col)) => {
+            msg!("Only color is provided");
+            favorites.color = col;
+        }
+        _ => {}
+    }
+}
+
+// The same for the fields added in version 2 of the layout, which every
+// update saves the favorites in. To clear an optional field, pass an empty
+// food, an empty list of hobbies or the default Pubkey as the token mint.
+fn apply_optional_update(
+    favorites: &mut Favorites,
+    food: Option<String>,
+    hobbies: Option<Vec<String>>,
+    token_mint: Option<Pubkey>,
+) {
+    favorites.version = FAVORITES_VERSION;
+    match food {
+        Some(food) if food.is_empty() => favorites.food = None,
+        Some(food) => favorites.food = Some(food),
+        None => {}
+    }
+    if let Some(hobbies) = hobbies {
+        favorites.hobbies = hobbies;
+    }
+    match token_mint {
+        Some(token_mint) if token_mint == Pubkey::default() => favorites.token_mint = None,
+        Some(token_mint) => favorites.token_mint = Some(token_mint),
+        None => {}
+    }
+}
+
+// The color in the form it is stored in, or a named error if it is not one
+fn stored_color(color: &str) -> Result<String> {
+    Ok(Color::parse(color)?.to_string())
+}
+
+// Checked before writing, so bad values fail with named errors instead of an
+// opaque serialization error
+fn validate_optional_fields(food: &Option<String>, hobbies: &Option<Vec<String>>) -> Result<()> {
+    if let Some(food) = food {
+        require!(food.len() <= MAX_FOOD_LENGTH, ErrorCode::FoodTooLong);
+    }
+    if let Some(hobbies) = hobbies {
+        require!(hobbies.len() <= MAX_HOBBIES, ErrorCode::TooManyHobbies);
+        require!(
+            hobbies.iter().all(|hobby| hobby.len() <= MAX_HOBBY_LENGTH),
+            ErrorCode::HobbyTooLong
+        );
+    }
+    Ok(())
+}
+
+// Adds `label` to the user's ProfileIndex, counting the user in the global
+// stats if they were not counted yet
+fn list_profile(
+    profile_index: &mut ProfileIndex,
+    global_stats: &mut GlobalStats,
+    label: String,
+) -> Result<()> {
+    profile_index.add(label)?;
+    if !profile_index.counted {
+        global_stats.users += 1;
+        profile_index.counted = true;
+    }
+    Ok(())
+}
+
+// Takes `label` out of the user's ProfileIndex, and the user out of the
+// global stats, if there are any, if it was their last profile
+fn unlist_profile(
+    profile_index: &mut ProfileIndex,
+    global_stats: Option<&mut GlobalStats>,
+    label: &str,
+) -> Result<()> {
+    profile_index.remove(label);
+    if let Some(global_stats) = global_stats {
+        if profile_index.counted && profile_index.labels.is_empty() {
+            global_stats.users = decrement(global_stats.users)?;
+            profile_index.counted = false;
+        }
+    }
+    Ok(())
+}
+
+// Loads an account that profiles saved by older versions of the program may
+// be without, or None if it was never created
+fn load_if_exists<T: AccountDeserialize + Owner>(info: &AccountInfo) -> Result<Option<T>> {
+    if info.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(
+        *info.owner,
+        T::owner(),
+        anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram
+    );
+    Ok(Some(T::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+}
+
+// Loads favorites saved in any layout, which `Account<Favorites>` can not:
+// a first-layout account whose color is too long to leave room for the
+// fields added since does not read as the current layout.
+fn load_favorites(info: &AccountInfo) -> Result<Favorites> {
+    if info.data_is_empty() {
+        return Err(anchor_lang::error::ErrorCode::AccountNotInitialized.into());
+    }
+    require_keys_eq!(
+        *info.owner,
+        crate::ID,
+        anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram
+    );
+    Favorites::try_from_versioned(&info.try_borrow_data()?)
+}
+
+// Saves an account loaded with load_if_exists or load_favorites
+fn store<T: AccountSerialize>(info: &AccountInfo, account: &T) -> Result<()> {
+    account.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
+
+// Closes an account that profiles saved by older versions of the program may
+// be without, if it exists
+fn close_if_exists<T: Owner>(info: &AccountInfo, refund_to: &AccountInfo) -> Result<()> {
+    if info.data_is_empty() {
+        return Ok(());
+    }
+    require_keys_eq!(
+        *info.owner,
+        T::owner(),
+        anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram
+    );
+    close(info, refund_to)
+}
+
+// Closes an account of this program the way the `close` constraint does, and
+// gives its rent to `refund_to`
+fn close(info: &AccountInfo, refund_to: &AccountInfo) -> Result<()> {
+    **refund_to.try_borrow_mut_lamports()? += info.lamports();
+    **info.try_borrow_mut_lamports()? = 0;
+    info.assign(&System::id());
+    info.resize(0)?;
+    Ok(())
+}
+
+// Adds the profile as it is now to its history. The history may have just
+// been created by `init_if_needed`, which leaves a zero-copy account without
+// its discriminator until the instruction ends, so it needs load_init.
+fn record_history(history: &AccountLoader<FavoritesHistory>, favorites: &Favorites) -> Result<()> {
+    let is_new = history.as_ref().try_borrow_data()?[..ANCHOR_DISCRIMINATOR_SIZE]
+        .iter()
+        .all(|byte| *byte == 0);
+    let mut history = if is_new {
+        history.load_init()?
+    } else {
+        history.load_mut()?
+    };
+    history.push(favorites.number, &favorites.color, Clock::get()?.slot);
+    Ok(())
+}
+
+// Creates the Favorites PDA of `label` with `space` bytes, the way the `init`
+// constraint does, also when someone already sent lamports to its address
+fn create_favorites<'info>(
+    favorites_info: &AccountInfo<'info>,
+    user: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    label: &str,
+    bump: u8,
+    space: usize,
+) -> Result<()> {
+    let user_key = user.key();
+    let seeds: &[&[u8]] = &[b"favorites", user_key.as_ref(), label.as_bytes(), &[bump]];
+    let needed_rent = Rent::get()?.minimum_balance(space);
+    let current_rent = favorites_info.lamports();
+    if current_rent == 0 {
+        return create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                CreateAccount {
+                    from: user.to_account_info(),
+                    to: favorites_info.clone(),
+                },
+                &[seeds],
+            ),
+            needed_rent,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    if needed_rent > current_rent {
+        transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: user.to_account_info(),
+                    to: favorites_info.clone(),
+                },
+            ),
+            needed_rent - current_rent,
+        )?;
+    }
+    allocate(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            Allocate {
+                account_to_allocate: favorites_info.clone(),
+            },
+            &[seeds],
+        ),
+        space as u64,
+    )?;
+    assign(
+        CpiContext::new_with_signer(
+            system_program.to_account_info(),
+            Assign {
+                account_to_assign: favorites_info.clone(),
+            },
+            &[seeds],
+        ),
+        &crate::ID,
+    )
 }
 
+// Grows or shrinks the Favorites PDA to `space` bytes, the same way the
+// `realloc` constraint does: `payer` pays the rent for extra bytes and
+// `refund_to` gets back the rent of the bytes freed.
+#[deny(deprecated)]
+fn resize_favorites<'info>(
+    favorites_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    refund_to: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+) -> Result<()> {
+    let current_rent = favorites_info.lamports();
+    let needed_rent = Rent::get()?.minimum_balance(space);
+    if needed_rent > current_rent {
+        transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: payer.clone(),
+                    to: favorites_info.clone(),
+                },
+            ),
+            needed_rent - current_rent,
+        )?;
+    } else if needed_rent < current_rent {
+        let refund = current_rent - needed_rent;
+        **favorites_info.try_borrow_mut_lamports()? -= refund;
+        **refund_to.try_borrow_mut_lamports()? += refund;
+    }
+    favorites_info.resize(space)?;
+    Ok(())
+}
 
 // What we will put inside the Favorites PDA
 #[account]
@@ -92,39 +677,777 @@ pub mod favorites {
 pub struct Favorites {
     pub number: u64,
 
-    #[max_len(50)]
+    #[max_len(MAX_COLOR_LENGTH)]
     pub color: String,
+
+    // Comes after the fields of the first layout, which had no version, so
+    // the two layouts can be told apart. See try_from_versioned().
+    pub version: u8,
+
+    #[max_len(MAX_FOOD_LENGTH)]
+    pub food: Option<String>,
+
+    #[max_len(MAX_HOBBIES, MAX_HOBBY_LENGTH)]
+    pub hobbies: Vec<String>,
+
+    pub token_mint: Option<Pubkey>,
+
+    // Whether GlobalStats counts these favorites. Profiles saved before the
+    // stats existed, or in the first layout, are not counted until they are
+    // next written.
+    pub counted: bool,
+}
+
+impl Favorites {
+    pub fn new(number: u64, color: String) -> Self {
+        Self {
+            number,
+            color,
+            version: FAVORITES_VERSION,
+            food: None,
+            hobbies: Vec::new(),
+            token_mint: None,
+            counted: false,
+        }
+    }
+
+    // Account size needed to store these favorites, which only grows as
+    // large as INIT_SPACE when every field is as long as allowed
+    pub fn space(&self) -> usize {
+        ANCHOR_DISCRIMINATOR_SIZE
+            + 8
+            + 4
+            + self.color.len()
+            + 1
+            + 1
+            + self.food.as_ref().map_or(0, |food| 4 + food.len())
+            + 4
+            + self
+                .hobbies
+                .iter()
+                .map(|hobby| 4 + hobby.len())
+                .sum::<usize>()
+            + 1
+            + self.token_mint.map_or(0, |_| 32)
+            + 1
+    }
+
+    // Decodes favorites saved in any layout this program ever used,
+    // upgrading them to the current one. The first layout ends after the
+    // color, or is followed by zeros in accounts made before accounts were
+    // sized to fit, while the current one goes on with a non-zero version.
+    pub fn try_from_versioned(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() >= ANCHOR_DISCRIMINATOR_SIZE
+                && data[..ANCHOR_DISCRIMINATOR_SIZE] == *Favorites::DISCRIMINATOR,
+            ErrorCode::UnknownFavoritesLayout
+        );
+        let body = &data[ANCHOR_DISCRIMINATOR_SIZE..];
+
+        let mut rest = body;
+        let first = FavoritesV1::deserialize(&mut rest)?;
+        if rest.iter().all(|byte| *byte == 0) {
+            return Ok(first.upgrade());
+        }
+
+        let favorites = Favorites::deserialize(&mut &body[..])?;
+        require!(
+            favorites.version == FAVORITES_VERSION,
+            ErrorCode::UnknownFavoritesLayout
+        );
+        Ok(favorites)
+    }
+}
+
+// Favorites as first saved, before the version byte and the optional fields
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct FavoritesV1 {
+    pub number: u64,
+    pub color: String,
+}
+
+impl FavoritesV1 {
+    pub fn upgrade(self) -> Favorites {
+        Favorites::new(self.number, self.color)
+    }
+}
+
+// The labels of a user's profiles, so clients can list them without
+// scanning every account of the program
+#[account]
+#[derive(InitSpace)]
+pub struct ProfileIndex {
+    #[max_len(MAX_PROFILES, MAX_LABEL_LENGTH)]
+    pub labels: Vec<String>,
+
+    // Whether GlobalStats counts this user. Indexes made before the stats
+    // existed read it as false from the zeros after their labels.
+    pub counted: bool,
+}
+
+impl ProfileIndex {
+    pub fn add(&mut self, label: String) -> Result<()> {
+        if self.labels.contains(&label) {
+            return Ok(());
+        }
+        require!(self.labels.len() < MAX_PROFILES, ErrorCode::TooManyProfiles);
+        self.labels.push(label);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, label: &str) {
+        self.labels.retain(|existing| existing != label);
+    }
+}
+
+// Program-wide counters for leaderboards. Every change to a profile takes
+// its old values out and puts its new ones in, so the counts always match
+// the profiles that exist. Profiles and users remember whether they were
+// counted, so ones saved before the stats existed are never taken out, and
+// a count that would still go below zero is an error.
+#[account]
+#[derive(InitSpace)]
+pub struct GlobalStats {
+    // Users with at least one profile in their ProfileIndex
+    pub users: u64,
+    pub profiles: u64,
+    // Profiles per color, for up to TRACKED_COLORS colors. A color leaves
+    // the list when its count drops to zero. A new one only takes a free
+    // place while other_colors is zero, so no profile of a listed color is
+    // ever counted in other_colors, and each count is every profile with
+    // that color.
+    #[max_len(TRACKED_COLORS)]
+    pub color_counts: Vec<ColorCount>,
+    // Every other profile: colors that found the list full, and text saved
+    // in the first layout that is not a color
+    pub other_colors: u64,
+    // Profiles per number, in the bucket given by number_bucket()
+    pub number_counts: [u64; NUMBER_BUCKETS],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+pub struct ColorCount {
+    // The hex code the color is stored as
+    #[max_len(HEX_COLOR_LENGTH)]
+    pub color: String,
+    pub count: u64,
+}
+
+impl GlobalStats {
+    pub fn add(&mut self, favorites: &mut Favorites) {
+        self.profiles += 1;
+        self.add_color(&favorites.color);
+        self.number_counts[number_bucket(favorites.number)] += 1;
+        favorites.counted = true;
+    }
+
+    pub fn remove(&mut self, favorites: &mut Favorites) -> Result<()> {
+        if !favorites.counted {
+            return Ok(());
+        }
+        self.profiles = decrement(self.profiles)?;
+        self.remove_color(&favorites.color)?;
+        let number_count = &mut self.number_counts[number_bucket(favorites.number)];
+        *number_count = decrement(*number_count)?;
+        favorites.counted = false;
+        Ok(())
+    }
+
+    fn add_color(&mut self, color: &str) {
+        let Some(color) = hex_color(color) else {
+            self.other_colors += 1;
+            return;
+        };
+        if let Some(entry) = self
+            .color_counts
+            .iter_mut()
+            .find(|entry| entry.color == color)
+        {
+            entry.count += 1;
+        } else if self.color_counts.len() < TRACKED_COLORS && self.other_colors == 0 {
+            self.color_counts.push(ColorCount { color, count: 1 });
+        } else {
+            self.other_colors += 1;
+        }
+    }
+
+    // Takes the color out of its entry in the list, or out of other_colors
+    // if it has none, which is where add_color counted it
+    fn remove_color(&mut self, color: &str) -> Result<()> {
+        let position = hex_color(color).and_then(|color| {
+            self.color_counts
+                .iter()
+                .position(|entry| entry.color == color)
+        });
+        match position {
+            Some(position) => {
+                let count = decrement(self.color_counts[position].count)?;
+                if count == 0 {
+                    self.color_counts.swap_remove(position);
+                } else {
+                    self.color_counts[position].count = count;
+                }
+            }
+            None => self.other_colors = decrement(self.other_colors)?,
+        }
+        Ok(())
+    }
+}
+
+// The hex code of a stored color. Colors saved in the first layout may be in
+// any form, or not a color at all.
+fn hex_color(color: &str) -> Option<String> {
+    Color::parse(color).ok().map(|color| color.to_string())
+}
+
+fn decrement(count: u64) -> Result<u64> {
+    count
+        .checked_sub(1)
+        .ok_or_else(|| error!(ErrorCode::GlobalStatsUnderflow))
+}
+
+pub fn number_bucket(number: u64) -> usize {
+    number.min(NUMBER_BUCKETS as u64 - 1) as usize
+}
+
+// A wallet the user allows to update their favorites. Its address is made
+// from the user and the delegate, so it only exists while the delegation does.
+#[account]
+#[derive(InitSpace)]
+pub struct Delegation {
+    // Unix timestamp after which the delegate can no longer update, or None
+    // to never expire
+    pub expires_at: Option<i64>,
+}
+
+// The last HISTORY_CAPACITY versions of a profile, oldest overwritten first.
+// At a few kilobytes it is zero-copy, so writing one entry does not
+// deserialize and serialize all of them.
+#[account(zero_copy)]
+pub struct FavoritesHistory {
+    // How many entries were ever written. The next one goes to
+    // `count % HISTORY_CAPACITY`, which is also the oldest once the buffer
+    // has wrapped around.
+    pub count: u64,
+    pub entries: [HistoryEntry; HISTORY_CAPACITY],
+}
+
+#[zero_copy]
+pub struct HistoryEntry {
+    pub number: u64,
+    // The slot the change was made in
+    pub slot: u64,
+    pub color_length: u8,
+    pub color: [u8; HISTORY_COLOR_LENGTH],
+}
+
+impl FavoritesHistory {
+    pub const SPACE: usize = ANCHOR_DISCRIMINATOR_SIZE + std::mem::size_of::<FavoritesHistory>();
+
+    pub fn push(&mut self, number: u64, color: &str, slot: u64) {
+        // Cut long colors on a character boundary, so they stay valid UTF-8
+        let mut color_length = color.len().min(HISTORY_COLOR_LENGTH);
+        while !color.is_char_boundary(color_length) {
+            color_length -= 1;
+        }
+
+        let entry = &mut self.entries[(self.count % HISTORY_CAPACITY as u64) as usize];
+        entry.number = number;
+        entry.slot = slot;
+        entry.color_length = color_length as u8;
+        entry.color = [0; HISTORY_COLOR_LENGTH];
+        entry.color[..color_length].copy_from_slice(&color.as_bytes()[..color_length]);
+        self.count += 1;
+    }
 }
 
 // When people call the set_favorites instruction, they will need to provide the accounts that will
 // be modified. This keeps Solana fast!
 #[derive(Accounts)]
+#[instruction(label: String)]
 pub struct SetFavorites<'info> {
-    #[account(mut)]
+    #[account(mut, constraint = label.len() <= MAX_LABEL_LENGTH @ ErrorCode::LabelTooLong)]
     pub user: Signer<'info>,
 
+    /// CHECK: Created by set_favorites, which fails if it already exists.
+    /// The address is checked here.
     #[account(
-        init,
+        mut,
+        seeds = [b"favorites", user.key().as_ref(), label.as_bytes()],
+        bump,
+    )]
+    pub favorites: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = FavoritesHistory::SPACE,
+        seeds = [b"history", favorites.key().as_ref()],
+        bump,
+    )]
+    pub history: AccountLoader<'info, FavoritesHistory>,
+
+    #[account(
+        init_if_needed,
         payer = user,
-        space = ANCHOR_DISCRIMINATOR_SIZE + Favorites::INIT_SPACE,
-        seeds = [b"favorites", user.key().as_ref()],
+        space = ANCHOR_DISCRIMINATOR_SIZE + ProfileIndex::INIT_SPACE,
+        seeds = [b"profile_index", user.key().as_ref()],
+        bump,
+    )]
+    pub profile_index: Account<'info, ProfileIndex>,
+
+    #[account(
+        mut,
+        seeds = [b"global_stats"],
         bump,
     )]
-    pub favorites: Account<'info, Favorites>,
+    pub global_stats: Account<'info, GlobalStats>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
+#[instruction(label: String)]
 pub struct UpdateFavorites<'info> {
+    #[account(mut, constraint = label.len() <= MAX_LABEL_LENGTH @ ErrorCode::LabelTooLong)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Loaded by update_favorites in whichever layout it was saved
+    /// in, then resized once the new size is known. The address is checked
+    /// here, the owner and discriminator in update_favorites.
+    #[account(
+        mut,
+        seeds = [b"favorites", user.key().as_ref(), label.as_bytes()],
+        bump,
+    )]
+    pub favorites: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_stats"],
+        bump,
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = FavoritesHistory::SPACE,
+        seeds = [b"history", favorites.key().as_ref()],
+        bump,
+    )]
+    pub history: AccountLoader<'info, FavoritesHistory>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(label: String)]
+pub struct UpsertFavorites<'info> {
+    #[account(mut, constraint = label.len() <= MAX_LABEL_LENGTH @ ErrorCode::LabelTooLong)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Missing until upsert_favorites creates it at the size it
+    /// needs, which `init_if_needed` can not do for accounts that grow. The
+    /// address is checked here, the owner and discriminator in
+    /// upsert_favorites when it already exists.
+    #[account(
+        mut,
+        seeds = [b"favorites", user.key().as_ref(), label.as_bytes()],
+        bump,
+    )]
+    pub favorites: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = FavoritesHistory::SPACE,
+        seeds = [b"history", favorites.key().as_ref()],
+        bump,
+    )]
+    pub history: AccountLoader<'info, FavoritesHistory>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = ANCHOR_DISCRIMINATOR_SIZE + ProfileIndex::INIT_SPACE,
+        seeds = [b"profile_index", user.key().as_ref()],
+        bump,
+    )]
+    pub profile_index: Account<'info, ProfileIndex>,
+
+    #[account(
+        mut,
+        seeds = [b"global_stats"],
+        bump,
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+    pub system_program: Program<'info, System>,
+}
+
+// Like UpdateFavorites, but signed by a delegate instead of the user
+#[derive(Accounts)]
+#[instruction(label: String)]
+pub struct UpdateFavoritesAsDelegate<'info> {
+    #[account(mut)]
+    pub delegate: Signer<'info>,
+
+    /// CHECK: The owner of the favorites, who does not sign. Only used in
+    /// the seeds below, and to refund rent when the account shrinks.
+    #[account(mut, constraint = label.len() <= MAX_LABEL_LENGTH @ ErrorCode::LabelTooLong)]
+    pub user: UncheckedAccount<'info>,
+
+    // Only exists if the user added this delegate
+    #[account(
+        seeds = [b"delegate", user.key().as_ref(), delegate.key().as_ref()],
+        bump,
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    /// CHECK: Loaded like in UpdateFavorites, by
+    /// update_favorites_as_delegate.
+    #[account(
+        mut,
+        seeds = [b"favorites", user.key().as_ref(), label.as_bytes()],
+        bump,
+    )]
+    pub favorites: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"global_stats"],
+        bump,
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    #[account(
+        init_if_needed,
+        payer = delegate,
+        space = FavoritesHistory::SPACE,
+        seeds = [b"history", favorites.key().as_ref()],
+        bump,
+    )]
+    pub history: AccountLoader<'info, FavoritesHistory>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(delegate: Pubkey)]
+pub struct AddDelegate<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = ANCHOR_DISCRIMINATOR_SIZE + Delegation::INIT_SPACE,
+        seeds = [b"delegate", user.key().as_ref(), delegate.as_ref()],
+        bump,
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(delegate: Pubkey)]
+pub struct RemoveDelegate<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"favorites", user.key().as_ref()],
+        close = user,
+        seeds = [b"delegate", user.key().as_ref(), delegate.as_ref()],
+        bump,
+    )]
+    pub delegation: Account<'info, Delegation>,
+}
+
+#[derive(Accounts)]
+#[instruction(label: String)]
+pub struct DeleteFavorites<'info> {
+    #[account(mut, constraint = label.len() <= MAX_LABEL_LENGTH @ ErrorCode::LabelTooLong)]
+    pub user: Signer<'info>,
+
+    /// CHECK: Loaded and closed by delete_favorites in whichever layout it
+    /// was saved in. The address is checked here, the owner and
+    /// discriminator in delete_favorites.
+    #[account(
+        mut,
+        seeds = [b"favorites", user.key().as_ref(), label.as_bytes()],
+        bump,
+    )]
+    pub favorites: UncheckedAccount<'info>,
+
+    /// CHECK: Missing if all of the user's profiles were saved before
+    /// profile indexes existed. The address is checked here, the rest by
+    /// delete_favorites if it exists.
+    #[account(
+        mut,
+        seeds = [b"profile_index", user.key().as_ref()],
+        bump,
+    )]
+    pub profile_index: UncheckedAccount<'info>,
+
+    /// CHECK: Missing if nobody created the global stats yet. The address is
+    /// checked here, the rest by delete_favorites if it exists.
+    #[account(
+        mut,
+        seeds = [b"global_stats"],
+        bump,
+    )]
+    pub global_stats: UncheckedAccount<'info>,
+
+    /// CHECK: Missing if the profile was saved before histories existed. The
+    /// address is checked here, the rest by delete_favorites if it exists.
+    #[account(
+        mut,
+        seeds = [b"history", favorites.key().as_ref()],
+        bump,
+    )]
+    pub history: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(label: String)]
+pub struct MigrateFavorites<'info> {
+    #[account(mut, constraint = label.len() <= MAX_LABEL_LENGTH @ ErrorCode::LabelTooLong)]
+    pub user: Signer<'info>,
+
+    /// CHECK: `Account<Favorites>` can not decode old layouts. The owner and
+    /// address are checked here, the discriminator in migrate_favorites.
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"favorites", user.key().as_ref(), label.as_bytes()],
         bump,
     )]
-    pub favorites: Account<'info, Favorites>,
+    pub favorites: UncheckedAccount<'info>,
+
     pub system_program: Program<'info, System>,
 }
+
+#[derive(Accounts)]
+pub struct InitializeGlobalStats<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = ANCHOR_DISCRIMINATOR_SIZE + GlobalStats::INIT_SPACE,
+        seeds = [b"global_stats"],
+        bump,
+    )]
+    pub global_stats: Account<'info, GlobalStats>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account_data(body: impl AnchorSerialize, padding: usize) -> Vec<u8> {
+        let mut data = Favorites::DISCRIMINATOR.to_vec();
+        body.serialize(&mut data).unwrap();
+        data.resize(data.len() + padding, 0);
+        data
+    }
+
+    #[test]
+    fn reads_the_first_layout_with_or_without_padding() {
+        for padding in [0, 30] {
+            let data = account_data(
+                FavoritesV1 {
+                    number: 7,
+                    color: "red".to_string(),
+                },
+                padding,
+            );
+            let favorites = Favorites::try_from_versioned(&data).unwrap();
+            assert_eq!(favorites.number, 7);
+            assert_eq!(favorites.color, "red");
+            assert_eq!(favorites.version, FAVORITES_VERSION);
+            assert_eq!(favorites.food, None);
+            assert!(favorites.hobbies.is_empty());
+            assert_eq!(favorites.token_mint, None);
+        }
+    }
+
+    #[test]
+    fn reads_the_first_layout_when_its_color_fills_the_account() {
+        // The first layout was 50 bytes of color, padding included
+        let color = "x".repeat(50);
+        let data = account_data(
+            FavoritesV1 {
+                number: 7,
+                color: color.clone(),
+            },
+            0,
+        );
+        assert!(Favorites::try_deserialize(&mut &data[..]).is_err());
+
+        let favorites = Favorites::try_from_versioned(&data).unwrap();
+        assert_eq!(favorites.color, color);
+        assert!(!favorites.counted);
+    }
+
+    #[test]
+    fn reads_the_current_layout_as_is() {
+        let mut stored = Favorites::new(7, "red".to_string());
+        stored.food = Some("borscht".to_string());
+        stored.hobbies = vec!["chess".to_string(), "climbing".to_string()];
+        stored.token_mint = Some(Pubkey::new_unique());
+        let data = account_data(&stored, 0);
+        assert_eq!(data.len(), stored.space());
+
+        let favorites = Favorites::try_from_versioned(&data).unwrap();
+        assert_eq!(favorites.food, stored.food);
+        assert_eq!(favorites.hobbies, stored.hobbies);
+        assert_eq!(favorites.token_mint, stored.token_mint);
+    }
+
+    #[test]
+    fn rejects_unknown_layouts() {
+        let mut stored = Favorites::new(7, "red".to_string());
+        stored.version = FAVORITES_VERSION + 1;
+        assert!(Favorites::try_from_versioned(&account_data(&stored, 0)).is_err());
+        assert!(Favorites::try_from_versioned(&[0; 32]).is_err());
+    }
+
+    #[test]
+    fn updates_number_and_color_in_any_combination() {
+        let mut favorites = Favorites::new(7, "red".to_string());
+
+        apply_update(&mut favorites, Some(8), Some("blue".to_string()));
+        assert_eq!((favorites.number, favorites.color.as_str()), (8, "blue"));
+        apply_update(&mut favorites, Some(9), None);
+        assert_eq!((favorites.number, favorites.color.as_str()), (9, "blue"));
+        apply_update(&mut favorites, None, Some("green".to_string()));
+        assert_eq!((favorites.number, favorites.color.as_str()), (9, "green"));
+        apply_update(&mut favorites, None, None);
+        assert_eq!((favorites.number, favorites.color.as_str()), (9, "green"));
+    }
+
+    #[test]
+    fn updates_keep_fields_left_out_and_clear_empty_ones() {
+        let mut favorites = Favorites::new(7, "red".to_string());
+        favorites.food = Some("borscht".to_string());
+        favorites.token_mint = Some(Pubkey::new_unique());
+        let food = favorites.food.clone();
+
+        apply_optional_update(
+            &mut favorites,
+            None,
+            Some(vec!["chess".to_string()]),
+            Some(Pubkey::default()),
+        );
+        assert_eq!(favorites.number, 7);
+        assert_eq!(favorites.color, "red");
+        assert_eq!(favorites.food, food);
+        assert_eq!(favorites.hobbies, vec!["chess".to_string()]);
+        assert_eq!(favorites.token_mint, None);
+
+        apply_optional_update(&mut favorites, Some(String::new()), None, None);
+        assert_eq!(favorites.food, None);
+        assert_eq!(favorites.hobbies, vec!["chess".to_string()]);
+    }
+
+    fn empty_stats() -> GlobalStats {
+        GlobalStats {
+            users: 0,
+            profiles: 0,
+            color_counts: Vec::new(),
+            other_colors: 0,
+            number_counts: [0; NUMBER_BUCKETS],
+        }
+    }
+
+    #[test]
+    fn stats_only_take_out_favorites_they_counted() {
+        let mut stats = empty_stats();
+        let mut counted = Favorites::new(7, "#ff0000".to_string());
+        stats.add(&mut counted);
+        assert!(counted.counted);
+
+        // Saved before the stats existed, so never added
+        let mut uncounted = Favorites::new(7, "#ff0000".to_string());
+        stats.remove(&mut uncounted).unwrap();
+        assert_eq!(stats.profiles, 1);
+        assert_eq!(stats.number_counts[7], 1);
+
+        stats.remove(&mut counted).unwrap();
+        assert!(!counted.counted);
+        assert_eq!(stats.profiles, 0);
+        assert_eq!(stats.number_counts[7], 0);
+        assert!(stats.color_counts.is_empty());
+
+        // Counts out of step with the profiles are an error, not a zero
+        counted.counted = true;
+        assert!(stats.remove(&mut counted).is_err());
+    }
+
+    #[test]
+    fn stats_count_colors_by_hex_code_up_to_the_limit() {
+        let mut stats = empty_stats();
+        let mut red = Favorites::new(1, "#ff0000".to_string());
+        stats.add(&mut red);
+        // A color saved in the first layout counts as its hex code
+        let mut legacy_red = Favorites::new(2, "Red".to_string());
+        stats.add(&mut legacy_red);
+        assert_eq!(
+            stats.color_counts,
+            vec![ColorCount {
+                color: "#ff0000".to_string(),
+                count: 2,
+            }]
+        );
+
+        let mut blues: Vec<Favorites> = (1..TRACKED_COLORS as u8)
+            .map(|blue| Favorites::new(4, format!("#0000{:02x}", blue)))
+            .collect();
+        for favorites in &mut blues {
+            stats.add(favorites);
+        }
+        assert_eq!(stats.color_counts.len(), TRACKED_COLORS);
+        assert_eq!(stats.other_colors, 0);
+
+        // Once the list is full, new colors are counted as others
+        let mut green = Favorites::new(5, "#00ff00".to_string());
+        stats.add(&mut green);
+        let mut not_a_color = Favorites::new(3, "sky-ish".to_string());
+        stats.add(&mut not_a_color);
+        assert_eq!(stats.other_colors, 2);
+
+        // Emptied colors leave the list, but green can not take their place
+        // while another green is counted as an other
+        stats.remove(&mut red).unwrap();
+        stats.remove(&mut legacy_red).unwrap();
+        assert_eq!(stats.color_counts.len(), TRACKED_COLORS - 1);
+        let mut second_green = Favorites::new(5, "#00ff00".to_string());
+        stats.add(&mut second_green);
+        assert_eq!(stats.color_counts.len(), TRACKED_COLORS - 1);
+        assert_eq!(stats.other_colors, 3);
+
+        // Every profile comes out where it went in
+        for favorites in [&mut green, &mut second_green, &mut not_a_color] {
+            stats.remove(favorites).unwrap();
+        }
+        assert_eq!(stats.other_colors, 0);
+        let mut third_green = Favorites::new(5, "#00ff00".to_string());
+        stats.add(&mut third_green);
+        assert!(stats
+            .color_counts
+            .iter()
+            .any(|entry| entry.color == "#00ff00" && entry.count == 1));
+
+        for favorites in blues.iter_mut().chain([&mut third_green]) {
+            stats.remove(favorites).unwrap();
+        }
+        assert!(stats.color_counts.is_empty());
+        assert_eq!(stats.other_colors, 0);
+        assert_eq!(stats.profiles, 0);
+    }
+}
@@ -0,0 +1,29 @@
+import { IdlAccounts } from "@coral-xyz/anchor";
+import { Favorites } from "../target/types/favorites";
+
+export type FavoritesHistory = IdlAccounts<Favorites>["favoritesHistory"];
+
+export interface HistoryEntry {
+  number: number;
+  color: string;
+  slot: number;
+}
+
+// Decodes the history ring buffer, oldest entry first. `count` is how many
+// entries were ever written: until the buffer is full they sit at the start,
+// afterwards the oldest is the one that will be overwritten next.
+export const historyInOrder = (history: FavoritesHistory): Array<HistoryEntry> => {
+  const capacity = history.entries.length;
+  const count = history.count.toNumber();
+  const length = Math.min(count, capacity);
+  const oldest = count > capacity ? count % capacity : 0;
+
+  return Array.from({ length }, (_, index) => {
+    const entry = history.entries[(oldest + index) % capacity];
+    return {
+      number: entry.number.toNumber(),
+      color: Buffer.from(entry.color.slice(0, entry.colorLength)).toString("utf8"),
+      slot: entry.slot.toNumber(),
+    };
+  });
+};
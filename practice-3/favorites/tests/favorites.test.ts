@@ -1,14 +1,33 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program, web3 } from "@coral-xyz/anchor";
 import { Favorites } from "../target/types/favorites";
+import { readFileSync } from "fs";
 import { airdropIfRequired, getCustomErrorMessage } from "@solana-developers/helpers";
-import { expect, describe } from '@jest/globals';
+import { expect, describe, beforeAll } from '@jest/globals';
 import { systemProgramErrors } from "./system-program-errors";
+import { historyInOrder } from "./favorites-history";
+import { colorCountsByColor, numberBucket } from "./global-stats";
 
 describe("favorites", () => {
   // Configure the client to use the local cluster.
   anchor.setProvider(anchor.AnchorProvider.env());
 
+  // The empty label is the default profile. Its PDA is seeded with just
+  // "favorites" and the user, since an empty seed adds nothing.
+  const defaultProfile = "";
+
+  // Every instruction that changes favorites keeps the global stats up to
+  // date, so the stats account has to exist first.
+  beforeAll(async () => {
+    const program = anchor.workspace.Favorites as Program<Favorites>;
+    await program.methods
+        .initializeGlobalStats()
+        .accounts({
+          payer: anchor.getProvider().publicKey,
+        })
+        .rpc();
+  });
+
   it("Writes our favorites to the blockchain!", async () => {
     const user = web3.Keypair.generate();
     const program = anchor.workspace.Favorites as Program<Favorites>;
@@ -30,7 +49,7 @@ describe("favorites", () => {
     try {
       tx = await program.methods
           // Call the set_favorites instruction handler
-          .setFavorites(favoriteNumber, favoriteColor)
+          .setFavorites(defaultProfile, favoriteNumber, favoriteColor)
           .accounts({
             user: user.publicKey,
             // Note that both `favorites` and `system_program` are added
@@ -58,7 +77,8 @@ describe("favorites", () => {
 
     // And make sure it matches!
     const dataFromPda = await program.account.favorites.fetch(favoritesPda);
-    expect(dataFromPda.color).toEqual(favoriteColor);
+    // Colors are stored as hex codes, however we wrote them
+    expect(dataFromPda.color).toEqual("#ff0000");
     expect(dataFromPda.number.toNumber()).toEqual(favoriteNumber.toNumber());
 
   });
@@ -84,7 +104,7 @@ describe("favorites", () => {
     try {
       tx = await program.methods
           // Call the set_favorites instruction handler
-          .setFavorites(favoriteNumber, favoriteColor)
+          .setFavorites(defaultProfile, favoriteNumber, favoriteColor)
           .accounts({
             user: user.publicKey,
             // Note that both `favorites` and `system_program` are added
@@ -112,7 +132,7 @@ describe("favorites", () => {
 
     // And make sure it matches!
     const dataFromPda = await program.account.favorites.fetch(favoritesPda);
-    expect(dataFromPda.color).toEqual(favoriteColor);
+    expect(dataFromPda.color).toEqual("#ff0000");
     expect(dataFromPda.number.toNumber()).toEqual(favoriteNumber.toNumber());
 
     const upFavoriteNumber = new anchor.BN(57);
@@ -120,7 +140,7 @@ describe("favorites", () => {
     try {
       tx = await program.methods
             // Call the set_favorites instruction handler
-            .updateFavorites(upFavoriteNumber, upFavoriteColor)
+            .updateFavorites(defaultProfile, upFavoriteNumber, upFavoriteColor, null, null, null)
             .accounts({
                 user: user.publicKey,
                 // Note that both `favorites` and `system_program` are added
@@ -148,7 +168,7 @@ describe("favorites", () => {
 
     // And make sure it matches!
     const dataFromPda1 = await program.account.favorites.fetch(favoritesPda1);
-    expect(dataFromPda1.color).toEqual(upFavoriteColor);
+    expect(dataFromPda1.color).toEqual("#0000ff");
     expect(dataFromPda1.number.toNumber()).toEqual(upFavoriteNumber.toNumber());
   });
 
@@ -173,7 +193,7 @@ describe("favorites", () => {
     try {
       tx = await program.methods
           // Call the set_favorites instruction handler
-          .setFavorites(favoriteNumber, favoriteColor)
+          .setFavorites(defaultProfile, favoriteNumber, favoriteColor)
           .accounts({
             user: user.publicKey,
             // Note that both `favorites` and `system_program` are added
@@ -201,14 +221,14 @@ describe("favorites", () => {
 
     // And make sure it matches!
     const dataFromPda = await program.account.favorites.fetch(favoritesPda);
-    expect(dataFromPda.color).toEqual(favoriteColor);
+    expect(dataFromPda.color).toEqual("#0000ff");
     expect(dataFromPda.number.toNumber()).toEqual(favoriteNumber.toNumber());
 
     const upFavoriteNumber = new anchor.BN(109);
     try {
       tx = await program.methods
           // Call the set_favorites instruction handler
-          .updateFavorites(upFavoriteNumber, null)
+          .updateFavorites(defaultProfile, upFavoriteNumber, null, null, null, null)
           .accounts({
             user: user.publicKey,
             // Note that both `favorites` and `system_program` are added
@@ -236,7 +256,7 @@ describe("favorites", () => {
 
     // And make sure it matches!
     const dataFromPda1 = await program.account.favorites.fetch(favoritesPda1);
-    expect(dataFromPda1.color).toEqual(favoriteColor);
+    expect(dataFromPda1.color).toEqual("#0000ff");
     expect(dataFromPda1.number.toNumber()).toEqual(upFavoriteNumber.toNumber());
   });
 
@@ -262,7 +282,7 @@ describe("favorites", () => {
     try {
       tx = await program.methods
           // Call the set_favorites instruction handler
-          .setFavorites(favoriteNumber, favoriteColor)
+          .setFavorites(defaultProfile, favoriteNumber, favoriteColor)
           .accounts({
             user: user.publicKey,
             // Note that both `favorites` and `system_program` are added
@@ -290,14 +310,14 @@ describe("favorites", () => {
 
     // And make sure it matches!
     const dataFromPda = await program.account.favorites.fetch(favoritesPda);
-    expect(dataFromPda.color).toEqual(favoriteColor);
+    expect(dataFromPda.color).toEqual("#0000ff");
     expect(dataFromPda.number.toNumber()).toEqual(favoriteNumber.toNumber());
 
     const upFavoriteColor = "green";
     try {
       tx = await program.methods
           // Call the set_favorites instruction handler
-          .updateFavorites(null, upFavoriteColor)
+          .updateFavorites(defaultProfile, null, upFavoriteColor, null, null, null)
           .accounts({
             user: user.publicKey,
             // Note that both `favorites` and `system_program` are added
@@ -325,7 +345,901 @@ describe("favorites", () => {
 
     // And make sure it matches!
     const dataFromPda1 = await program.account.favorites.fetch(favoritesPda1);
-    expect(dataFromPda1.color).toEqual(upFavoriteColor);
+    expect(dataFromPda1.color).toEqual("#008000");
     expect(dataFromPda1.number.toNumber()).toEqual(favoriteNumber.toNumber());
   });
+
+  it("Delete our favorites and write them again!", async () => {
+    const user = web3.Keypair.generate();
+    const program = anchor.workspace.Favorites as Program<Favorites>;
+    const connection = anchor.getProvider().connection;
+
+    console.log(`User public key: ${user.publicKey}`);
+
+    await airdropIfRequired(
+        connection,
+        user.publicKey,
+        0.5 * web3.LAMPORTS_PER_SOL,
+        0.5 * web3.LAMPORTS_PER_SOL
+    );
+
+    const [favoritesPda, _favoritesBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("favorites"), user.publicKey.toBuffer()],
+        program.programId
+    );
+
+    const setFavorites = async (number: number, color: string) => {
+      try {
+        return await program.methods
+            .setFavorites(defaultProfile, new anchor.BN(number), color)
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc({ commitment: "confirmed" });
+      } catch (thrownObject) {
+        const rawError = thrownObject as Error;
+        throw new Error(getCustomErrorMessage(systemProgramErrors, rawError.message));
+      }
+    };
+
+    const [historyPda, _historyBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("history"), favoritesPda.toBuffer()],
+        program.programId
+    );
+
+    await setFavorites(23, "red");
+    const rent =
+        (await connection.getBalance(favoritesPda, "confirmed")) +
+        (await connection.getBalance(historyPda, "confirmed"));
+    const balanceBeforeDelete = await connection.getBalance(user.publicKey, "confirmed");
+
+    const tx = await program.methods
+        .deleteFavorites(defaultProfile)
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+
+    console.log(`Tx signature: ${tx}`);
+
+    // The account and its history are gone, and their rent is back with the
+    // user, less the transaction fee they paid.
+    expect(await connection.getAccountInfo(favoritesPda, "confirmed")).toBeNull();
+    expect(await connection.getAccountInfo(historyPda, "confirmed")).toBeNull();
+    const transaction = await connection.getTransaction(tx, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    expect(await connection.getBalance(user.publicKey, "confirmed")).toEqual(
+        balanceBeforeDelete + rent - transaction!.meta!.fee
+    );
+
+    // And the same address can hold favorites again.
+    await setFavorites(57, "blue");
+    const dataFromPda = await program.account.favorites.fetch(favoritesPda, "confirmed");
+    expect(dataFromPda.color).toEqual("#0000ff");
+    expect(dataFromPda.number.toNumber()).toEqual(57);
+  });
+
+  it("Reads color names, hex codes and RGB tuples!", async () => {
+    const user = web3.Keypair.generate();
+    const program = anchor.workspace.Favorites as Program<Favorites>;
+
+    await airdropIfRequired(
+        anchor.getProvider().connection,
+        user.publicKey,
+        0.5 * web3.LAMPORTS_PER_SOL,
+        0.5 * web3.LAMPORTS_PER_SOL
+    );
+
+    const setFavorites = (color: string) =>
+        program.methods
+            .setFavorites(defaultProfile, new anchor.BN(7), color)
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+    const updateColor = (color: string) =>
+        program.methods
+            .updateFavorites(defaultProfile, null, color, null, null, null)
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+
+    const [favoritesPda, _favoritesBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("favorites"), user.publicKey.toBuffer()],
+        program.programId
+    );
+    const storedColor = async () => (await program.account.favorites.fetch(favoritesPda)).color;
+
+    // Input is still limited by its length in bytes, before it is even read:
+    // 257 Cyrillic letters take 514 bytes.
+    const tooLong = "ж".repeat(257);
+    expect(Buffer.byteLength(tooLong)).toEqual(514);
+
+    await expect(setFavorites(tooLong)).rejects.toThrow("ColorTooLong");
+    await expect(setFavorites("")).rejects.toThrow("ColorEmpty");
+    await expect(setFavorites(" \t ")).rejects.toThrow("ColorEmpty");
+    await expect(setFavorites("жовто-блакитний")).rejects.toThrow("UnknownColorName");
+    await expect(setFavorites("#12345")).rejects.toThrow("InvalidHexColor");
+    await expect(setFavorites("#gg0000")).rejects.toThrow("InvalidHexColor");
+    await expect(setFavorites("rgb(256, 0, 0)")).rejects.toThrow("InvalidRgbColor");
+    await expect(setFavorites("(1, 2)")).rejects.toThrow("InvalidRgbColor");
+
+    // CSS color names, in any case
+    await setFavorites("RebeccaPurple");
+    expect(await storedColor()).toEqual("#663399");
+
+    // Hex codes
+    await updateColor(" #FF8800 ");
+    expect(await storedColor()).toEqual("#ff8800");
+
+    // RGB tuples, with or without the rgb
+    await updateColor("rgb(255, 170, 0)");
+    expect(await storedColor()).toEqual("#ffaa00");
+    await updateColor("(0,0,0)");
+    expect(await storedColor()).toEqual("#000000");
+
+    // A bad color leaves the stored one alone
+    await expect(updateColor("ultramarine")).rejects.toThrow("UnknownColorName");
+    await expect(updateColor("  ")).rejects.toThrow("ColorEmpty");
+    expect(await storedColor()).toEqual("#000000");
+  });
+
+  it("Resizes our favorites to fit the food!", async () => {
+    const user = web3.Keypair.generate();
+    const program = anchor.workspace.Favorites as Program<Favorites>;
+    const connection = anchor.getProvider().connection;
+
+    await airdropIfRequired(
+        connection,
+        user.publicKey,
+        0.5 * web3.LAMPORTS_PER_SOL,
+        0.5 * web3.LAMPORTS_PER_SOL
+    );
+
+    const [favoritesPda, _favoritesBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("favorites"), user.publicKey.toBuffer()],
+        program.programId
+    );
+    const [profileIndexPda, _profileIndexBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("profile_index"), user.publicKey.toBuffer()],
+        program.programId
+    );
+    // Seeded with the address of the favorites it belongs to
+    const [historyPda, _historyBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("history"), favoritesPda.toBuffer()],
+        program.programId
+    );
+    // Discriminator, number, the 7 byte color code with its 4 byte length
+    // prefix, the version, the food if any, no hobbies and no token mint
+    const spaceFor = (food: string) =>
+        8 + 8 + 4 + 7 + 1 + (food ? 1 + 4 + Buffer.byteLength(food) : 1) + 4 + 1;
+    const updateFood = (food: string) =>
+        program.methods
+            .updateFavorites(defaultProfile, null, null, food, null, null)
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+
+    // The provider wallet pays the transaction fees, so the user's balance
+    // only moves by the rent of the favorites account, the profile index and
+    // the history.
+    const userBefore = await connection.getBalance(user.publicKey);
+    await program.methods
+        .setFavorites(defaultProfile, new anchor.BN(7), "red")
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+
+    const indexRent = (await connection.getAccountInfo(profileIndexPda)).lamports;
+    const historyRent = (await connection.getAccountInfo(historyPda)).lamports;
+    let account = await connection.getAccountInfo(favoritesPda);
+    expect(account.data.length).toEqual(spaceFor(""));
+    const smallRent = await connection.getMinimumBalanceForRentExemption(spaceFor(""));
+    expect(account.lamports).toEqual(smallRent);
+    expect(await connection.getBalance(user.publicKey)).toEqual(userBefore - indexRent - historyRent - smallRent);
+
+    // Growing: the user pays the extra rent
+    const longFood = "slow roasted aubergine with pomegranate and mint";
+    await updateFood(longFood);
+
+    account = await connection.getAccountInfo(favoritesPda);
+    expect(account.data.length).toEqual(spaceFor(longFood));
+    const largeRent = await connection.getMinimumBalanceForRentExemption(spaceFor(longFood));
+    expect(account.lamports).toEqual(largeRent);
+    expect(await connection.getBalance(user.publicKey)).toEqual(userBefore - indexRent - historyRent - largeRent);
+
+    // Shrinking: the user gets the difference back
+    await updateFood("tea");
+
+    account = await connection.getAccountInfo(favoritesPda);
+    expect(account.data.length).toEqual(spaceFor("tea"));
+    const shrunkRent = await connection.getMinimumBalanceForRentExemption(spaceFor("tea"));
+    expect(account.lamports).toEqual(shrunkRent);
+    expect(await connection.getBalance(user.publicKey)).toEqual(userBefore - indexRent - historyRent - shrunkRent);
+
+    // Updating only the number keeps the size as it is
+    await program.methods
+        .updateFavorites(defaultProfile, new anchor.BN(8), null, null, null, null)
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    const dataFromPda = await program.account.favorites.fetch(favoritesPda);
+    expect(dataFromPda.food).toEqual("tea");
+    expect(dataFromPda.number.toNumber()).toEqual(8);
+    account = await connection.getAccountInfo(favoritesPda);
+    expect(account.data.length).toEqual(spaceFor("tea"));
+  });
+
+  it("Upserts our favorites whether or not they exist yet!", async () => {
+    const user = web3.Keypair.generate();
+    const program = anchor.workspace.Favorites as Program<Favorites>;
+    const connection = anchor.getProvider().connection;
+
+    await airdropIfRequired(
+        connection,
+        user.publicKey,
+        0.5 * web3.LAMPORTS_PER_SOL,
+        0.5 * web3.LAMPORTS_PER_SOL
+    );
+
+    const [favoritesPda, _favoritesBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("favorites"), user.publicKey.toBuffer()],
+        program.programId
+    );
+    const spaceFor = (food: string) =>
+        8 + 8 + 4 + 7 + 1 + (food ? 1 + 4 + Buffer.byteLength(food) : 1) + 4 + 1;
+    const upsertFavorites = (number: anchor.BN | null, color: string | null, food: string | null = null) =>
+        program.methods
+            .upsertFavorites(defaultProfile, number, color, food, null, null)
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+
+    // A new account needs a color, since it starts out without one
+    await expect(upsertFavorites(new anchor.BN(7), null)).rejects.toThrow("ColorEmpty");
+    await expect(upsertFavorites(null, null)).rejects.toThrow("NothingToUpdate");
+    expect(await connection.getAccountInfo(favoritesPda)).toBeNull();
+
+    // Missing: the account is created
+    await upsertFavorites(new anchor.BN(7), "green");
+    let dataFromPda = await program.account.favorites.fetch(favoritesPda);
+    expect(dataFromPda.number.toNumber()).toEqual(7);
+    expect(dataFromPda.color).toEqual("#008000");
+
+    // Present: only the fields we pass change
+    await upsertFavorites(new anchor.BN(8), null);
+    dataFromPda = await program.account.favorites.fetch(favoritesPda);
+    expect(dataFromPda.number.toNumber()).toEqual(8);
+    expect(dataFromPda.color).toEqual("#008000");
+
+    // And the account grows and shrinks with the food, like update_favorites
+    const userBefore = await connection.getBalance(user.publicKey);
+    const plainRent = await connection.getMinimumBalanceForRentExemption(spaceFor(""));
+    const longFood = "a very particular kind of sea salt caramel";
+    await upsertFavorites(null, null, longFood);
+    dataFromPda = await program.account.favorites.fetch(favoritesPda);
+    expect(dataFromPda.number.toNumber()).toEqual(8);
+    expect(dataFromPda.food).toEqual(longFood);
+    let account = await connection.getAccountInfo(favoritesPda);
+    expect(account.data.length).toEqual(spaceFor(longFood));
+    const longRent = await connection.getMinimumBalanceForRentExemption(spaceFor(longFood));
+    expect(account.lamports).toEqual(longRent);
+    expect(await connection.getBalance(user.publicKey)).toEqual(userBefore - (longRent - plainRent));
+
+    await upsertFavorites(null, "teal", "tea");
+    account = await connection.getAccountInfo(favoritesPda);
+    expect(account.data.length).toEqual(spaceFor("tea"));
+    const teaRent = await connection.getMinimumBalanceForRentExemption(spaceFor("tea"));
+    expect(account.lamports).toEqual(teaRent);
+    expect(await connection.getBalance(user.publicKey)).toEqual(userBefore - (teaRent - plainRent));
+
+    // update_favorites still works on an account made by upsert_favorites
+    await program.methods
+        .updateFavorites(defaultProfile, new anchor.BN(9), null, null, null, null)
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    dataFromPda = await program.account.favorites.fetch(favoritesPda);
+    expect(dataFromPda.number.toNumber()).toEqual(9);
+    expect(dataFromPda.color).toEqual("#008080");
+
+    // And upsert_favorites updates a profile made by set_favorites, however
+    // much it has grown since
+    const [workPda, _workBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("favorites"), user.publicKey.toBuffer(), Buffer.from("work")],
+        program.programId
+    );
+    await program.methods
+        .setFavorites("work", new anchor.BN(1), "red")
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    await program.methods
+        .updateFavorites("work", null, null, longFood, null, null)
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    await program.methods
+        .upsertFavorites("work", new anchor.BN(2), null, null, null, null)
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    const work = await program.account.favorites.fetch(workPda);
+    expect(work.number.toNumber()).toEqual(2);
+    expect(work.color).toEqual("#ff0000");
+    expect(work.food).toEqual(longFood);
+  });
+
+  it("Keeps several profiles and lists them in the index!", async () => {
+    const user = web3.Keypair.generate();
+    const program = anchor.workspace.Favorites as Program<Favorites>;
+
+    await airdropIfRequired(
+        anchor.getProvider().connection,
+        user.publicKey,
+        0.5 * web3.LAMPORTS_PER_SOL,
+        0.5 * web3.LAMPORTS_PER_SOL
+    );
+
+    const profilePda = (label: string) => web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("favorites"), user.publicKey.toBuffer(), Buffer.from(label)],
+        program.programId
+    )[0];
+    const [profileIndexPda, _profileIndexBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("profile_index"), user.publicKey.toBuffer()],
+        program.programId
+    );
+    const setFavorites = (label: string, number: number, color: string) =>
+        program.methods
+            .setFavorites(label, new anchor.BN(number), color)
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+
+    await setFavorites("work", 9, "grey");
+    await setFavorites("gaming", 42, "lime");
+    await program.methods
+        .upsertFavorites(defaultProfile, new anchor.BN(1), "white", null, null, null)
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+
+    // Each profile lives in its own PDA
+    const work = await program.account.favorites.fetch(profilePda("work"));
+    expect(work.number.toNumber()).toEqual(9);
+    expect(work.color).toEqual("#808080");
+    const gaming = await program.account.favorites.fetch(profilePda("gaming"));
+    expect(gaming.number.toNumber()).toEqual(42);
+    expect(gaming.color).toEqual("#00ff00");
+
+    // Updating one profile leaves the others alone
+    await program.methods
+        .updateFavorites("work", null, "dimgray", null, null, null)
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    expect((await program.account.favorites.fetch(profilePda("work"))).color).toEqual("#696969");
+    expect((await program.account.favorites.fetch(profilePda("gaming"))).color).toEqual("#00ff00");
+
+    let profileIndex = await program.account.profileIndex.fetch(profileIndexPda);
+    expect(profileIndex.labels).toEqual(["work", "gaming", defaultProfile]);
+
+    // Deleting a profile takes it out of the index
+    await program.methods
+        .deleteFavorites("gaming")
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    expect(await anchor.getProvider().connection.getAccountInfo(profilePda("gaming"))).toBeNull();
+    profileIndex = await program.account.profileIndex.fetch(profileIndexPda);
+    expect(profileIndex.labels).toEqual(["work", defaultProfile]);
+
+    // Labels are seeds, so they are limited to 32 bytes, and every
+    // instruction says so before deriving an address from one. The client
+    // can not derive the address either, so we pass one.
+    const longLabel = "x".repeat(33);
+    const withLongLabel = {
+      user: user.publicKey,
+      favorites: profilePda(defaultProfile),
+    };
+    await expect(
+        program.methods
+            .setFavorites(longLabel, new anchor.BN(1), "red")
+            .accountsPartial(withLongLabel)
+            .signers([user])
+            .rpc()
+    ).rejects.toThrow("LabelTooLong");
+    await expect(
+        program.methods
+            .upsertFavorites(longLabel, new anchor.BN(1), "red", null, null, null)
+            .accountsPartial(withLongLabel)
+            .signers([user])
+            .rpc()
+    ).rejects.toThrow("LabelTooLong");
+    await expect(
+        program.methods
+            .updateFavorites(longLabel, new anchor.BN(1), null, null, null, null)
+            .accountsPartial(withLongLabel)
+            .signers([user])
+            .rpc()
+    ).rejects.toThrow("LabelTooLong");
+  });
+
+  it("Keeps a history of our favorites!", async () => {
+    const user = web3.Keypair.generate();
+    const program = anchor.workspace.Favorites as Program<Favorites>;
+
+    await airdropIfRequired(
+        anchor.getProvider().connection,
+        user.publicKey,
+        0.5 * web3.LAMPORTS_PER_SOL,
+        0.5 * web3.LAMPORTS_PER_SOL
+    );
+
+    const [favoritesPda, _favoritesBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("favorites"), user.publicKey.toBuffer(), Buffer.from("work")],
+        program.programId
+    );
+    const [historyPda, _historyBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("history"), favoritesPda.toBuffer()],
+        program.programId
+    );
+    const updateFavorites = (number: number | null, color: string | null) =>
+        program.methods
+            .updateFavorites(
+                "work",
+                number === null ? null : new anchor.BN(number),
+                color,
+                null,
+                null,
+                null
+            )
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+
+    await program.methods
+        .setFavorites("work", new anchor.BN(1), "red")
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    await updateFavorites(2, null);
+    await updateFavorites(null, "blue");
+
+    let history = historyInOrder(await program.account.favoritesHistory.fetch(historyPda));
+    expect(history.map(({ number, color }) => ({ number, color }))).toEqual([
+      { number: 1, color: "#ff0000" },
+      { number: 2, color: "#ff0000" },
+      { number: 2, color: "#0000ff" },
+    ]);
+    // Slots never go backwards
+    expect(history[0].slot).toBeLessThanOrEqual(history[1].slot);
+    expect(history[1].slot).toBeLessThanOrEqual(history[2].slot);
+
+    // History keeps the color as it was stored
+    await updateFavorites(null, "rgb(0, 128, 128)");
+    history = historyInOrder(await program.account.favoritesHistory.fetch(historyPda));
+    expect(history[3].color).toEqual("#008080");
+
+    // Once the buffer is full the oldest entries make way for new ones
+    const capacity = 32;
+    for (let number = 3; number <= capacity + 2; number++) {
+      await updateFavorites(number, null);
+    }
+    const rawHistory = await program.account.favoritesHistory.fetch(historyPda);
+    expect(rawHistory.count.toNumber()).toEqual(capacity + 4);
+    history = historyInOrder(rawHistory);
+    expect(history.length).toEqual(capacity);
+    expect(history[0]).toMatchObject({ number: 3, color: "#008080" });
+    expect(history[capacity - 1]).toMatchObject({ number: capacity + 2 });
+
+    // Deleting the profile closes its history, and a new profile under the
+    // same label starts a new one
+    await program.methods
+        .deleteFavorites("work")
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+    expect(
+        await anchor.getProvider().connection.getAccountInfo(historyPda, "confirmed")
+    ).toBeNull();
+    await program.methods
+        .setFavorites("work", new anchor.BN(5), "green")
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    history = historyInOrder(await program.account.favoritesHistory.fetch(historyPda));
+    expect(history.map(({ number, color }) => ({ number, color }))).toEqual([
+      { number: 5, color: "#008000" },
+    ]);
+  }, 120 * 1000);
+
+  it("Lets a delegate update our favorites!", async () => {
+    const user = web3.Keypair.generate();
+    const delegate = web3.Keypair.generate();
+    const program = anchor.workspace.Favorites as Program<Favorites>;
+    const connection = anchor.getProvider().connection;
+
+    for (const wallet of [user, delegate]) {
+      await airdropIfRequired(
+          connection,
+          wallet.publicKey,
+          0.5 * web3.LAMPORTS_PER_SOL,
+          0.5 * web3.LAMPORTS_PER_SOL
+      );
+    }
+
+    const [favoritesPda, _favoritesBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("favorites"), user.publicKey.toBuffer()],
+        program.programId
+    );
+    const addDelegate = (expiresAt: anchor.BN | null) =>
+        program.methods
+            .addDelegate(delegate.publicKey, expiresAt)
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+    const updateAsDelegate = (number: number) =>
+        program.methods
+            .updateFavoritesAsDelegate(defaultProfile, new anchor.BN(number), null, null, null, null)
+            .accounts({
+              delegate: delegate.publicKey,
+              user: user.publicKey,
+            })
+            .signers([delegate])
+            .rpc();
+    // The cluster's clock, which is what expiries are compared against
+    const chainTime = async () => connection.getBlockTime(await connection.getSlot());
+
+    await program.methods
+        .setFavorites(defaultProfile, new anchor.BN(1), "red")
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+
+    // Not a delegate yet
+    await expect(updateAsDelegate(2)).rejects.toThrow("AccountNotInitialized");
+
+    await addDelegate(null);
+    await updateAsDelegate(2);
+    let dataFromPda = await program.account.favorites.fetch(favoritesPda);
+    expect(dataFromPda.number.toNumber()).toEqual(2);
+    expect(dataFromPda.color).toEqual("#ff0000");
+
+    // Only the user can manage delegates or delete the record: the delegate
+    // signing in their place derives other PDAs
+    await expect(
+        program.methods
+            .deleteFavorites(defaultProfile)
+            .accounts({
+              user: delegate.publicKey,
+            })
+            .signers([delegate])
+            .rpc()
+    ).rejects.toThrow();
+    expect(await connection.getAccountInfo(favoritesPda)).not.toBeNull();
+
+    // Expiries must be in the future, and stop the delegate once they pass
+    const now = await chainTime();
+    await expect(addDelegate(new anchor.BN(now - 60))).rejects.toThrow("ExpiryInThePast");
+    await addDelegate(new anchor.BN(now + 3));
+    await updateAsDelegate(3);
+    while ((await chainTime()) <= now + 3) {
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+    }
+    await expect(updateAsDelegate(4)).rejects.toThrow("DelegationExpired");
+
+    // Removed delegates can not update either
+    await addDelegate(null);
+    await program.methods
+        .removeDelegate(delegate.publicKey)
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    await expect(updateAsDelegate(5)).rejects.toThrow("AccountNotInitialized");
+
+    dataFromPda = await program.account.favorites.fetch(favoritesPda);
+    expect(dataFromPda.number.toNumber()).toEqual(3);
+  });
+
+  it("Keeps the global stats in step with our favorites!", async () => {
+    const user = web3.Keypair.generate();
+    const program = anchor.workspace.Favorites as Program<Favorites>;
+
+    await airdropIfRequired(
+        anchor.getProvider().connection,
+        user.publicKey,
+        0.5 * web3.LAMPORTS_PER_SOL,
+        0.5 * web3.LAMPORTS_PER_SOL
+    );
+
+    const [globalStatsPda, _globalStatsBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("global_stats")],
+        program.programId
+    );
+    const setFavorites = (label: string, number: number, color: string) =>
+        program.methods
+            .setFavorites(label, new anchor.BN(number), color)
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+    const deleteFavorites = (label: string) =>
+        program.methods
+            .deleteFavorites(label)
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+
+    // Colors are counted in the form they are stored in. Other tests share
+    // the stats, so we compare against where they started
+    const fetchStats = async () => {
+      const stats = await program.account.globalStats.fetch(globalStatsPda);
+      return {
+        users: stats.users.toNumber(),
+        profiles: stats.profiles.toNumber(),
+        colorCounts: colorCountsByColor(stats.colorCounts),
+        otherColors: stats.otherColors.toNumber(),
+        numberCounts: stats.numberCounts.map((count) => count.toNumber()),
+      };
+    };
+    const before = await fetchStats();
+    const expectStats = async (
+        users: number,
+        profiles: number,
+        colors: Array<string>,
+        numbers: Array<number>
+    ) => {
+      const colorCounts = { ...before.colorCounts };
+      colors.forEach((color) => (colorCounts[color] = (colorCounts[color] ?? 0) + 1));
+      const numberCounts = [...before.numberCounts];
+      numbers.forEach((number) => numberCounts[numberBucket(number)]++);
+      expect(await fetchStats()).toEqual({
+        users: before.users + users,
+        profiles: before.profiles + profiles,
+        colorCounts,
+        otherColors: before.otherColors,
+        numberCounts,
+      });
+    };
+
+    await setFavorites("work", 7, "Red");
+    await expectStats(1, 1, ["#ff0000"], [7]);
+
+    // A second profile does not make a second user. Numbers past the last
+    // bucket are counted in it.
+    await setFavorites("gaming", 1000, "blue");
+    await expectStats(1, 2, ["#ff0000", "#0000ff"], [7, 31]);
+
+    // Updates move the profile from its old buckets to its new ones
+    await program.methods
+        .updateFavorites("work", new anchor.BN(8), "green", null, null, null)
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    await expectStats(1, 2, ["#008000", "#0000ff"], [8, 31]);
+
+    await program.methods
+        .upsertFavorites("gaming", null, "  BLUE ", null, null, null)
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+    await expectStats(1, 2, ["#008000", "#0000ff"], [8, 31]);
+
+    // Deleting takes the profile out, and the user with their last one
+    await deleteFavorites("gaming");
+    await expectStats(1, 1, ["#008000"], [8]);
+    await deleteFavorites("work");
+    await expectStats(0, 0, [], []);
+  });
+
+  it("Updates any of our favorites in one go!", async () => {
+    const user = web3.Keypair.generate();
+    const program = anchor.workspace.Favorites as Program<Favorites>;
+    const connection = anchor.getProvider().connection;
+
+    await airdropIfRequired(
+        connection,
+        user.publicKey,
+        0.5 * web3.LAMPORTS_PER_SOL,
+        0.5 * web3.LAMPORTS_PER_SOL
+    );
+
+    const [favoritesPda, _favoritesBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("favorites"), user.publicKey.toBuffer()],
+        program.programId
+    );
+    const updateFavorites = (
+        number: anchor.BN | null,
+        color: string | null,
+        food: string | null,
+        hobbies: Array<string> | null,
+        tokenMint: web3.PublicKey | null
+    ) =>
+        program.methods
+            .updateFavorites(defaultProfile, number, color, food, hobbies, tokenMint)
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc();
+
+    await program.methods
+        .setFavorites(defaultProfile, new anchor.BN(7), "red")
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc();
+
+    // New profiles start out in the current layout with no extra fields
+    let dataFromPda = await program.account.favorites.fetch(favoritesPda);
+    expect(dataFromPda.version).toEqual(2);
+    expect(dataFromPda.food).toBeNull();
+    expect(dataFromPda.hobbies).toEqual([]);
+    expect(dataFromPda.tokenMint).toBeNull();
+
+    // Every field can change at once
+    const tokenMint = web3.Keypair.generate().publicKey;
+    await updateFavorites(new anchor.BN(8), "blue", "varenyky", ["chess", "climbing"], tokenMint);
+    dataFromPda = await program.account.favorites.fetch(favoritesPda);
+    expect(dataFromPda.number.toNumber()).toEqual(8);
+    expect(dataFromPda.color).toEqual("#0000ff");
+    expect(dataFromPda.food).toEqual("varenyky");
+    expect(dataFromPda.hobbies).toEqual(["chess", "climbing"]);
+    expect(dataFromPda.tokenMint.equals(tokenMint)).toBe(true);
+
+    // Or just some, leaving the rest alone
+    await updateFavorites(null, null, null, ["chess"], null);
+    dataFromPda = await program.account.favorites.fetch(favoritesPda);
+    expect(dataFromPda.hobbies).toEqual(["chess"]);
+    expect(dataFromPda.food).toEqual("varenyky");
+    expect(dataFromPda.color).toEqual("#0000ff");
+
+    // Empty values clear the optional fields, and the account shrinks to fit
+    await updateFavorites(null, null, "", [], web3.PublicKey.default);
+    dataFromPda = await program.account.favorites.fetch(favoritesPda);
+    expect(dataFromPda.food).toBeNull();
+    expect(dataFromPda.hobbies).toEqual([]);
+    expect(dataFromPda.tokenMint).toBeNull();
+    expect(dataFromPda.number.toNumber()).toEqual(8);
+    const account = await connection.getAccountInfo(favoritesPda);
+    expect(account.data.length).toEqual(8 + 8 + 4 + "#0000ff".length + 1 + 1 + 4 + 1);
+
+    await expect(updateFavorites(null, null, null, null, null)).rejects.toThrow("NothingToUpdate");
+    await expect(updateFavorites(null, null, "x".repeat(65), null, null)).rejects.toThrow("FoodTooLong");
+    await expect(
+        updateFavorites(null, null, null, ["a", "b", "c", "d", "e", "f"], null)
+    ).rejects.toThrow("TooManyHobbies");
+    await expect(
+        updateFavorites(null, null, null, ["x".repeat(33)], null)
+    ).rejects.toThrow("HobbyTooLong");
+
+    // Profiles already in the current layout have nothing to migrate
+    await expect(
+        program.methods
+            .migrateFavorites(defaultProfile)
+            .accounts({
+              user: user.publicKey,
+            })
+            .signers([user])
+            .rpc()
+    ).rejects.toThrow("FavoritesAlreadyMigrated");
+  });
+
+  it("Deletes favorites saved in the first layout, before profiles existed!", async () => {
+    const program = anchor.workspace.Favorites as Program<Favorites>;
+    const connection = anchor.getProvider().connection;
+
+    // Anchor.toml loads this user's favorites from tests/fixtures. They have
+    // no profile index and no history, and their color takes up all 50 bytes
+    // the first layout had, so they do not decode in the current layout.
+    const user = web3.Keypair.fromSecretKey(
+        Uint8Array.from(JSON.parse(readFileSync("tests/fixtures/legacy-user.json", "utf8")))
+    );
+    await airdropIfRequired(
+        connection,
+        user.publicKey,
+        0.5 * web3.LAMPORTS_PER_SOL,
+        0.5 * web3.LAMPORTS_PER_SOL
+    );
+
+    const [favoritesPda, _favoritesBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("favorites"), user.publicKey.toBuffer()],
+        program.programId
+    );
+    const [profileIndexPda, _profileIndexBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("profile_index"), user.publicKey.toBuffer()],
+        program.programId
+    );
+    const legacyColor = "the red of a fire engine, seen at dusk in the rain";
+    const legacyAccount = await connection.getAccountInfo(favoritesPda, "confirmed");
+    expect(legacyAccount!.data.length).toEqual(8 + 8 + 4 + legacyColor.length);
+    expect(legacyAccount!.data.readBigUInt64LE(8)).toEqual(BigInt(23));
+    expect(legacyAccount!.data.subarray(20).toString()).toEqual(legacyColor);
+    expect(() => program.coder.accounts.decode("favorites", legacyAccount!.data)).toThrow();
+    expect(await connection.getAccountInfo(profileIndexPda, "confirmed")).toBeNull();
+
+    const [globalStatsPda, _globalStatsBump] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("global_stats")],
+        program.programId
+    );
+    const statsBeforeDelete = await program.account.globalStats.fetch(globalStatsPda, "confirmed");
+
+    const rent = await connection.getBalance(favoritesPda, "confirmed");
+    const balanceBeforeDelete = await connection.getBalance(user.publicKey, "confirmed");
+    const tx = await program.methods
+        .deleteFavorites(defaultProfile)
+        .accounts({
+          user: user.publicKey,
+        })
+        .signers([user])
+        .rpc({ commitment: "confirmed" });
+
+    expect(await connection.getAccountInfo(favoritesPda, "confirmed")).toBeNull();
+    expect(await connection.getAccountInfo(profileIndexPda, "confirmed")).toBeNull();
+    // The stats never counted these favorites, so they are left as they were
+    expect(
+        JSON.stringify(await program.account.globalStats.fetch(globalStatsPda, "confirmed"))
+    ).toEqual(JSON.stringify(statsBeforeDelete));
+    const transaction = await connection.getTransaction(tx, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    expect(await connection.getBalance(user.publicKey, "confirmed")).toEqual(
+        balanceBeforeDelete + rent - transaction!.meta!.fee
+    );
+  });
 });
@@ -0,0 +1,17 @@
+import { BN } from "@coral-xyz/anchor";
+
+// Must match the program's NUMBER_BUCKETS
+export const NUMBER_BUCKETS = 32;
+
+export const numberBucket = (number: number): number => Math.min(number, NUMBER_BUCKETS - 1);
+
+// GlobalStats lists its color counts in no particular order. Keyed by hex
+// code they compare the same whatever the order, and sorting them gives the
+// most common colors.
+export const colorCountsByColor = (
+    colorCounts: Array<{ color: string; count: BN }>
+): Record<string, number> =>
+    colorCounts.reduce(
+        (byColor, { color, count }) => ({ ...byColor, [color]: count.toNumber() }),
+        {} as Record<string, number>
+    );